@@ -20,10 +20,13 @@ export async function activate(context: vscode.ExtensionContext) {
 		"sourcemap.ignoreGlobs",
 		"sourcemap.includeNonScripts",
 		"sourcemap.rojoProjectFile",
+		"sourcemap.roots",
 		"explorer.showClassNames",
 		"explorer.showFilePaths",
 		"explorer.iconPack",
 		"explorer.customIconDir",
+		"explorer.readOnly",
+		"explorer.protectedPaths",
 		"wally.modifyPackagesDir",
 		"wally.showPackageVersion",
 	]
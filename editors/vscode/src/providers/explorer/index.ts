@@ -77,7 +77,8 @@ export class ExplorerTreeProvider implements vscode.TreeDataProvider<ExplorerIte
 			this.explorerRoots.clear()
 			const numWorkspaces = this.servers.size
 			for (const [workspacePath, server] of this.servers) {
-				const rootInstance = await server.sendRequest("dom/root", null)
+				const rootInstances = await server.sendRequest("dom/roots", null)
+				const rootInstance = rootInstances.length === 1 ? rootInstances[0] : null
 
 				if (
 					rootInstance &&
@@ -106,25 +107,27 @@ export class ExplorerTreeProvider implements vscode.TreeDataProvider<ExplorerIte
 						idMap.set(item.domInstance.id, item)
 						items.push(item)
 					}
-				} else if (rootInstance) {
-					// Otherwise, we show the single root instance for the workspace,
-					// or single roots for multiple workspaces if we have more than one
-					const item = new ExplorerItem(
-						this.providers,
-						workspacePath,
-						rootInstance,
-						true,
-						null
+				} else if (rootInstances.length > 0) {
+					// Otherwise, we show all of the root instances for the workspace,
+					// which is usually a single root instance per workspace, unless
+					// the server was given multiple project files or sourcemaps
+					const rootItems = rootInstances.map(
+						(inst) => new ExplorerItem(this.providers, workspacePath, inst, true, null)
 					)
 
-					this.explorerRoots.set(workspacePath, item)
+					this.explorerRoots.set(
+						workspacePath,
+						rootItems.length === 1 ? rootItems[0] : rootItems
+					)
 					const idMap = this.explorerIdMaps.get(workspacePath)
 					if (idMap === undefined) {
 						throw new Error("Missing id map")
 					}
 
-					idMap.set(item.domInstance.id, item)
-					items.push(item)
+					for (const item of rootItems) {
+						idMap.set(item.domInstance.id, item)
+						items.push(item)
+					}
 				}
 			}
 		}
@@ -220,11 +223,16 @@ export class ExplorerTreeProvider implements vscode.TreeDataProvider<ExplorerIte
 				if (notif.kind === "Added") {
 					if (notif.data.parentId) {
 						this.refreshItemById(workspacePath, notif.data.parentId)
+					} else if (this.loaded.get(workspacePath) === true) {
+						// A root was added after others were loaded, show it
+						this._onDidChangeTreeData.fire(null)
 					}
 				} else if (notif.kind === "Removed") {
 					this.deleteItemById(workspacePath, notif.data.childId)
 					if (notif.data.parentId) {
 						this.refreshItemById(workspacePath, notif.data.parentId)
+					} else {
+						this._onDidChangeTreeData.fire(null)
 					}
 				} else if (notif.kind === "Changed") {
 					this.refreshItemById(workspacePath, notif.data.id)
@@ -14,7 +14,7 @@ export class QuickOpenProvider implements vscode.Disposable {
 	constructor(public readonly providers: Providers) {
 		this.picker = vscode.window.createQuickPick()
 		this.picker.canSelectMany = false
-		this.picker.placeholder = "Search..."
+		this.picker.placeholder = "Search... (use ReplicatedStorage.Door for full names, filter using is:BasePart, tag:Enemy)"
 		this.picker.title = "Quick Open"
 
 		// biome-ignore lint/suspicious/noExplicitAny: property does not yet exist in typedef
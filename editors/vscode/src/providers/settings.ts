@@ -11,10 +11,13 @@ const DEFAULT_VALUES = {
 	"explorer.showFilePaths": false,
 	"explorer.iconPack": "Vanilla2" as IconPack,
 	"explorer.customIconDir": null as string | null,
+	"explorer.readOnly": false,
+	"explorer.protectedPaths": [] as string[],
 	"sourcemap.autogenerate": true,
 	"sourcemap.ignoreGlobs": [],
 	"sourcemap.includeNonScripts": false,
 	"sourcemap.rojoProjectFile": "default.project.json",
+	"sourcemap.roots": [] as string[],
 	"wally.modifyPackagesDir": true,
 	"wally.showPackageVersion": true,
 }
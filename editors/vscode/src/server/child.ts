@@ -52,8 +52,11 @@ export const start = (
 	const settingsJson = JSON.stringify({
 		autogenerate: providers.settings.get("sourcemap.autogenerate"),
 		rojoProjectFile: providers.settings.get("sourcemap.rojoProjectFile"),
+		roots: providers.settings.get("sourcemap.roots"),
 		includeNonScripts: providers.settings.get("sourcemap.includeNonScripts"),
 		ignoreGlobs: providers.settings.get("sourcemap.ignoreGlobs"),
+		readOnly: providers.settings.get("explorer.readOnly"),
+		protectedPaths: providers.settings.get("explorer.protectedPaths"),
 	})
 
 	const command = findServerExecutable(providers.extensionContext)
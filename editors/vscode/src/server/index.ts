@@ -1,8 +1,9 @@
+import * as vscode from "vscode"
 import * as cp from "child_process"
 
 import { kill, log, start } from "./child"
 import { RpcMessage, createRpcRequest, respondToRpcMessage } from "./message"
-import { MethodTypes } from "./types"
+import { MethodTypes, ServerCapabilities } from "./types"
 import { Providers } from "../providers"
 
 export * from "./types"
@@ -13,6 +14,14 @@ type RpcHandler<M extends keyof MethodTypes> = (
 ) => MethodTypes[M]["response"]
 type RpcResolver<M extends keyof MethodTypes> = (response: MethodTypes[M]["response"]) => void
 
+/**
+	The version of the protocol that this extension speaks.
+
+	Must match the protocol version of the server, otherwise
+	requests and responses may silently be misinterpreted.
+*/
+export const PROTOCOL_VERSION = 1
+
 export class RpcServer {
 	// biome-ignore lint/suspicious/noExplicitAny:
 	private readonly handlers: Map<string, RpcHandler<any>> = new Map()
@@ -22,10 +31,28 @@ export class RpcServer {
 	private child: cp.ChildProcessWithoutNullStreams
 	private idCounter = 0
 
+	public capabilities: ServerCapabilities | undefined
+
 	constructor(public readonly providers: Providers, public readonly workspacePath: string) {
 		this.child = start(providers, workspacePath, (message) => {
 			this.onMessage(message)
 		})
+		this.initialize()
+	}
+
+	private async initialize() {
+		this.capabilities = undefined
+		const response = await this.sendRequest("server/initialize", {
+			protocolVersion: PROTOCOL_VERSION,
+		})
+		if (response) {
+			if (response.protocolVersion !== PROTOCOL_VERSION) {
+				const message = `The roblox-ui server (version ${response.serverVersion}) uses protocol version ${response.protocolVersion}, but this extension expects protocol version ${PROTOCOL_VERSION}. Please update the extension and the server to matching versions.`
+				log(`${message}\n`)
+				vscode.window.showErrorMessage(message)
+			}
+			this.capabilities = response.capabilities
+		}
 	}
 
 	public async stop() {
@@ -39,6 +66,7 @@ export class RpcServer {
 		this.child = start(this.providers, this.workspacePath, (message) => {
 			this.onMessage(message)
 		})
+		this.initialize()
 	}
 
 	public async sendRequest<M extends keyof MethodTypes>(
@@ -98,6 +126,14 @@ export class RpcServer {
 			const resolver = this.resolvers.get(message.data.id)
 			if (resolver !== undefined) {
 				this.resolvers.delete(message.data.id)
+				const error = message.data.error
+				if (error !== undefined) {
+					const errorPath = error.path !== undefined ? `\nPath: ${error.path}` : ""
+					log(
+						`Request failed!\nMethod: "${message.data.method}"\nId: ${message.data.id}\nCode: ${error.code}\nMessage: ${error.message}${errorPath}\n`
+					)
+					vscode.window.showErrorMessage(error.message)
+				}
 				resolver(message.data.value)
 			} else {
 				log(
@@ -1,9 +1,31 @@
 export type RpcMessageKind = "Request" | "Response"
 
+export type RpcErrorCode =
+	| "ParseError"
+	| "InvalidRequest"
+	| "UnknownMethod"
+	| "InvalidParams"
+	| "NotFound"
+	| "NotFileBacked"
+	| "InvalidName"
+	| "InvalidOperation"
+	| "InvalidValue"
+	| "NameCollision"
+	| "PermissionDenied"
+	| "Cancelled"
+	| "Internal"
+
+export type RpcError = {
+	code: RpcErrorCode
+	message: string
+	path?: string
+}
+
 export type RpcMessageData = {
 	id: number
 	method: string
 	value?: unknown
+	error?: RpcError
 }
 
 export type RpcMessage = {
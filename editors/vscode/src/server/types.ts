@@ -8,6 +8,8 @@ export type DomInstance = {
 	className: string
 	name: string
 	children?: string[]
+	tags?: string[]
+	attributes?: Record<string, unknown>
 	metadata?: DomInstanceMetadata
 }
 
@@ -17,6 +19,7 @@ export type DomInstanceMetadata = {
 	package?: DomInstanceMetadataPackage
 	actions?: DomInstanceMetadataActions
 	paths?: DomInstanceMetadataPaths
+	project?: DomInstanceMetadataProject
 }
 
 export type DomInstanceMetadataPackage = {
@@ -24,6 +27,11 @@ export type DomInstanceMetadataPackage = {
 	name: string
 	version: string
 	isRoot: boolean
+	realm?: WallyRealm
+	dependency?: WallyRealm
+	requirement?: string
+	resolvedVersion?: string
+	outdated?: true
 }
 
 export type DomInstanceMetadataActions = {
@@ -44,25 +52,126 @@ export type DomInstanceMetadataPaths = {
 	wallyLock?: string
 }
 
+export type DomInstanceMetadataProjectFile<T> = { path: string; error?: string } & Partial<T>
+
+export type DomInstanceMetadataProject = {
+	wally?: DomInstanceMetadataProjectFile<{
+		name?: string
+		version?: string
+		realm?: WallyRealm
+		dependencies?: Record<string, string>
+		serverDependencies?: Record<string, string>
+		devDependencies?: Record<string, string>
+	}>
+	wallyLock?: DomInstanceMetadataProjectFile<{ registry?: string; packages: string[] }>
+	aftman?: DomInstanceMetadataProjectFile<{ tools: Record<string, string> }>
+	rokit?: DomInstanceMetadataProjectFile<{ tools: Record<string, string> }>
+	selene?: DomInstanceMetadataProjectFile<{ std?: string }>
+	luaurc?: DomInstanceMetadataProjectFile<{
+		languageMode?: string
+		aliases?: Record<string, string>
+	}>
+}
+
+// Instance properties
+
+export type DomInstanceProperty = {
+	name: string
+	dataType: string
+	enumType?: string
+	enumItem?: string
+	// NOTE: Values use the explicit format from Rojo project files, eg. { Vector3: [1, 2, 3] }
+	value: Record<string, unknown>
+	isDefault?: true
+}
+
+// Server capabilities
+
+export type ServerProviderKind =
+	| "None"
+	| "FileSourcemap"
+	| "RojoSourcemap"
+	| "RojoProject"
+	| "RojoSession"
+	| "RobloxFile"
+
+export type ServerCapabilities = {
+	methods: string[]
+	providerKind: ServerProviderKind
+	mutations: boolean
+	iconPacks: string[]
+}
+
 // Request & response types
 
+export type ServerInitializeRequest = { protocolVersion: number }
+export type ServerInitializeResponse = Option<{
+	protocolVersion: number
+	serverVersion: string
+	capabilities: ServerCapabilities
+}>
+
 export type DomRootRequest = None
 export type DomRootResponse = Option<DomInstance>
 
+export type DomRootsRequest = None
+export type DomRootsResponse = DomInstance[]
+
 export type DomGetRequest = { id: string }
 export type DomGetResponse = Option<DomInstance>
 
 export type DomChildrenRequest = { id: string }
 export type DomChildrenResponse = DomInstance[]
 
+export type DomPropertiesRequest = { id: string; includeDefaults?: boolean }
+export type DomPropertiesResponse = Option<DomInstanceProperty[]>
+
 export type DomAncestorsRequest = { id: string }
 export type DomAncestorsResponse = DomInstance[]
 
 export type DomFindByPathRequest = { path: string }
 export type DomFindByPathResponse = Option<DomInstance>
 
-export type DomFindByQueryRequest = { query: string; limit: Option<number> }
-export type DomFindByQueryResponse = DomInstance[]
+export type DomFindByQueryRequest = {
+	query: string
+	limit: Option<number>
+	offset?: number
+	cursor?: string
+	withinId?: string
+	minimumScore?: number
+	skipNonFiles?: boolean
+	skipPackages?: boolean
+	className?: string
+}
+export type DomQueryResult = DomInstance & {
+	score: number
+	matches?: [number, number][]
+	cursor: string
+}
+export type DomFindByQueryResponse = DomQueryResult[]
+
+export type DomInstancePathStyle = "dot" | "findFirstChild" | "waitForChild"
+
+export type DomFindByInstancePathRequest = { path: string; scriptId?: string }
+export type DomFindByInstancePathResponse = Option<DomInstance>
+
+export type DomGetInstancePathRequest = { id: string; style?: DomInstancePathStyle }
+export type DomGetInstancePathResponse = Option<string>
+
+export type DomRequire = {
+	line: number
+	expression: string
+	instance?: DomInstance
+}
+
+export type DomRequiresRequest = { id: string }
+export type DomRequiresResponse = DomRequire[]
+
+export type DomRequiredByRequest = { id: string }
+export type DomRequiredByResponse = DomRequire[]
+
+export type DomRequireCyclesRequest = None
+export type DomRequireCyclesResponse = DomInstance[][]
 
 export type InstanceInsertRequest = { parentId: string; className: string; name: string }
 export type InstanceInsertResponse = Option<DomInstance>
@@ -70,12 +179,68 @@ export type InstanceInsertResponse = Option<DomInstance>
 export type InstanceRenameRequest = { id: string; name: string }
 export type InstanceRenameResponse = boolean
 
+export type InstanceSetPropertyRequest = { id: string; name: string; value?: unknown }
+export type InstanceSetPropertyResponse = boolean
+
 export type InstanceDeleteRequest = { id: string }
 export type InstanceDeleteResponse = boolean
 
 export type InstanceMoveRequest = { id: string; parentId: string }
 export type InstanceMoveResponse = boolean
 
+export type InstanceCopyRequest = { ids: string[] }
+export type InstanceCopyResponse = boolean
+
+export type InstancePasteRequest = { parentId: string }
+export type InstancePasteResponse = DomInstance[]
+
+export type InstanceDuplicateRequest = { id: string; parentId?: string }
+export type InstanceDuplicateResponse = Option<DomInstance>
+
+export type InstanceBatchOperation =
+	| { kind: "Insert"; data: InstanceInsertRequest }
+	| { kind: "Rename"; data: InstanceRenameRequest }
+	| { kind: "Delete"; data: InstanceDeleteRequest }
+	| { kind: "Move"; data: InstanceMoveRequest }
+	| { kind: "SetProperty"; data: InstanceSetPropertyRequest }
+
+export type InstanceBatchRequest = { operations: InstanceBatchOperation[] }
+export type InstanceBatchResponse = Option<Option<DomInstance>[]>
+
+export type InstanceUndoRequest = None
+export type InstanceUndoResponse = boolean
+
+export type InstanceRedoRequest = None
+export type InstanceRedoResponse = boolean
+
+export type WallyRealm = "shared" | "server" | "dev"
+
+export type WallyPackage = {
+	scope: string
+	name: string
+	version: string
+	realm?: WallyRealm
+	dependency?: WallyRealm
+	alias?: string
+	requirement?: string
+	resolvedVersion?: string
+	dependencies?: string[]
+	outdated?: true
+}
+
+export type WallyProject = {
+	name?: string
+	version?: string
+	realm?: WallyRealm
+	registry?: string
+	manifest?: string
+	lockfile?: string
+	packages: WallyPackage[]
+}
+
+export type PackagesListRequest = None
+export type PackagesListResponse = WallyProject[]
+
 // Notifications
 
 type DomNotificationAdded = {
@@ -92,6 +257,7 @@ type DomNotificationChanged = {
 	id: string
 	className?: string
 	name?: string
+	properties?: string[]
 }
 
 export type DomNotification =
@@ -103,6 +269,10 @@ export type DomNotification =
 // Method -> request & response type maps
 
 export type MethodTypes = {
+	"server/initialize": {
+		request: ServerInitializeRequest
+		response: ServerInitializeResponse
+	}
 	"dom/notification": {
 		request: DomNotification
 		response: None
@@ -111,6 +281,10 @@ export type MethodTypes = {
 		request: DomRootRequest
 		response: DomRootResponse
 	}
+	"dom/roots": {
+		request: DomRootsRequest
+		response: DomRootsResponse
+	}
 	"dom/get": {
 		request: DomGetRequest
 		response: DomGetResponse
@@ -119,6 +293,10 @@ export type MethodTypes = {
 		request: DomChildrenRequest
 		response: DomChildrenResponse
 	}
+	"dom/properties": {
+		request: DomPropertiesRequest
+		response: DomPropertiesResponse
+	}
 	"dom/ancestors": {
 		request: DomAncestorsRequest
 		response: DomAncestorsResponse
@@ -131,6 +309,26 @@ export type MethodTypes = {
 		request: DomFindByQueryRequest
 		response: DomFindByQueryResponse
 	}
+	"dom/findByInstancePath": {
+		request: DomFindByInstancePathRequest
+		response: DomFindByInstancePathResponse
+	}
+	"dom/getInstancePath": {
+		request: DomGetInstancePathRequest
+		response: DomGetInstancePathResponse
+	}
+	"dom/requires": {
+		request: DomRequiresRequest
+		response: DomRequiresResponse
+	}
+	"dom/requiredBy": {
+		request: DomRequiredByRequest
+		response: DomRequiredByResponse
+	}
+	"dom/requireCycles": {
+		request: DomRequireCyclesRequest
+		response: DomRequireCyclesResponse
+	}
 	"instance/insert": {
 		request: InstanceInsertRequest
 		response: InstanceInsertResponse
@@ -139,6 +337,10 @@ export type MethodTypes = {
 		request: InstanceRenameRequest
 		response: InstanceRenameResponse
 	}
+	"instance/setProperty": {
+		request: InstanceSetPropertyRequest
+		response: InstanceSetPropertyResponse
+	}
 	"instance/delete": {
 		request: InstanceDeleteRequest
 		response: InstanceDeleteResponse
@@ -147,4 +349,32 @@ export type MethodTypes = {
 		request: InstanceMoveRequest
 		response: InstanceMoveResponse
 	}
+	"instance/copy": {
+		request: InstanceCopyRequest
+		response: InstanceCopyResponse
+	}
+	"instance/paste": {
+		request: InstancePasteRequest
+		response: InstancePasteResponse
+	}
+	"instance/duplicate": {
+		request: InstanceDuplicateRequest
+		response: InstanceDuplicateResponse
+	}
+	"instance/batch": {
+		request: InstanceBatchRequest
+		response: InstanceBatchResponse
+	}
+	"instance/undo": {
+		request: InstanceUndoRequest
+		response: InstanceUndoResponse
+	}
+	"instance/redo": {
+		request: InstanceRedoRequest
+		response: InstanceRedoResponse
+	}
+	"packages/list": {
+		request: PackagesListRequest
+		response: PackagesListResponse
+	}
 }
@@ -2,17 +2,25 @@ use anyhow::Result;
 use clap::Parser;
 use tracing::debug;
 
-use crate::server::{Config, Server};
+use crate::server::{Config, ListenAddress, RpcProtocol, Server};
 
 #[derive(Debug, Clone, Parser)]
 pub struct ServeCommand {
     #[arg(long, env)]
     pub settings: Option<Config>,
+    /// The protocol to use for messages sent to and from clients
+    #[arg(long, value_enum, default_value_t)]
+    pub protocol: RpcProtocol,
+    /// Listen for clients on a TCP loopback address or Unix socket path, instead of using stdio
+    #[arg(long, value_name = "ADDRESS")]
+    pub listen: Option<ListenAddress>,
 }
 
 impl ServeCommand {
     pub async fn run(self) -> Result<()> {
-        let config = self.settings.unwrap_or_default();
+        let mut config = self.settings.unwrap_or_default();
+        config.protocol = self.protocol;
+        config.listen = self.listen;
 
         debug!("Parsed arguments\nconfig: {config:#?}");
 
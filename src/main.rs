@@ -6,7 +6,9 @@ mod reflection;
 mod server;
 mod util;
 
-#[tokio::main(flavor = "current_thread")]
+// NOTE: We use a couple of worker threads so that long-running requests,
+// such as queries, do not block reading and responding to other requests
+#[tokio::main(flavor = "multi_thread", worker_threads = 2)]
 async fn main() -> anyhow::Result<()> {
     rayon::ThreadPoolBuilder::new()
         .num_threads(4)
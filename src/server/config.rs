@@ -3,10 +3,17 @@ use std::{
     str::FromStr,
 };
 
+use globset::{GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
 use once_cell::sync::Lazy;
-use serde::Deserialize;
+use serde::{de::Error as _, Deserialize, Deserializer};
 
-use crate::util::path::make_absolute_and_clean;
+use super::{dom::InstanceMetadataProject, ListenAddress, RpcProtocol};
+#[cfg(test)]
+use crate::util::path::TestDir;
+use crate::util::{
+    path::{make_absolute_and_clean, make_relative_to_cwd},
+    rojo::{is_roblox_model_path, is_roblox_place_path},
+};
 
 /**
     Configuration for the instance server.
@@ -15,31 +22,70 @@ use crate::util::path::make_absolute_and_clean;
     from a string, but have some defaults that may be surprising:
 
     - `autogenerate` defaults to `true`
+    - `read_only` defaults to `false`
     - `include_non_scripts` defaults to `true`
     - `rojo_project_file` defaults to `default.project.json` in the current directory
     - `sourcemap_file` defaults to `sourcemap.json` in the current directory
+    - `roots` defaults to a single root using `rojo_project_file` and `sourcemap_file`
+
+    Roots may be given as paths or glob patterns, such as `*.project.json`, and are
+    expanded once when the config is parsed, skipping any paths that match `ignore_globs`. The `rojo_project_file` and `sourcemap_file`
+    fields always refer to the files of the first root, and are replaced by the files
+    of a specific root in any config returned from [`Config::for_root`].
+
+    Roots may also be standalone place or model files (`.rbxl`, `.rbxlx`, `.rbxm`, `.rbxmx`),
+    which are set as the `roblox_file`, and never use a project file or sourcemap.
+
+    The `protocol` and `listen` address are never deserialized, and are instead set using command line arguments.
+
+    The `trash_dir` is never deserialized either, and is a directory inside of the temporary
+    directory for this process, where files for deleted instances are kept until they can no
+    longer be restored using undo.
 */
 #[derive(Debug, Clone)]
 pub struct Config {
     pub autogenerate: bool,
     pub rojo_project_file: PathBuf,
     pub sourcemap_file: PathBuf,
+    pub roblox_file: Option<PathBuf>,
+    pub roots: Vec<ConfigRoot>,
+    pub read_only: bool,
+    pub protected_paths: ProtectedPaths,
+    pub protocol: RpcProtocol,
+    pub listen: Option<ListenAddress>,
+    pub trash_dir: PathBuf,
 }
 
 impl Config {
-    pub fn is_sourcemap_path(&self, path: &Path) -> bool {
-        let abs_path = make_absolute_and_clean(path);
-        abs_path == self.sourcemap_file
+    /**
+        Returns a copy of this config for the root at the given index,
+        with the project, sourcemap, and roblox files set to the ones of that root.
+    */
+    pub fn for_root(&self, index: usize) -> Self {
+        let mut this = self.clone();
+        if let Some(root) = self.roots.get(index) {
+            this.rojo_project_file = root.rojo_project_file.clone();
+            this.sourcemap_file = root.sourcemap_file.clone();
+            this.roblox_file = root.roblox_file.clone();
+        }
+        this
     }
 
-    pub fn is_rojo_project_path(&self, path: &Path) -> bool {
-        let abs_path = make_absolute_and_clean(path);
-        abs_path == self.rojo_project_file
+    /**
+        Checks if the file or folder at the given path may be modified.
+    */
+    pub fn is_writable_path(&self, path: &Path) -> bool {
+        !self.read_only && !self.protected_paths.is_protected(path)
     }
 
-    pub fn paths_to_watch(&self) -> Vec<&Path> {
-        if self.autogenerate {
-            vec![
+    pub fn paths_to_watch(&self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        for root in &self.roots {
+            if let Some(roblox_file) = &root.roblox_file {
+                paths.push(roblox_file.clone());
+                continue;
+            }
+            if self.autogenerate {
                 /*
                     NOTE: Order here is important! We should put the project
                     file first, since during initialization these paths and their
@@ -48,11 +94,104 @@ impl Config {
                     The rojo project provider will take precedence and ensure we never
                     use the sourcemap, and emit an initial massive instance tree diff.
                 */
-                self.rojo_project_file.as_ref(),
-                self.sourcemap_file.as_ref(),
-            ]
+                paths.push(root.rojo_project_file.clone());
+            }
+            paths.push(root.sourcemap_file.clone());
+            paths.extend(root.project_file_paths());
+        }
+        paths
+    }
+}
+
+/**
+    A single root in the instance dom, which has its own project
+    file and sourcemap file, or place / model file, and instance provider.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigRoot {
+    pub rojo_project_file: PathBuf,
+    pub sourcemap_file: PathBuf,
+    pub roblox_file: Option<PathBuf>,
+}
+
+impl ConfigRoot {
+    pub fn is_sourcemap_path(&self, path: &Path) -> bool {
+        let abs_path = make_absolute_and_clean(path);
+        self.roblox_file.is_none() && abs_path == self.sourcemap_file
+    }
+
+    pub fn is_rojo_project_path(&self, path: &Path) -> bool {
+        let abs_path = make_absolute_and_clean(path);
+        self.roblox_file.is_none() && abs_path == self.rojo_project_file
+    }
+
+    pub fn is_roblox_file_path(&self, path: &Path) -> bool {
+        let abs_path = make_absolute_and_clean(path);
+        self.roblox_file.as_ref() == Some(&abs_path)
+    }
+
+    /**
+        Gets the paths to all project files for this root, such as `wally.toml`,
+        which are next to the project file, or none for place / model files.
+    */
+    pub fn project_file_paths(&self) -> Vec<PathBuf> {
+        match self.rojo_project_file.parent() {
+            Some(dir) if self.roblox_file.is_none() => InstanceMetadataProject::FILE_NAMES
+                .iter()
+                .map(|file_name| dir.join(file_name))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn is_project_file_path(&self, path: &Path) -> bool {
+        let abs_path = make_absolute_and_clean(path);
+        self.project_file_paths().contains(&abs_path)
+    }
+
+    /**
+        Creates a root from a path to a project file, a sourcemap file, a
+        directory containing either of those files, or a place / model file.
+
+        Any file that is not given is assumed to have the
+        default file name, in the same directory as the other.
+    */
+    fn from_path(path: &Path) -> Self {
+        let path = make_absolute_and_clean(path);
+        let file_name = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .unwrap_or_default();
+        let is_roblox_file = is_roblox_place_path(&path) || is_roblox_model_path(&path);
+        let dir = if path.is_dir() || !(file_name.ends_with(".json") || is_roblox_file) {
+            path.clone()
         } else {
-            vec![self.sourcemap_file.as_ref()]
+            path.parent().map(Path::to_path_buf).unwrap_or_default()
+        };
+        if is_roblox_file {
+            Self {
+                rojo_project_file: dir.join("default.project.json"),
+                sourcemap_file: dir.join("sourcemap.json"),
+                roblox_file: Some(path.clone()),
+            }
+        } else if file_name.ends_with(".project.json") {
+            Self {
+                rojo_project_file: path.clone(),
+                sourcemap_file: dir.join("sourcemap.json"),
+                roblox_file: None,
+            }
+        } else if dir == path {
+            Self {
+                rojo_project_file: dir.join("default.project.json"),
+                sourcemap_file: dir.join("sourcemap.json"),
+                roblox_file: None,
+            }
+        } else {
+            Self {
+                rojo_project_file: dir.join("default.project.json"),
+                sourcemap_file: path.clone(),
+                roblox_file: None,
+            }
         }
     }
 }
@@ -65,10 +204,38 @@ impl Default for Config {
 
 impl From<ConfigDeserializable> for Config {
     fn from(value: ConfigDeserializable) -> Self {
+        // NOTE: Roots that did not match any files fall back to the default
+        // root, the same as if no roots were given, so we always have one
+        let roots = value
+            .roots
+            .map(|roots| roots.expand(&value.ignore_globs))
+            .unwrap_or_default();
+        let roots = match roots {
+            roots if !roots.is_empty() => roots,
+            _ => vec![ConfigRoot {
+                rojo_project_file: value
+                    .rojo_project_file
+                    .clone()
+                    .expect("missing rojo_project_file"),
+                sourcemap_file: value
+                    .sourcemap_file
+                    .clone()
+                    .expect("missing sourcemap_file"),
+                roblox_file: None,
+            }],
+        };
+        let first = roots[0].clone();
         Self {
             autogenerate: value.autogenerate,
-            rojo_project_file: value.rojo_project_file.expect("missing rojo_project_file"),
-            sourcemap_file: value.sourcemap_file.expect("missing sourcemap_file"),
+            rojo_project_file: first.rojo_project_file,
+            sourcemap_file: first.sourcemap_file,
+            roblox_file: first.roblox_file,
+            roots,
+            read_only: value.read_only,
+            protected_paths: value.protected_paths,
+            protocol: RpcProtocol::default(),
+            listen: None,
+            trash_dir: std::env::temp_dir().join(format!("roblox-ui-trash-{}", std::process::id())),
         }
     }
 }
@@ -101,7 +268,11 @@ fn parse_config() {
         "autogenerate": true,
         "ignoreNonScripts": false,
         "rojoProjectFile": "default.project.json",
-        "sourcemapFile": "sourcemap.json"
+        "sourcemapFile": "sourcemap.json",
+        "roots": ["default.project.json", "packages/*/*.project.json"],
+        "ignoreGlobs": ["**/node_modules/**"],
+        "readOnly": false,
+        "protectedPaths": ["Packages/**", "*.lock"]
     }
     "#;
     assert!("".parse::<Config>().is_ok());
@@ -110,6 +281,97 @@ fn parse_config() {
     assert!("{}".parse::<Config>().is_ok());
     assert!("'{}'".parse::<Config>().is_ok());
     assert!(full_conf.parse::<Config>().is_ok());
+    assert!(r#"{ "protectedPaths": ["[invalid"] }"#.parse::<Config>().is_err());
+    assert!(r#"{ "roots": ["[invalid"] }"#.parse::<Config>().is_err());
+    assert!(r#"{ "ignoreGlobs": ["[invalid"] }"#.parse::<Config>().is_err());
+}
+
+#[test]
+fn config_roots() {
+    let proj = ConfigRoot::from_path(Path::new("game/default.project.json"));
+    assert_eq!(
+        proj.rojo_project_file,
+        make_absolute_and_clean("game/default.project.json")
+    );
+    assert_eq!(
+        proj.sourcemap_file,
+        make_absolute_and_clean("game/sourcemap.json")
+    );
+
+    let smap = ConfigRoot::from_path(Path::new("plugin/sourcemap.json"));
+    assert_eq!(
+        smap.rojo_project_file,
+        make_absolute_and_clean("plugin/default.project.json")
+    );
+    assert_eq!(
+        smap.sourcemap_file,
+        make_absolute_and_clean("plugin/sourcemap.json")
+    );
+
+    let place = ConfigRoot::from_path(Path::new("places/Game.rbxl"));
+    assert_eq!(
+        place.roblox_file,
+        Some(make_absolute_and_clean("places/Game.rbxl"))
+    );
+    assert!(!place.is_sourcemap_path(Path::new("places/sourcemap.json")));
+
+    let dir = ConfigRoot::from_path(Path::new("packages/thing"));
+    assert_eq!(
+        dir.rojo_project_file,
+        make_absolute_and_clean("packages/thing/default.project.json")
+    );
+
+    let conf = r#"{ "roots": ["a.project.json", "b/sourcemap.json"] }"#
+        .parse::<Config>()
+        .unwrap();
+    assert_eq!(conf.roots.len(), 2);
+    assert_eq!(
+        conf.rojo_project_file,
+        make_absolute_and_clean("a.project.json")
+    );
+    assert_eq!(
+        conf.for_root(1).sourcemap_file,
+        make_absolute_and_clean("b/sourcemap.json")
+    );
+
+    // Globs that match nothing fall back to the default root
+    let conf = r#"{ "roots": ["does/not/exist/**/*.project.json"] }"#
+        .parse::<Config>()
+        .unwrap();
+    assert_eq!(conf.roots.len(), 1);
+    assert_eq!(
+        conf.rojo_project_file,
+        DEFAULT_ROJO_PROJECT_PATH.to_path_buf()
+    );
+}
+
+#[test]
+fn config_root_globs() {
+    let dir = TestDir::new("config-roots");
+    for path in [
+        "game/default.project.json",
+        "ignored/default.project.json",
+        "node_modules/thing/default.project.json",
+        "Packages/_Index/x/default.project.json",
+    ] {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "{}").unwrap();
+    }
+
+    // Package and node_modules directories are never searched, and ignored paths are skipped
+    let conf = serde_json::json!({
+        "roots": [format!("{}/**/*.project.json", dir.display())],
+        "ignoreGlobs": [format!("{}/ignored/**", dir.display())],
+    })
+    .to_string()
+    .parse::<Config>()
+    .unwrap();
+    assert_eq!(conf.roots.len(), 1);
+    assert_eq!(
+        conf.rojo_project_file,
+        dir.join("game/default.project.json")
+    );
 }
 
 /**
@@ -123,6 +385,10 @@ struct ConfigDeserializable {
     autogenerate: bool,
     rojo_project_file: Option<PathBuf>,
     sourcemap_file: Option<PathBuf>,
+    roots: Option<ConfigRoots>,
+    ignore_globs: IgnoreGlobs,
+    read_only: bool,
+    protected_paths: ProtectedPaths,
 }
 
 impl ConfigDeserializable {
@@ -145,6 +411,10 @@ impl Default for ConfigDeserializable {
             autogenerate: true,
             rojo_project_file: None,
             sourcemap_file: None,
+            roots: None,
+            ignore_globs: IgnoreGlobs::default(),
+            read_only: false,
+            protected_paths: ProtectedPaths::default(),
         };
         this.apply_path_defaults_and_clean();
         this
@@ -160,3 +430,186 @@ static DEFAULT_ROJO_PROJECT_PATH: Lazy<PathBuf> = Lazy::new(|| {
     let path = PathBuf::from("default.project.json");
     make_absolute_and_clean(path)
 });
+
+/**
+    A set of glob patterns for files and folders that must never be modified.
+
+    Patterns are matched against paths relative to the current directory, and a
+    path is protected if either the path itself or any of its ancestors match.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ProtectedPaths {
+    globs: GlobSet,
+}
+
+impl ProtectedPaths {
+    pub fn is_protected(&self, path: &Path) -> bool {
+        if self.globs.is_empty() {
+            return false;
+        }
+        let relative = make_relative_to_cwd(path);
+        relative
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .any(|ancestor| self.globs.is_match(ancestor))
+    }
+}
+
+impl<'de> Deserialize<'de> for ProtectedPaths {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let globs = deserialize_glob_set(deserializer)?;
+        Ok(Self { globs })
+    }
+}
+
+/**
+    A set of glob patterns for files and folders that should never be used as roots.
+
+    Patterns are matched against paths relative to the current directory.
+*/
+#[derive(Debug, Clone, Default)]
+struct IgnoreGlobs {
+    globs: GlobSet,
+}
+
+impl IgnoreGlobs {
+    fn is_ignored(&self, relative_path: &Path) -> bool {
+        self.globs.is_match(relative_path)
+    }
+}
+
+impl<'de> Deserialize<'de> for IgnoreGlobs {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let globs = deserialize_glob_set(deserializer)?;
+        Ok(Self { globs })
+    }
+}
+
+fn deserialize_glob_set<'de, D: Deserializer<'de>>(deserializer: D) -> Result<GlobSet, D::Error> {
+    let patterns = Vec::<String>::deserialize(deserializer)?;
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(D::Error::custom)?;
+        builder.add(glob);
+    }
+    builder.build().map_err(D::Error::custom)
+}
+
+/**
+    A list of roots, given as paths and glob patterns, which are expanded using [`ConfigRoots::expand`].
+
+    Glob patterns are matched against paths relative to the current directory, and
+    hidden directories (starting with a `.`), Wally package indexes (`_Index`), and
+    `node_modules` directories are never searched, since they may contain many
+    project files that are not meant to be used as roots.
+*/
+#[derive(Debug, Clone, Default)]
+struct ConfigRoots(Vec<ConfigRootPattern>);
+
+#[derive(Debug, Clone)]
+enum ConfigRootPattern {
+    Path(PathBuf),
+    Glob(PathBuf, GlobMatcher),
+}
+
+impl ConfigRoots {
+    /**
+        Expands all paths and glob patterns into roots, skipping any ignored paths.
+    */
+    fn expand(&self, ignore_globs: &IgnoreGlobs) -> Vec<ConfigRoot> {
+        let mut roots = Vec::new();
+        for pattern in &self.0 {
+            let paths = match pattern {
+                ConfigRootPattern::Path(path) => vec![path.clone()],
+                ConfigRootPattern::Glob(base_dir, glob) => {
+                    let mut paths = Vec::new();
+                    find_matching_paths(base_dir, glob, ignore_globs, &mut paths);
+                    paths.sort();
+                    paths
+                }
+            };
+            for path in paths {
+                let root = ConfigRoot::from_path(&path);
+                if !roots.contains(&root) {
+                    roots.push(root);
+                }
+            }
+        }
+        roots
+    }
+}
+
+impl<'de> Deserialize<'de> for ConfigRoots {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let patterns = Vec::<String>::deserialize(deserializer)?;
+        let mut roots = Vec::new();
+        for pattern in patterns {
+            if is_glob_pattern(&pattern) {
+                let glob = GlobBuilder::new(&pattern)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(D::Error::custom)?
+                    .compile_matcher();
+                roots.push(ConfigRootPattern::Glob(glob_base_dir(&pattern), glob));
+            } else {
+                roots.push(ConfigRootPattern::Path(PathBuf::from(pattern)));
+            }
+        }
+        Ok(Self(roots))
+    }
+}
+
+fn is_glob_pattern(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+/**
+    Gets the directory that all paths matching the given pattern must be
+    in, meaning all of the leading path components that are not globs.
+*/
+fn glob_base_dir(pattern: &str) -> PathBuf {
+    let mut dir = PathBuf::new();
+    for component in Path::new(pattern).components() {
+        if component.as_os_str().to_str().is_some_and(is_glob_pattern) {
+            break;
+        }
+        dir.push(component);
+    }
+    if dir.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        dir
+    }
+}
+
+const SKIPPED_DIR_NAMES: &[&str] = &["_Index", "node_modules"];
+
+fn find_matching_paths(
+    dir: &Path,
+    glob: &GlobMatcher,
+    ignore_globs: &IgnoreGlobs,
+    paths: &mut Vec<PathBuf>,
+) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let relative = make_relative_to_cwd(&path);
+        if ignore_globs.is_ignored(&relative) {
+            continue;
+        }
+        let is_skipped = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|n| n.starts_with('.') || SKIPPED_DIR_NAMES.contains(&n));
+        if glob.is_match(&relative) {
+            paths.push(relative);
+        } else if !is_skipped && entry.file_type().is_ok_and(|t| t.is_dir()) {
+            find_matching_paths(&path, glob, ignore_globs, paths);
+        }
+    }
+}
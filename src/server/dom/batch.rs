@@ -0,0 +1,538 @@
+use rbx_dom_weak::types::Ref;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    journal::{discard_operations, JournalOperation},
+    Dom, DomError, DomErrorKind, DomNotification, DomResult,
+};
+
+/**
+    A single operation in a batch of operations, see [`Dom::apply_batch`].
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", content = "data")]
+pub enum DomBatchOperation {
+    #[serde(rename_all = "camelCase")]
+    Insert {
+        parent_id: Ref,
+        class_name: String,
+        name: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Rename { id: Ref, name: String },
+    #[serde(rename_all = "camelCase")]
+    Delete { id: Ref },
+    #[serde(rename_all = "camelCase")]
+    Move { id: Ref, parent_id: Ref },
+    #[serde(rename_all = "camelCase")]
+    SetProperty {
+        id: Ref,
+        name: String,
+        value: Option<JsonValue>,
+    },
+}
+
+/**
+    Coalesces notifications emitted during a batch of operations.
+
+    - Instances that were both added and removed are never emitted
+    - Changes for the same instance are merged into a single notification
+*/
+fn coalesce_notifications(notifications: Vec<DomNotification>) -> Vec<DomNotification> {
+    let mut coalesced: Vec<DomNotification> = Vec::new();
+    let mut added = HashSet::default();
+
+    for notification in notifications {
+        match notification {
+            DomNotification::Added { child_id, .. } => {
+                added.insert(child_id);
+                coalesced.push(notification);
+            }
+            DomNotification::Removed { child_id, .. } if added.remove(&child_id) => {
+                coalesced.retain(|n| match n {
+                    DomNotification::Added { child_id: id, .. } => *id != child_id,
+                    DomNotification::Changed { id, .. } => *id != child_id,
+                    DomNotification::Removed { .. } => true,
+                });
+            }
+            DomNotification::Removed { .. } => coalesced.push(notification),
+            DomNotification::Changed {
+                id,
+                class_name,
+                name,
+                properties,
+            } => {
+                let existing = coalesced.iter_mut().find_map(|n| match n {
+                    DomNotification::Changed {
+                        id: existing_id,
+                        class_name: existing_class_name,
+                        name: existing_name,
+                        properties: existing_properties,
+                    } if *existing_id == id => {
+                        Some((existing_class_name, existing_name, existing_properties))
+                    }
+                    _ => None,
+                });
+                if let Some((existing_class_name, existing_name, existing_properties)) = existing {
+                    if class_name.is_some() {
+                        *existing_class_name = class_name;
+                    }
+                    if name.is_some() {
+                        *existing_name = name;
+                    }
+                    if let Some(properties) = properties {
+                        let existing_properties = existing_properties.get_or_insert_with(Vec::new);
+                        for property in properties {
+                            if !existing_properties.contains(&property) {
+                                existing_properties.push(property);
+                            }
+                        }
+                    }
+                } else {
+                    coalesced.push(DomNotification::Changed {
+                        id,
+                        class_name,
+                        name,
+                        properties,
+                    });
+                }
+            }
+        }
+    }
+
+    coalesced
+}
+
+/**
+    Changes that operations validated so far in a batch will make,
+    used to find problems with later operations before applying any.
+*/
+#[derive(Debug, Default)]
+struct BatchPlan {
+    deleted: HashSet<Ref>,
+    renamed: HashMap<Ref, String>,
+    moved: HashMap<Ref, Ref>,
+    inserted: HashSet<(Ref, String)>,
+}
+
+impl Dom {
+    fn is_or_descends_from(&self, id: Ref, ancestors: &HashSet<Ref>) -> bool {
+        let mut current = Some(id);
+        while let Some(current_id) = current.take() {
+            if ancestors.contains(&current_id) {
+                return true;
+            }
+            if let Some(inst) = self.get_instance(current_id) {
+                if inst.parent().is_some() {
+                    current = Some(inst.parent());
+                }
+            }
+        }
+        false
+    }
+
+    fn ensure_not_deleted(&self, id: Ref, deleted: &HashSet<Ref>) -> DomResult<()> {
+        if self.is_or_descends_from(id, deleted) {
+            Err(DomError::new(
+                DomErrorKind::InvalidOperation,
+                "Instance is deleted earlier in the batch",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn planned_name<'a>(&'a self, plan: &'a BatchPlan, id: Ref) -> Option<&'a str> {
+        match plan.renamed.get(&id) {
+            Some(name) => Some(name.as_str()),
+            None => self.get_instance(id).map(|inst| inst.name.as_str()),
+        }
+    }
+
+    /**
+        Checks that no child of the given parent, other than the instance being
+        renamed or moved, will be using the given name at this point in the batch.
+    */
+    fn ensure_unique_planned_name(
+        &self,
+        plan: &BatchPlan,
+        parent: Ref,
+        name: &str,
+        id: Option<Ref>,
+    ) -> DomResult<()> {
+        let parent_inst = self.get_instance(parent).ok_or_else(DomError::not_found)?;
+        let current_children = parent_inst
+            .children()
+            .iter()
+            .filter(|child_id| plan.moved.get(child_id).is_none_or(|p| *p == parent));
+        let moved_children = plan
+            .moved
+            .iter()
+            .filter(|(_, moved_parent)| **moved_parent == parent)
+            .map(|(child_id, _)| child_id);
+        let collides = current_children
+            .chain(moved_children)
+            .filter(|child_id| Some(**child_id) != id && !plan.deleted.contains(child_id))
+            .any(|child_id| self.planned_name(plan, *child_id) == Some(name))
+            || plan.inserted.contains(&(parent, name.to_string()));
+        if collides {
+            Err(DomError::new(
+                DomErrorKind::NameCollision,
+                format!("An instance named '{name}' already exists"),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /**
+        Checks that all operations in a batch refer to existing instances that are backed
+        by files, that no operation refers to an instance deleted earlier in the batch,
+        and that no two instances with the same parent will end up with the same name.
+    */
+    fn validate_batch(&self, operations: &[DomBatchOperation]) -> DomResult<()> {
+        let mut plan = BatchPlan::default();
+        for operation in operations {
+            match operation {
+                DomBatchOperation::Insert {
+                    parent_id, name, ..
+                } => {
+                    self.get_writable_paths(*parent_id, Some(name))?;
+                    self.ensure_not_deleted(*parent_id, &plan.deleted)?;
+                    self.ensure_unique_planned_name(&plan, *parent_id, name, None)?;
+                    plan.inserted.insert((*parent_id, name.clone()));
+                }
+                DomBatchOperation::Rename { id, name } => {
+                    self.get_writable_paths(*id, None)?;
+                    self.ensure_not_deleted(*id, &plan.deleted)?;
+                    let parent_id = match plan.moved.get(id) {
+                        Some(parent_id) => *parent_id,
+                        None => self
+                            .get_instance(*id)
+                            .ok_or_else(DomError::not_found)?
+                            .parent(),
+                    };
+                    self.ensure_unique_planned_name(&plan, parent_id, name, Some(*id))?;
+                    plan.renamed.insert(*id, name.clone());
+                }
+                DomBatchOperation::Delete { id } => {
+                    self.get_writable_paths(*id, None)?;
+                    self.ensure_not_deleted(*id, &plan.deleted)?;
+                    plan.deleted.insert(*id);
+                }
+                DomBatchOperation::Move { id, parent_id } => {
+                    self.get_writable_paths(*id, None)?;
+                    let name = self.planned_name(&plan, *id).unwrap_or_default();
+                    self.get_writable_paths(*parent_id, Some(name))?;
+                    self.ensure_not_deleted(*id, &plan.deleted)?;
+                    self.ensure_not_deleted(*parent_id, &plan.deleted)?;
+                    let moved = [*id].into_iter().collect::<HashSet<_>>();
+                    if self.is_or_descends_from(*parent_id, &moved) {
+                        return Err(DomError::new(
+                            DomErrorKind::InvalidOperation,
+                            "Can not move an instance into itself",
+                        ));
+                    }
+                    self.ensure_unique_planned_name(&plan, *parent_id, name, Some(*id))?;
+                    plan.moved.insert(*id, *parent_id);
+                }
+                DomBatchOperation::SetProperty { id, name, value } => {
+                    self.get_file_paths(*id)?;
+                    self.ensure_not_deleted(*id, &plan.deleted)?;
+                    if let Some(value) = value {
+                        self.resolve_instance_property(*id, name, value)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn apply_batch_operation(
+        &mut self,
+        operation: DomBatchOperation,
+        reversed: &mut Vec<JournalOperation>,
+    ) -> DomResult<Option<Ref>> {
+        match operation {
+            DomBatchOperation::Insert {
+                parent_id,
+                class_name,
+                name,
+            } => {
+                let (id, changed_parent) = self
+                    .insert_instance_inner(parent_id, class_name, name)
+                    .await?;
+                reversed.push(JournalOperation::Remove {
+                    id,
+                    collapse_parent: changed_parent,
+                });
+                Ok(Some(id))
+            }
+            DomBatchOperation::Rename { id, name } => {
+                let previous_name = self.rename_instance_inner(id, name).await?;
+                reversed.push(JournalOperation::Rename {
+                    id,
+                    name: previous_name,
+                });
+                Ok(Some(id))
+            }
+            DomBatchOperation::Delete { id } => {
+                let (parent_id, trashed) = self.delete_instance_inner(id).await?;
+                reversed.push(JournalOperation::Insert { parent_id, trashed });
+                Ok(None)
+            }
+            DomBatchOperation::Move { id, parent_id } => {
+                let (previous_parent_id, changed_parent) =
+                    self.move_instance_inner(id, parent_id).await?;
+                if previous_parent_id != parent_id {
+                    reversed.push(JournalOperation::Move {
+                        id,
+                        parent_id: previous_parent_id,
+                        collapse_parent: changed_parent,
+                    });
+                }
+                Ok(Some(id))
+            }
+            DomBatchOperation::SetProperty { id, name, value } => {
+                let previous = self.set_property_inner(id, name.clone(), value).await?;
+                reversed.push(JournalOperation::SetProperty {
+                    id,
+                    name,
+                    value: previous,
+                });
+                Ok(Some(id))
+            }
+        }
+    }
+
+    /**
+        Applies a batch of operations, in order, as a single change.
+
+        All operations are validated before any of them are applied, and if any operation
+        fails or the batch is cancelled using the given token, all previously applied
+        operations are rolled back. The whole batch can be undone and redone as a single change.
+
+        Returns the resulting instance id for each operation, which is
+        `None` for deletions, or the error that caused the batch to fail.
+    */
+    pub async fn apply_batch(
+        &mut self,
+        operations: Vec<DomBatchOperation>,
+        cancel: &CancellationToken,
+    ) -> DomResult<Vec<Option<Ref>>> {
+        self.validate_batch(&operations)?;
+
+        self.notification_buffer.replace(Vec::new());
+
+        let mut results = Vec::new();
+        let mut reversed = Vec::new();
+        let mut error = None;
+        for operation in operations {
+            if cancel.is_cancelled() {
+                error.replace(DomError::cancelled());
+                break;
+            }
+            match self.apply_batch_operation(operation, &mut reversed).await {
+                Ok(result) => results.push(result),
+                Err(e) => {
+                    error.replace(e);
+                    break;
+                }
+            }
+            // NOTE: Operations may finish without ever yielding, so we yield here
+            // to make sure that a request to cancel the batch can be handled
+            tokio::task::yield_now().await;
+        }
+
+        if error.is_some() {
+            let (discarded, failed, rolled_back) = self.apply_operations(reversed).await;
+            if let Err(e) = rolled_back {
+                tracing::error!("failed to fully roll back batch operations: {e}");
+            }
+            discard_operations(discarded);
+            // NOTE: Deleted instances that could not be restored are still in
+            // the trash, recording them makes it possible to restore them later
+            self.journal.record(failed);
+        } else {
+            self.journal.record(reversed);
+        }
+
+        let notifications = self.notification_buffer.take().unwrap_or_default();
+        for notification in coalesce_notifications(notifications) {
+            self.notify(notification);
+        }
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(results),
+        }
+    }
+}
+
+#[tokio::test]
+async fn validate_batch_names() {
+    use DomBatchOperation::*;
+
+    let (mut dom, _dir, module_id) = Dom::for_test("batch-names");
+    let root_id = dom.get_root_id().unwrap();
+    let insert = |name: &str| Insert {
+        parent_id: root_id,
+        class_name: "ModuleScript".to_string(),
+        name: name.to_string(),
+    };
+    let rename = |name: &str| Rename {
+        id: module_id,
+        name: name.to_string(),
+    };
+    let collides = |dom: &Dom, operations: Vec<DomBatchOperation>| {
+        dom.validate_batch(&operations)
+            .is_err_and(|e| e.kind() == DomErrorKind::NameCollision)
+    };
+
+    // Names that clash within the batch, or with existing siblings
+    assert!(collides(&dom, vec![insert("A"), insert("A")]));
+    assert!(collides(&dom, vec![insert("A"), rename("A")]));
+    assert!(collides(&dom, vec![rename("A"), insert("A")]));
+    assert!(collides(&dom, vec![insert("Module")]));
+
+    // Names that are freed up earlier in the batch
+    assert!(!collides(&dom, vec![rename("A"), insert("Module")]));
+    assert!(!collides(
+        &dom,
+        vec![Delete { id: module_id }, insert("Module")]
+    ));
+    assert!(!collides(&dom, vec![rename("Module")]));
+
+    // Renames onto existing siblings
+    let other_id = dom
+        .insert_instance(root_id, "ModuleScript".to_string(), "Other".to_string())
+        .await
+        .unwrap();
+    let rename_other = Rename {
+        id: other_id,
+        name: "A".to_string(),
+    };
+    assert!(collides(&dom, vec![rename("Other")]));
+    assert!(collides(&dom, vec![rename_other.clone(), rename("A")]));
+    assert!(!collides(&dom, vec![rename_other, rename("Other")]));
+}
+
+#[tokio::test]
+async fn apply_batch_rolls_back() {
+    use DomBatchOperation::*;
+
+    let (mut dom, dir, module_id) = Dom::for_test("batch-rollback");
+    let src = dir.join("src");
+    let root_id = dom.get_root_id().unwrap();
+    let operations = |name: &str| {
+        vec![
+            Insert {
+                parent_id: root_id,
+                class_name: "ModuleScript".to_string(),
+                name: "Inserted".to_string(),
+            },
+            Rename {
+                id: module_id,
+                name: "Renamed".to_string(),
+            },
+            Insert {
+                parent_id: root_id,
+                class_name: "ModuleScript".to_string(),
+                name: name.to_string(),
+            },
+        ]
+    };
+    let assert_unchanged = |dom: &Dom| {
+        assert!(src.join("Module.luau").exists());
+        assert!(!src.join("Inserted.luau").exists());
+        assert!(!src.join("Renamed.luau").exists());
+        assert_eq!(dom.get_instance(root_id).unwrap().children(), [module_id]);
+        assert_eq!(dom.get_instance(module_id).unwrap().name, "Module");
+    };
+
+    // Failures that are only found once the batch is applied, such
+    // as a file that is not part of the dom, roll everything back
+    std::fs::write(src.join("Untracked.luau"), "").unwrap();
+    let err = dom
+        .apply_batch(operations("Untracked"), &CancellationToken::new())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), DomErrorKind::NameCollision);
+    assert_unchanged(&dom);
+    assert!(!dom.undo().await.unwrap());
+
+    // Cancelling while the batch is being applied rolls everything back,
+    // here the token is cancelled as soon as the batch first yields
+    let cancel = CancellationToken::new();
+    let err = {
+        let mut batch = std::pin::pin!(dom.apply_batch(operations("Other"), &cancel));
+        std::future::poll_fn(|cx| {
+            let poll = std::future::Future::poll(batch.as_mut(), cx);
+            if poll.is_pending() {
+                cancel.cancel();
+            }
+            poll
+        })
+        .await
+        .unwrap_err()
+    };
+    assert_eq!(err.kind(), DomErrorKind::Cancelled);
+    assert!(!src.join("Other.luau").exists());
+    assert_unchanged(&dom);
+    assert!(!dom.undo().await.unwrap());
+}
+
+#[test]
+fn coalesce_batch_notifications() {
+    let (added, changed, removed) = (Ref::new(), Ref::new(), Ref::new());
+    let notifications = vec![
+        DomNotification::Added {
+            parent_id: None,
+            child_id: added,
+        },
+        DomNotification::Changed {
+            id: changed,
+            class_name: None,
+            name: Some("First".to_string()),
+            properties: Some(vec!["Value".to_string()]),
+        },
+        DomNotification::Changed {
+            id: added,
+            class_name: None,
+            name: Some("Renamed".to_string()),
+            properties: None,
+        },
+        DomNotification::Changed {
+            id: changed,
+            class_name: None,
+            name: Some("Second".to_string()),
+            properties: Some(vec!["Value".to_string(), "Tags".to_string()]),
+        },
+        DomNotification::Removed {
+            parent_id: None,
+            child_id: added,
+        },
+        DomNotification::Removed {
+            parent_id: None,
+            child_id: removed,
+        },
+    ];
+    assert_eq!(
+        coalesce_notifications(notifications),
+        vec![
+            DomNotification::Changed {
+                id: changed,
+                class_name: None,
+                name: Some("Second".to_string()),
+                properties: Some(vec!["Value".to_string(), "Tags".to_string()]),
+            },
+            DomNotification::Removed {
+                parent_id: None,
+                child_id: removed,
+            },
+        ]
+    );
+}
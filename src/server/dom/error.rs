@@ -0,0 +1,115 @@
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+use tokio::io;
+
+/**
+    The kind of error that occurred while performing an operation on a dom.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DomErrorKind {
+    /// The instance does not exist in the dom.
+    NotFound,
+    /// The instance is not backed by any file or folder.
+    NotFileBacked,
+    /// The given instance name can not be used as a file name.
+    InvalidName,
+    /// The operation is not valid for the given instance(s).
+    InvalidOperation,
+    /// The given property value is not valid for the property.
+    InvalidValue,
+    /// A file or folder already exists at the target path.
+    NameCollision,
+    /// Permission was denied when accessing a file or folder.
+    PermissionDenied,
+    /// The operation was cancelled before it could finish.
+    Cancelled,
+    /// Any other filesystem error.
+    Io,
+}
+
+impl From<io::ErrorKind> for DomErrorKind {
+    fn from(kind: io::ErrorKind) -> Self {
+        match kind {
+            io::ErrorKind::NotFound => Self::NotFound,
+            io::ErrorKind::AlreadyExists => Self::NameCollision,
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+            _ => Self::Io,
+        }
+    }
+}
+
+/**
+    An error that occurred while performing an operation on a dom,
+    optionally with the path of the file or folder that caused it.
+*/
+#[derive(Debug, Error)]
+#[error("{message}")]
+pub struct DomError {
+    kind: DomErrorKind,
+    message: String,
+    path: Option<PathBuf>,
+}
+
+impl DomError {
+    pub fn new(kind: DomErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            path: None,
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self::new(DomErrorKind::NotFound, "Instance does not exist")
+    }
+
+    pub fn not_file_backed() -> Self {
+        Self::new(
+            DomErrorKind::NotFileBacked,
+            "Instance has no file or folder",
+        )
+    }
+
+    pub fn cancelled() -> Self {
+        Self::new(DomErrorKind::Cancelled, "Operation was cancelled")
+    }
+
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path.replace(path.into());
+        self
+    }
+
+    pub fn kind(&self) -> DomErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+impl From<io::Error> for DomError {
+    fn from(err: io::Error) -> Self {
+        Self::new(err.kind().into(), err.to_string())
+    }
+}
+
+pub type DomResult<T> = Result<T, DomError>;
+
+/**
+    Extension trait for attaching the affected path to filesystem errors.
+*/
+pub(super) trait IoResultExt<T> {
+    fn at_path(self, path: impl AsRef<Path>) -> DomResult<T>;
+}
+
+impl<T> IoResultExt<T> for io::Result<T> {
+    fn at_path(self, path: impl AsRef<Path>) -> DomResult<T> {
+        self.map_err(|e| DomError::from(e).with_path(path.as_ref()))
+    }
+}
@@ -1,11 +1,32 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use tokio::fs::{create_dir, read, remove_dir_all, remove_file, rename, write};
-use tokio::io;
+use serde_json::Value as JsonValue;
+use tokio::{
+    fs::{
+        copy, create_dir, create_dir_all, metadata, read, read_dir, read_to_string, remove_dir,
+        remove_dir_all, remove_file, rename, try_exists, write,
+    },
+    io,
+};
 
-use crate::util::rojo::{parse_name_and_class_name, parse_name_and_suffix, CLASS_NAME_SUFFIXES};
+use crate::util::{
+    json::edit_json,
+    rojo::{file_name_str, parse_name_and_class_name, parse_name_and_suffix, CLASS_NAME_SUFFIXES},
+};
 
-use super::InstanceMetadataPaths;
+use super::{DomError, DomErrorKind, DomResult, InstanceMetadataPaths, IoResultExt};
+
+#[cfg(test)]
+use crate::util::path::TestDir;
+
+/**
+    Characters that are not allowed in file names on at least one major platform.
+*/
+const INVALID_NAME_CHARS: &[char] = &['/', '\\', '<', '>', ':', '"', '|', '?', '*'];
 
 enum InstancePathVariant<'a> {
     Dir(&'a Path),
@@ -21,7 +42,7 @@ fn is_init_path(path: &Path) -> bool {
     }
 }
 
-fn get_instance_path_variant(paths: &InstanceMetadataPaths) -> InstancePathVariant {
+fn get_instance_path_variant(paths: &InstanceMetadataPaths) -> InstancePathVariant<'_> {
     let dir_path_opt = paths.folder.as_deref();
     let file_path_opt = paths.file.as_deref().or(paths.file_meta.as_deref());
     if matches!(file_path_opt.map(is_init_path), Some(true)) {
@@ -35,30 +56,56 @@ fn get_instance_path_variant(paths: &InstanceMetadataPaths) -> InstancePathVaria
     }
 }
 
-async fn transform_file_to_dir_with_init(file_path: &Path) -> io::Result<(PathBuf, PathBuf)> {
-    let parent_dir = file_path
-        .parent()
-        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "No parent dir"))?;
-
-    let file_name = file_path
-        .file_name()
-        .and_then(|name| name.to_str())
-        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "No file name"))?;
+fn unsupported_path(message: &str, path: &Path) -> DomError {
+    DomError::new(DomErrorKind::InvalidOperation, message).with_path(path)
+}
 
-    let (ext, _) = CLASS_NAME_SUFFIXES
-        .iter()
-        .find(|(ext, _)| file_name.ends_with(ext))
-        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "No matching extension"))?;
+/**
+    Checks that the given instance name can be used as a file name on all major platforms.
+*/
+fn validate_instance_name(name: &str) -> DomResult<()> {
+    let message = if name.trim().is_empty() {
+        Some("Name must not be empty")
+    } else if name == "." || name == ".." {
+        Some("Name must not be a relative path")
+    } else if name.contains(INVALID_NAME_CHARS) || name.contains(char::is_control) {
+        Some("Name contains characters that are not allowed in file names")
+    } else {
+        None
+    };
+    match message {
+        Some(message) => Err(DomError::new(
+            DomErrorKind::InvalidName,
+            format!("{message}: '{name}'"),
+        )),
+        None => Ok(()),
+    }
+}
 
-    let contents = read(&file_path).await?;
-    remove_file(&file_path).await?;
+async fn ensure_not_exists(path: &Path) -> DomResult<()> {
+    if try_exists(path).await.at_path(path)? {
+        Err(DomError::new(
+            DomErrorKind::NameCollision,
+            format!("Path already exists at {}", path.display()),
+        )
+        .with_path(path))
+    } else {
+        Ok(())
+    }
+}
 
-    let new_name = file_name.trim_end_matches(ext);
-    let new_dir = parent_dir.join(new_name);
-    let new_init = new_dir.join(format!("init{ext}"));
+async fn transform_file_to_dir_with_init(file_path: &Path) -> DomResult<(PathBuf, PathBuf)> {
+    let new_dir = get_dir_path_for_file(file_path)?;
+    let (_, suffix) = parse_name_and_suffix(file_path)
+        .ok_or_else(|| unsupported_path("No matching extension", file_path))?;
+    let new_init = new_dir.join(format!("init{suffix}"));
 
-    create_dir(&new_dir).await?;
-    write(&new_init, contents).await?;
+    // NOTE: The original file must be removed last, creating the new
+    // directory may fail if something already exists at its path
+    let contents = read(file_path).await.at_path(file_path)?;
+    create_dir(&new_dir).await.at_path(&new_dir)?;
+    write(&new_init, contents).await.at_path(&new_init)?;
+    remove_file(file_path).await.at_path(file_path)?;
 
     Ok((new_dir, new_init))
 }
@@ -67,10 +114,10 @@ async fn create_instance_in_dir(
     parent_path: &Path,
     class_name: &str,
     name: &str,
-) -> io::Result<Vec<PathBuf>> {
+) -> DomResult<Vec<PathBuf>> {
     if class_name == "Folder" {
         let child_path = parent_path.join(name);
-        create_dir(&child_path).await?;
+        create_dir(&child_path).await.at_path(&child_path)?;
 
         Ok(vec![child_path])
     } else {
@@ -91,7 +138,10 @@ async fn create_instance_in_dir(
             String::new()
         };
 
-        write(&child_path, child_contents).await?;
+        ensure_not_exists(&child_path).await?;
+        write(&child_path, child_contents)
+            .await
+            .at_path(&child_path)?;
         Ok(vec![child_path, parent_path.to_path_buf()])
     }
 }
@@ -100,49 +150,57 @@ pub async fn create_instance(
     parent_paths: &InstanceMetadataPaths,
     class_name: &str,
     name: &str,
-) -> io::Result<(Vec<PathBuf>, Option<Vec<PathBuf>>)> {
-    let mut changed_parent_paths = None;
-    let mut new_child_paths = Vec::new();
+) -> DomResult<(Vec<PathBuf>, Option<Vec<PathBuf>>)> {
+    validate_instance_name(name)?;
 
     match get_instance_path_variant(parent_paths) {
         InstancePathVariant::Dir(dir_path) => {
-            new_child_paths = create_instance_in_dir(dir_path, class_name, name).await?;
+            let new_child_paths = create_instance_in_dir(dir_path, class_name, name).await?;
+            Ok((new_child_paths, None))
         }
         InstancePathVariant::File(file_path) => {
             let (new_parent_dir, new_parent_init) =
                 transform_file_to_dir_with_init(file_path).await?;
-            new_child_paths = create_instance_in_dir(&new_parent_dir, class_name, name).await?;
-            changed_parent_paths = Some(vec![new_parent_dir, new_parent_init]);
+            let new_child_paths = create_instance_in_dir(&new_parent_dir, class_name, name).await?;
+            Ok((new_child_paths, Some(vec![new_parent_dir, new_parent_init])))
         }
-        InstancePathVariant::None => {}
+        InstancePathVariant::None => Err(DomError::not_file_backed()),
     }
-
-    Ok((new_child_paths, changed_parent_paths))
 }
 
 pub async fn rename_instance(
     instance_paths: &InstanceMetadataPaths,
     current_name: &str,
     name: &str,
-) -> io::Result<Vec<PathBuf>> {
+) -> DomResult<Vec<PathBuf>> {
+    validate_instance_name(name)?;
+
     let mut new_paths = Vec::new();
 
     match get_instance_path_variant(instance_paths) {
         InstancePathVariant::Dir(dir_path) => {
             let new_path = dir_path.with_file_name(name);
-            rename(dir_path, &new_path).await?;
+            if new_path != dir_path {
+                ensure_not_exists(&new_path).await?;
+            }
+            rename(dir_path, &new_path).await.at_path(dir_path)?;
             new_paths.push(new_path);
         }
         InstancePathVariant::File(_) => {
             let mut paths_to_change = Vec::new();
             paths_to_change.extend(instance_paths.file.as_deref());
             paths_to_change.extend(instance_paths.file_meta.as_deref());
+            // NOTE: Check all paths before renaming anything, since renaming
+            // would otherwise silently replace any existing file with the same name
+            let mut renames = Vec::new();
             for current_path in paths_to_change {
                 if let Some((parsed_name, suffix)) = parse_name_and_suffix(current_path) {
                     if parsed_name == current_name {
                         let new_path = current_path.with_file_name(format!("{name}{suffix}"));
-                        rename(current_path, &new_path).await?;
-                        new_paths.push(new_path);
+                        if new_path != current_path {
+                            ensure_not_exists(&new_path).await?;
+                        }
+                        renames.push((current_path, new_path));
                     } else {
                         tracing::warn!(
                             "name mismatch while renaming instance from '{}' to '{}'\nat {}",
@@ -160,26 +218,863 @@ pub async fn rename_instance(
                     )
                 }
             }
+            for (current_path, new_path) in renames {
+                rename(current_path, &new_path)
+                    .await
+                    .at_path(current_path)?;
+                new_paths.push(new_path);
+            }
         }
-        InstancePathVariant::None => {
-            tracing::warn!(
-                "no path was found while renaming instance from '{}' to '{}'",
-                current_name,
-                name
-            )
-        }
+        InstancePathVariant::None => return Err(DomError::not_file_backed()),
     }
 
     Ok(new_paths)
 }
 
-pub async fn delete_instance(instance_paths: &InstanceMetadataPaths) -> io::Result<()> {
+/**
+    Gets the path of the file that properties for an instance are stored in - the model file for
+    instances created from `.model.json` files, and the meta file for any other instance.
+
+    The meta file may not exist yet, in which case it will be created when setting a property.
+*/
+pub fn get_property_file_path(instance_paths: &InstanceMetadataPaths) -> DomResult<PathBuf> {
+    if let Some(file_path) = instance_paths.file.as_deref() {
+        if file_name_str(file_path).is_some_and(|f| f.ends_with(".model.json")) {
+            return Ok(file_path.to_path_buf());
+        }
+    }
     if let Some(meta_path) = instance_paths.file_meta.as_deref() {
-        remove_file(meta_path).await?;
+        return Ok(meta_path.to_path_buf());
     }
     match get_instance_path_variant(instance_paths) {
-        InstancePathVariant::Dir(dir_path) => remove_dir_all(dir_path).await,
-        InstancePathVariant::File(file_path) => remove_file(file_path).await,
-        InstancePathVariant::None => Ok(()),
+        InstancePathVariant::Dir(dir_path) => Ok(dir_path.join("init.meta.json")),
+        InstancePathVariant::File(file_path) => {
+            let (name, _) = parse_name_and_class_name(file_path)
+                .ok_or_else(|| unsupported_path("No matching extension", file_path))?;
+            Ok(file_path.with_file_name(format!("{name}.meta.json")))
+        }
+        InstancePathVariant::None => Err(DomError::not_file_backed()),
+    }
+}
+
+/**
+    Sets a property in a meta file or model file, or removes it if no value is given,
+    keeping the existing formatting and key order of the file. The file is created if
+    it does not exist yet.
+
+    Returns the previous value of the property, and if the file was created.
+*/
+pub async fn set_property(
+    property_path: &Path,
+    name: &str,
+    value: Option<&JsonValue>,
+) -> DomResult<(Option<JsonValue>, bool)> {
+    let contents = match read_to_string(property_path).await {
+        Ok(contents) => Some(contents),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(DomError::from(e).with_path(property_path)),
+    };
+    if contents.is_none() && value.is_none() {
+        return Ok((None, false));
+    }
+
+    let invalid_file = |e: &dyn std::fmt::Display| {
+        DomError::new(
+            DomErrorKind::InvalidOperation,
+            format!("Failed to edit properties file: {e}"),
+        )
+        .with_path(property_path)
+    };
+    let existing = match contents.as_deref() {
+        Some(contents) => {
+            serde_json::from_str::<JsonValue>(contents).map_err(|e| invalid_file(&e))?
+        }
+        None => JsonValue::Object(Default::default()),
+    };
+
+    // NOTE: Rojo also accepts the legacy capitalized keys in model files, which
+    // are what we use when creating them, so we need to stay consistent with those
+    let is_model_file = file_name_str(property_path).is_some_and(|f| f.ends_with(".model.json"));
+    let key = if !is_model_file || existing.get("properties").is_some() {
+        "properties"
+    } else if existing.get("Properties").is_some() || existing.get("ClassName").is_some() {
+        "Properties"
+    } else {
+        "properties"
+    };
+    let previous = existing.get(key).and_then(|p| p.get(name)).cloned();
+
+    let source = contents.as_deref().unwrap_or("{}");
+    let mut edited = edit_json(source, &[key, name], value).map_err(|e| invalid_file(&e))?;
+    if contents.is_none() {
+        edited.push('\n');
+    }
+    write(property_path, edited).await.at_path(property_path)?;
+
+    Ok((previous, contents.is_none()))
+}
+
+fn get_dir_path_for_file(file_path: &Path) -> DomResult<PathBuf> {
+    let parent_dir = file_path
+        .parent()
+        .ok_or_else(|| unsupported_path("No parent dir", file_path))?;
+
+    let file_name = file_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| unsupported_path("No file name", file_path))?;
+
+    let (ext, _) = CLASS_NAME_SUFFIXES
+        .iter()
+        .find(|(ext, _)| file_name.ends_with(ext))
+        .ok_or_else(|| unsupported_path("No matching extension", file_path))?;
+
+    Ok(parent_dir.join(file_name.trim_end_matches(ext)))
+}
+
+/**
+    Gets all of the paths that belong to an instance, meaning paths that should be
+    moved, copied, or deleted together with it, as well as its directory, if any.
+*/
+fn get_owned_paths(
+    instance_paths: &InstanceMetadataPaths,
+) -> DomResult<(Vec<&Path>, Option<&Path>)> {
+    match get_instance_path_variant(instance_paths) {
+        InstancePathVariant::Dir(dir_path) => {
+            let mut paths = vec![dir_path];
+            // NOTE: Meta files for directories are usually inside of them, as init.meta.json,
+            // but we should also handle any meta file that happens to be a sibling instead
+            if let Some(meta_path) = instance_paths.file_meta.as_deref() {
+                if !meta_path.starts_with(dir_path) {
+                    paths.push(meta_path);
+                }
+            }
+            Ok((paths, Some(dir_path)))
+        }
+        InstancePathVariant::File(file_path) => {
+            let mut paths = vec![file_path];
+            if let Some(meta_path) = instance_paths.file_meta.as_deref() {
+                if meta_path != file_path {
+                    paths.push(meta_path);
+                }
+            }
+            Ok((paths, None))
+        }
+        InstancePathVariant::None => Err(DomError::not_file_backed()),
+    }
+}
+
+/**
+    Gets the directory that children should be placed in for the given parent.
+
+    If the parent is a file, the directory will not exist yet, and the file path is also
+    returned, to be transformed using [`transform_file_to_dir_with_init`] when needed.
+*/
+fn get_parent_dir(parent_paths: &InstanceMetadataPaths) -> DomResult<(PathBuf, Option<&Path>)> {
+    match get_instance_path_variant(parent_paths) {
+        InstancePathVariant::Dir(dir_path) => Ok((dir_path.to_path_buf(), None)),
+        InstancePathVariant::File(file_path) => {
+            Ok((get_dir_path_for_file(file_path)?, Some(file_path)))
+        }
+        InstancePathVariant::None => Err(DomError::new(
+            DomErrorKind::NotFileBacked,
+            "Parent has no file or folder",
+        )),
+    }
+}
+
+async fn transform_parent_file(parent_file: Option<&Path>) -> DomResult<Option<Vec<PathBuf>>> {
+    match parent_file {
+        None => Ok(None),
+        Some(file_path) => {
+            let (new_parent_dir, new_parent_init) =
+                transform_file_to_dir_with_init(file_path).await?;
+            Ok(Some(vec![new_parent_dir, new_parent_init]))
+        }
+    }
+}
+
+/**
+    Turns a parent that was transformed using [`transform_parent_file`] back into a file.
+
+    Only used to roll back after a failed operation, so errors are logged instead of returned.
+*/
+async fn revert_parent_file(parent_file: Option<&Path>, changed_parent_paths: Option<&[PathBuf]>) {
+    if let (Some(file_path), Some([new_parent_dir, new_parent_init])) =
+        (parent_file, changed_parent_paths)
+    {
+        let reverted = match rename(new_parent_init, file_path).await {
+            Ok(()) => remove_dir(new_parent_dir).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = reverted {
+            tracing::error!(
+                "failed to turn parent back into a file at {}: {e}",
+                file_path.display()
+            );
+        }
+    }
+}
+
+/**
+    Moves a file or directory to a new path.
+
+    This is a plain rename, unless the new path is on a different device,
+    in which case the file or directory is copied over and then removed.
+*/
+async fn move_path(current_path: &Path, new_path: &Path) -> DomResult<()> {
+    match rename(current_path, new_path).await {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            if metadata(current_path).await.at_path(current_path)?.is_dir() {
+                copy_dir_all(current_path, new_path).await?;
+                remove_dir_all(current_path).await.at_path(current_path)
+            } else {
+                copy(current_path, new_path).await.at_path(current_path)?;
+                remove_file(current_path).await.at_path(current_path)
+            }
+        }
+        result => result.at_path(current_path),
+    }
+}
+
+/**
+    Moves all of the given `(old, new)` path pairs, in order.
+
+    If any move fails, all moves that already happened
+    are undone, in reverse order, before returning the error.
+*/
+async fn rename_all(renames: &[(PathBuf, PathBuf)]) -> DomResult<()> {
+    for (index, (current_path, new_path)) in renames.iter().enumerate() {
+        if let Err(e) = move_path(current_path, new_path).await {
+            for (current_path, new_path) in renames[..index].iter().rev() {
+                if let Err(e) = move_path(new_path, current_path).await {
+                    tracing::error!(
+                        "failed to roll back rename of {}: {e}",
+                        current_path.display()
+                    );
+                }
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/**
+    Moves the files and / or folder for an instance into the folder of a new parent.
+
+    If the new parent is a file, it will be transformed into a directory with an
+    init file, and its new paths will be returned, same as in [`create_instance`].
+
+    Returns a list of `(old, new)` pairs for every path that was moved. This list
+    is empty if the instance already lives in the folder of the new parent.
+*/
+pub async fn move_instance(
+    instance_paths: &InstanceMetadataPaths,
+    parent_paths: &InstanceMetadataPaths,
+) -> DomResult<(Vec<(PathBuf, PathBuf)>, Option<Vec<PathBuf>>)> {
+    let (paths_to_move, _) = get_owned_paths(instance_paths)?;
+    let (parent_dir, parent_file) = get_parent_dir(parent_paths)?;
+
+    // Check everything we possibly can before touching the filesystem,
+    // we don't want to end up with a partially moved instance on errors
+    let mut moves = Vec::new();
+    for current_path in paths_to_move {
+        if current_path.parent() == Some(parent_dir.as_path()) {
+            continue;
+        }
+        if parent_dir.starts_with(current_path) {
+            return Err(DomError::new(
+                DomErrorKind::InvalidOperation,
+                "Can not move an instance into itself",
+            )
+            .with_path(current_path));
+        }
+        let file_name = current_path
+            .file_name()
+            .ok_or_else(|| unsupported_path("No file name", current_path))?;
+        let new_path = parent_dir.join(file_name);
+        ensure_not_exists(&new_path).await?;
+        moves.push((current_path.to_path_buf(), new_path));
+    }
+
+    if moves.is_empty() {
+        return Ok((moves, None));
+    }
+
+    // NOTE: Renames may still fail, for example if another process has a file
+    // open, so any renames that already happened are rolled back on errors
+    let changed_parent_paths = transform_parent_file(parent_file).await?;
+    if let Err(e) = rename_all(&moves).await {
+        revert_parent_file(parent_file, changed_parent_paths.as_deref()).await;
+        return Err(e);
+    }
+
+    Ok((moves, changed_parent_paths))
+}
+
+async fn copy_dir_all(from: &Path, to: &Path) -> DomResult<()> {
+    let mut dirs = vec![(from.to_path_buf(), to.to_path_buf())];
+    while let Some((from_dir, to_dir)) = dirs.pop() {
+        create_dir(&to_dir).await.at_path(&to_dir)?;
+        let mut entries = read_dir(&from_dir).await.at_path(&from_dir)?;
+        while let Some(entry) = entries.next_entry().await.at_path(&from_dir)? {
+            let from_path = entry.path();
+            let to_path = to_dir.join(entry.file_name());
+            if entry.file_type().await.at_path(&from_path)?.is_dir() {
+                dirs.push((from_path, to_path));
+            } else {
+                copy(&from_path, to_path).await.at_path(&from_path)?;
+            }
+        }
     }
+    Ok(())
+}
+
+fn with_instance_name(path: &Path, name: &str) -> PathBuf {
+    // NOTE: The file stem does not always match the instance name,
+    // but the suffix must be kept either way, or the class changes
+    match parse_name_and_suffix(path) {
+        Some((_, suffix)) => path.with_file_name(format!("{name}{suffix}")),
+        None => path.with_file_name(name),
+    }
+}
+
+/**
+    Removes a file or directory, if it exists, logging any errors.
+*/
+async fn remove_path(path: &Path) {
+    let removed = match metadata(path).await {
+        Ok(meta) if meta.is_dir() => remove_dir_all(path).await,
+        Ok(_) => remove_file(path).await,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = removed {
+        tracing::error!("failed to remove {}: {e}", path.display());
+    }
+}
+
+/**
+    Copies all of the given `(old, new)` path pairs, in order.
+
+    If any copy fails, all copies that were made, including any
+    partial copy of a directory, are removed before returning the error.
+*/
+async fn copy_all(copies: &[(PathBuf, PathBuf)], dir_to_copy: Option<&Path>) -> DomResult<()> {
+    for (index, (current_path, new_path)) in copies.iter().enumerate() {
+        let copied = if Some(current_path.as_path()) == dir_to_copy {
+            copy_dir_all(current_path, new_path).await
+        } else {
+            copy(current_path, new_path)
+                .await
+                .map(|_| ())
+                .at_path(current_path)
+        };
+        if let Err(e) = copied {
+            for (_, new_path) in copies[..=index].iter().rev() {
+                remove_path(new_path).await;
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/**
+    Copies the files and / or folder for an instance into the folder of a new parent.
+
+    If any of the copied paths would conflict with an existing file or folder in the new
+    parent, or the name of the copy is one of the given taken names, a numeric suffix
+    is added to the name of the copy until there is no conflict.
+
+    Returns the name for the copied instance, and a list of `(old, new)` pairs for
+    every path that was copied. Any changed paths for the parent are also returned,
+    if the parent had to be transformed into a directory, same as in [`create_instance`].
+*/
+pub async fn copy_instance(
+    instance_paths: &InstanceMetadataPaths,
+    parent_paths: &InstanceMetadataPaths,
+    current_name: &str,
+    taken_names: &[String],
+) -> DomResult<(String, Vec<(PathBuf, PathBuf)>, Option<Vec<PathBuf>>)> {
+    let (paths_to_copy, dir_to_copy) = get_owned_paths(instance_paths)?;
+    let (parent_dir, parent_file) = get_parent_dir(parent_paths)?;
+
+    if let Some(dir_path) = dir_to_copy {
+        if parent_dir.starts_with(dir_path) {
+            return Err(DomError::new(
+                DomErrorKind::InvalidOperation,
+                "Can not copy an instance into itself",
+            )
+            .with_path(dir_path));
+        }
+    }
+
+    // Find the first name that does not conflict with anything in the new parent
+    let mut name = current_name.to_string();
+    let mut suffix = 1;
+    let copies = loop {
+        let mut copies = Vec::new();
+        let mut conflict = taken_names.contains(&name);
+        for current_path in &paths_to_copy {
+            if conflict {
+                break;
+            }
+            let new_path = with_instance_name(current_path, &name);
+            let new_path = parent_dir.join(new_path.file_name().unwrap());
+            conflict = try_exists(&new_path).await.at_path(&new_path)?;
+            copies.push((current_path.to_path_buf(), new_path));
+        }
+        if !conflict {
+            break copies;
+        }
+        suffix += 1;
+        name = format!("{current_name}{suffix}");
+    };
+
+    // NOTE: Copies may fail halfway through, for example if a file is missing
+    // or can not be read, so anything that was already done is rolled back
+    let changed_parent_paths = transform_parent_file(parent_file).await?;
+    if let Err(e) = copy_all(&copies, dir_to_copy).await {
+        revert_parent_file(parent_file, changed_parent_paths.as_deref()).await;
+        return Err(e);
+    }
+
+    Ok((name, copies, changed_parent_paths))
+}
+
+/**
+    A file or directory that has been deleted, by moving it into the trash directory.
+*/
+#[derive(Debug, Clone)]
+pub struct TrashedPath {
+    /// Where the file or directory was before it got deleted.
+    pub path: PathBuf,
+    /// Where the file or directory is now, inside of the trash directory.
+    pub trashed: PathBuf,
+}
+
+/**
+    Creates a new, empty directory inside of the given trash directory.
+
+    Every deleted instance gets a directory of its own, so that
+    files with the same name can be in the trash at the same time.
+*/
+async fn create_trash_dir(trash_dir: &Path) -> DomResult<PathBuf> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let dir = trash_dir.join(format!("{timestamp}-{count}"));
+    create_dir_all(trash_dir).await.at_path(trash_dir)?;
+    create_dir(&dir).await.at_path(&dir)?;
+    Ok(dir)
+}
+
+/**
+    Deletes all files and directories for an instance, by moving
+    them into a new directory inside of the given trash directory.
+
+    The returned paths can be passed to [`restore_instance`] to bring the
+    instance back, or to [`purge_trashed`] once it will never be restored.
+*/
+pub async fn trash_instance(
+    instance_paths: &InstanceMetadataPaths,
+    trash_dir: &Path,
+) -> DomResult<Vec<TrashedPath>> {
+    let (owned_paths, _) = get_owned_paths(instance_paths)?;
+
+    let dir = create_trash_dir(trash_dir).await?;
+    let mut moves = Vec::new();
+    for path in owned_paths {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| unsupported_path("No file name", path))?;
+        moves.push((path.to_path_buf(), dir.join(file_name)));
+    }
+
+    if let Err(e) = rename_all(&moves).await {
+        remove_dir_all(&dir).await.ok();
+        return Err(e);
+    }
+
+    Ok(moves
+        .into_iter()
+        .map(|(path, trashed)| TrashedPath { path, trashed })
+        .collect())
+}
+
+/**
+    Restores files and directories for an instance that was previously
+    deleted using [`trash_instance`], into the folder of the given parent.
+
+    Returns the `(old, new)` pair for the folder that the instance was restored into,
+    as well as any changed paths for the parent, same as in [`create_instance`].
+*/
+pub async fn restore_instance(
+    trashed: &[TrashedPath],
+    parent_paths: &InstanceMetadataPaths,
+) -> DomResult<((PathBuf, PathBuf), Option<Vec<PathBuf>>)> {
+    let old_dir = trashed
+        .first()
+        .and_then(|t| t.path.parent())
+        .ok_or_else(|| DomError::new(DomErrorKind::InvalidOperation, "Nothing to restore"))?
+        .to_path_buf();
+    let (parent_dir, parent_file) = get_parent_dir(parent_paths)?;
+
+    let mut restores = Vec::new();
+    for t in trashed {
+        let new_path = parent_dir.join(t.path.strip_prefix(&old_dir).unwrap_or(&t.path));
+        ensure_not_exists(&new_path).await?;
+        restores.push((t.trashed.clone(), new_path));
+    }
+
+    let changed_parent_paths = transform_parent_file(parent_file).await?;
+    if let Err(e) = rename_all(&restores).await {
+        revert_parent_file(parent_file, changed_parent_paths.as_deref()).await;
+        return Err(e);
+    }
+    purge_trashed(trashed).await;
+
+    Ok(((old_dir, parent_dir), changed_parent_paths))
+}
+
+/**
+    Removes the directories in the trash that were created for
+    the given paths, along with anything that is still in them.
+*/
+pub async fn purge_trashed(trashed: &[TrashedPath]) {
+    for dir in trashed_dirs(trashed) {
+        if let Err(e) = remove_dir_all(dir).await {
+            if e.kind() != io::ErrorKind::NotFound {
+                tracing::error!("failed to purge trash at {}: {e}", dir.display());
+            }
+        }
+    }
+}
+
+/**
+    Same as [`purge_trashed`], but blocking, for when there is no async runtime to use.
+*/
+pub fn purge_trashed_blocking(trashed: &[TrashedPath]) {
+    for dir in trashed_dirs(trashed) {
+        if let Err(e) = std::fs::remove_dir_all(dir) {
+            if e.kind() != io::ErrorKind::NotFound {
+                tracing::error!("failed to purge trash at {}: {e}", dir.display());
+            }
+        }
+    }
+}
+
+fn trashed_dirs(trashed: &[TrashedPath]) -> Vec<&Path> {
+    let mut dirs = trashed
+        .iter()
+        .filter_map(|t| t.trashed.parent())
+        .collect::<Vec<_>>();
+    dirs.dedup();
+    dirs
+}
+
+/**
+    Transforms a directory that contains nothing but an init file back into a plain file.
+
+    This is the opposite of what happens when an instance is inserted into a file.
+
+    Returns the new paths for the instance if it was transformed, or `None` if the
+    instance is not a directory or if the directory contains anything else.
+*/
+pub async fn collapse_instance(
+    instance_paths: &InstanceMetadataPaths,
+) -> DomResult<Option<Vec<PathBuf>>> {
+    let (dir_path, init_path) = match (
+        get_instance_path_variant(instance_paths),
+        instance_paths.file.as_deref(),
+    ) {
+        (InstancePathVariant::Dir(dir_path), Some(file_path)) if is_init_path(file_path) => {
+            (dir_path, file_path)
+        }
+        _ => return Ok(None),
+    };
+
+    let mut entries = read_dir(dir_path).await.at_path(dir_path)?;
+    while let Some(entry) = entries.next_entry().await.at_path(dir_path)? {
+        if entry.path() != init_path {
+            return Ok(None);
+        }
+    }
+
+    let parent_dir = dir_path
+        .parent()
+        .ok_or_else(|| unsupported_path("No parent dir", dir_path))?;
+    let (_, suffix) = parse_name_and_suffix(init_path)
+        .ok_or_else(|| unsupported_path("No matching extension", init_path))?;
+    let dir_name = dir_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| unsupported_path("No file name", dir_path))?;
+
+    let new_file = parent_dir.join(format!("{dir_name}{suffix}"));
+    if try_exists(&new_file).await.at_path(&new_file)? {
+        return Ok(None);
+    }
+
+    rename(init_path, &new_file).await.at_path(init_path)?;
+    remove_dir(dir_path).await.at_path(dir_path)?;
+
+    Ok(Some(vec![new_file, parent_dir.to_path_buf()]))
+}
+
+#[cfg(test)]
+fn test_paths(
+    folder: Option<&Path>,
+    file: Option<&Path>,
+    meta: Option<&Path>,
+) -> InstanceMetadataPaths {
+    InstanceMetadataPaths {
+        folder: folder.map(Path::to_path_buf),
+        file: file.map(Path::to_path_buf),
+        file_meta: meta.map(Path::to_path_buf),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn move_instances() {
+    let dir = TestDir::new("fs-move");
+    let src = dir.join("src");
+    let target = src.join("Target");
+    for path in ["Target", "Package"] {
+        std::fs::create_dir_all(src.join(path)).unwrap();
+    }
+    for path in [
+        "Module.luau",
+        "Script.server.luau",
+        "Script.meta.json",
+        "Other.luau",
+        "Package/init.luau",
+        "Package/Child.luau",
+    ] {
+        std::fs::write(src.join(path), path).unwrap();
+    }
+
+    let target_paths = test_paths(Some(&target), None, None);
+
+    // Files
+    let module = test_paths(Some(&src), Some(&src.join("Module.luau")), None);
+    let (moves, changed) = move_instance(&module, &target_paths).await.unwrap();
+    assert_eq!(
+        moves,
+        vec![(src.join("Module.luau"), target.join("Module.luau"))]
+    );
+    assert!(changed.is_none());
+    assert!(target.join("Module.luau").exists());
+    assert!(!src.join("Module.luau").exists());
+
+    // Scripts with meta files
+    let script = test_paths(
+        Some(&src),
+        Some(&src.join("Script.server.luau")),
+        Some(&src.join("Script.meta.json")),
+    );
+    let (moves, _) = move_instance(&script, &target_paths).await.unwrap();
+    assert_eq!(moves.len(), 2);
+    assert!(target.join("Script.server.luau").exists());
+    assert!(target.join("Script.meta.json").exists());
+    assert!(!src.join("Script.meta.json").exists());
+
+    // Folders with init files, and their children
+    let package = test_paths(
+        Some(&src.join("Package")),
+        Some(&src.join("Package/init.luau")),
+        None,
+    );
+    move_instance(&package, &target_paths).await.unwrap();
+    assert!(target.join("Package/init.luau").exists());
+    assert!(target.join("Package/Child.luau").exists());
+    assert!(!src.join("Package").exists());
+
+    // Moving into itself, or one of its descendants, must not touch anything
+    let target_folder = test_paths(Some(&target), None, None);
+    let package_paths = test_paths(
+        Some(&target.join("Package")),
+        Some(&target.join("Package/init.luau")),
+        None,
+    );
+    let err = move_instance(&target_folder, &package_paths)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), DomErrorKind::InvalidOperation);
+    assert!(target.join("Package/init.luau").exists());
+
+    // Moving into a file turns it into a folder with an init file
+    let moved_module = test_paths(Some(&target), Some(&target.join("Module.luau")), None);
+    let other = test_paths(Some(&src), Some(&src.join("Other.luau")), None);
+    let (_, changed) = move_instance(&moved_module, &other).await.unwrap();
+    assert_eq!(
+        changed,
+        Some(vec![src.join("Other"), src.join("Other/init.luau")])
+    );
+    assert!(src.join("Other/Module.luau").exists());
+
+    // Moving into the folder it is already in does nothing
+    let moved_script = test_paths(
+        Some(&target),
+        Some(&target.join("Script.server.luau")),
+        Some(&target.join("Script.meta.json")),
+    );
+    let (moves, _) = move_instance(&moved_script, &target_paths).await.unwrap();
+    assert!(moves.is_empty());
+}
+
+#[tokio::test]
+async fn copy_instances() {
+    let dir = TestDir::new("fs-copy");
+    let src = dir.join("src");
+    let target = src.join("Target");
+    for path in ["Target", "Folder", "Package"] {
+        std::fs::create_dir_all(src.join(path)).unwrap();
+    }
+    for path in [
+        "Script.server.luau",
+        "Script.meta.json",
+        "Folder/Child.luau",
+        "Package/init.luau",
+        "Package/Child.luau",
+    ] {
+        std::fs::write(src.join(path), path).unwrap();
+    }
+
+    let target_paths = test_paths(Some(&target), None, None);
+
+    // Files, keeping their suffixes
+    let script = test_paths(
+        Some(&src),
+        Some(&src.join("Script.server.luau")),
+        Some(&src.join("Script.meta.json")),
+    );
+    let (name, copies, _) = copy_instance(&script, &target_paths, "Script", &[])
+        .await
+        .unwrap();
+    assert_eq!(name, "Script");
+    assert_eq!(copies.len(), 2);
+    assert!(target.join("Script.server.luau").exists());
+    assert!(target.join("Script.meta.json").exists());
+    assert!(src.join("Script.server.luau").exists());
+
+    // Folders, with all of their contents
+    let folder = test_paths(Some(&src.join("Folder")), None, None);
+    copy_instance(&folder, &target_paths, "Folder", &[])
+        .await
+        .unwrap();
+    assert!(target.join("Folder/Child.luau").exists());
+
+    // Folders with init files
+    let package = test_paths(
+        Some(&src.join("Package")),
+        Some(&src.join("Package/init.luau")),
+        None,
+    );
+    copy_instance(&package, &target_paths, "Package", &[])
+        .await
+        .unwrap();
+    assert!(target.join("Package/init.luau").exists());
+    assert!(target.join("Package/Child.luau").exists());
+
+    // Name clashes get a numeric suffix added, for every path
+    let (name, _, _) = copy_instance(&script, &target_paths, "Script", &[])
+        .await
+        .unwrap();
+    assert_eq!(name, "Script2");
+    assert!(target.join("Script2.server.luau").exists());
+    assert!(target.join("Script2.meta.json").exists());
+    let (name, _, _) = copy_instance(&script, &target_paths, "Script", &[])
+        .await
+        .unwrap();
+    assert_eq!(name, "Script3");
+    assert!(target.join("Script3.server.luau").exists());
+
+    // Files named differently from their instance still keep their suffix
+    let (name, _, _) = copy_instance(&script, &target_paths, "Renamed", &[])
+        .await
+        .unwrap();
+    assert_eq!(name, "Renamed");
+    assert!(target.join("Renamed.server.luau").exists());
+    assert!(target.join("Renamed.meta.json").exists());
+
+    // Names taken by siblings in the dom are skipped, even without any files
+    let taken = vec!["Folder2".to_string()];
+    let (name, _, _) = copy_instance(&folder, &target_paths, "Folder", &taken)
+        .await
+        .unwrap();
+    assert_eq!(name, "Folder3");
+    assert!(target.join("Folder3/Child.luau").exists());
+
+    // Copies that fail halfway through are removed, and parent files are restored
+    std::fs::write(src.join("Parent.luau"), "").unwrap();
+    let parent = test_paths(Some(&src), Some(&src.join("Parent.luau")), None);
+    let missing_meta = test_paths(
+        Some(&src),
+        Some(&src.join("Script.server.luau")),
+        Some(&src.join("Missing.meta.json")),
+    );
+    let err = copy_instance(&missing_meta, &parent, "Script", &[])
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), DomErrorKind::NotFound);
+    assert!(src.join("Parent.luau").exists());
+    assert!(!src.join("Parent").exists());
+}
+
+#[tokio::test]
+async fn trash_and_restore_instances() {
+    let dir = TestDir::new("fs-trash");
+    let src = dir.join("src");
+    let trash = dir.join("trash");
+    std::fs::create_dir_all(src.join("Target")).unwrap();
+    for path in ["Script.server.luau", "Script.meta.json"] {
+        std::fs::write(src.join(path), path).unwrap();
+    }
+
+    let script = test_paths(
+        Some(&src),
+        Some(&src.join("Script.server.luau")),
+        Some(&src.join("Script.meta.json")),
+    );
+    let trashed = trash_instance(&script, &trash).await.unwrap();
+    assert_eq!(trashed.len(), 2);
+    assert!(!src.join("Script.server.luau").exists());
+    assert!(trashed.iter().all(|t| t.trashed.starts_with(&trash)));
+    assert!(trashed.iter().all(|t| t.trashed.exists()));
+
+    // Restoring onto an existing path fails, and keeps everything in the trash
+    std::fs::write(src.join("Target/Script.server.luau"), "").unwrap();
+    let target_paths = test_paths(Some(&src.join("Target")), None, None);
+    let err = restore_instance(&trashed, &target_paths).await.unwrap_err();
+    assert_eq!(err.kind(), DomErrorKind::NameCollision);
+    assert!(trashed.iter().all(|t| t.trashed.exists()));
+
+    let src_paths = test_paths(Some(&src), None, None);
+    let (dir_move, _) = restore_instance(&trashed, &src_paths).await.unwrap();
+    assert_eq!(dir_move, (src.clone(), src.clone()));
+    assert!(src.join("Script.server.luau").exists());
+    assert!(src.join("Script.meta.json").exists());
+    assert_eq!(std::fs::read_dir(&trash).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn rename_all_rolls_back() {
+    let dir = TestDir::new("fs-rename-all");
+    std::fs::write(dir.join("First.luau"), "").unwrap();
+
+    let renames = vec![
+        (dir.join("First.luau"), dir.join("Renamed.luau")),
+        (dir.join("Missing.luau"), dir.join("Renamed.meta.json")),
+    ];
+    let err = rename_all(&renames).await.unwrap_err();
+    assert_eq!(err.kind(), DomErrorKind::NotFound);
+    assert!(dir.join("First.luau").exists());
+    assert!(!dir.join("Renamed.luau").exists());
 }
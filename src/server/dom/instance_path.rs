@@ -0,0 +1,588 @@
+use rbx_dom_weak::{types::Ref, Instance};
+use serde::Deserialize;
+
+use super::{
+    class_is_a, class_is_service, find_property_descriptor, Dom, DomError, DomErrorKind, DomResult,
+};
+
+const DATA_MODEL_CLASS_NAME: &str = "DataModel";
+
+const LUAU_KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in", "local",
+    "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+// NOTE: The reflection database only contains properties, so any methods and events
+// of instances that children could be named after are listed here, since indexing
+// an instance using one of these names gives the member instead of the child
+const INSTANCE_MEMBERS: &[&str] = &[
+    "AddTag",
+    "AncestryChanged",
+    "AttributeChanged",
+    "Changed",
+    "ChildAdded",
+    "ChildRemoved",
+    "ClearAllChildren",
+    "Clone",
+    "DescendantAdded",
+    "DescendantRemoving",
+    "Destroy",
+    "Destroying",
+    "FindFirstAncestor",
+    "FindFirstAncestorOfClass",
+    "FindFirstAncestorWhichIsA",
+    "FindFirstChild",
+    "FindFirstChildOfClass",
+    "FindFirstChildWhichIsA",
+    "FindFirstDescendant",
+    "GetAttribute",
+    "GetAttributeChangedSignal",
+    "GetAttributes",
+    "GetChildren",
+    "GetDescendants",
+    "GetFullName",
+    "GetPropertyChangedSignal",
+    "GetTags",
+    "HasTag",
+    "IsA",
+    "IsAncestorOf",
+    "IsDescendantOf",
+    "RemoveTag",
+    "SetAttribute",
+    "WaitForChild",
+];
+
+/**
+    The instance that an instance path starts at.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstancePathRoot {
+    /// The root `DataModel` - `game`
+    Game,
+    /// The script that the path is relative to - `script`
+    Script,
+    /// A root that is not a `DataModel`, by its name
+    Named(String),
+}
+
+/**
+    A single step in an instance path, from one instance to another.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstancePathSegment {
+    /// The parent of the instance - `.Parent`
+    Parent,
+    /// A child by name - `.Name`, `["Name"]`, `:FindFirstChild("Name")`, `:WaitForChild("Name")`
+    Child(String),
+    /// A descendant by name - `:FindFirstChild("Name", true)`
+    Descendant(String),
+    /// An ancestor by name - `:FindFirstAncestor("Name")`
+    Ancestor(String),
+    /// A service by its class name - `:GetService("ClassName")`
+    Service(String),
+    /// A child by its exact class name - `:FindFirstChildOfClass("ClassName")`
+    ChildOfClass(String),
+    /// A child by class name, or any subclass of it - `:FindFirstChildWhichIsA("ClassName")`
+    ChildWhichIsA(String),
+}
+
+/**
+    A path to an instance, parsed from a Luau expression.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstancePath {
+    pub root: InstancePathRoot,
+    pub segments: Vec<InstancePathSegment>,
+}
+
+impl InstancePath {
+    /**
+        Parses an instance path from a Luau expression, such as `game.ReplicatedStorage.Shared.Util`,
+        `script.Parent:WaitForChild("Foo")`, or `game:GetService("Players")["Player Scripts"]`.
+
+        Only the subset of Luau that is used to index instances is supported, and
+        anything else, such as variables or calling any other methods, is an error.
+    */
+    pub fn parse(source: &str) -> DomResult<Self> {
+        let tokens = tokenize(source).map_err(invalid_path)?;
+        let mut tokens = tokens.into_iter();
+
+        let mut segments = Vec::new();
+        let root = match tokens.next() {
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "game" | "Game" => InstancePathRoot::Game,
+                "script" => InstancePathRoot::Script,
+                "workspace" | "Workspace" => {
+                    segments.push(InstancePathSegment::Service(String::from("Workspace")));
+                    InstancePathRoot::Game
+                }
+                _ => InstancePathRoot::Named(ident),
+            },
+            Some(Token::Str(name)) => InstancePathRoot::Named(name),
+            _ => return Err(invalid_path("expected `game`, `script`, or a root name")),
+        };
+
+        while let Some(token) = tokens.next() {
+            let segment = match token {
+                Token::Dot => match tokens.next() {
+                    Some(Token::Ident(name)) if name == "Parent" => InstancePathSegment::Parent,
+                    Some(Token::Ident(name)) => InstancePathSegment::Child(name),
+                    _ => return Err(invalid_path("expected a name after `.`")),
+                },
+                Token::Punct('[') => match (tokens.next(), tokens.next()) {
+                    (Some(Token::Str(name)), Some(Token::Punct(']'))) => {
+                        InstancePathSegment::Child(name)
+                    }
+                    _ => return Err(invalid_path("expected a string inside of `[]`")),
+                },
+                Token::Punct(':') => {
+                    let Some(Token::Ident(method)) = tokens.next() else {
+                        return Err(invalid_path("expected a method name after `:`"));
+                    };
+                    if tokens.next() != Some(Token::Punct('(')) {
+                        return Err(invalid_path("expected `(` after method name"));
+                    }
+                    let mut args = Vec::new();
+                    loop {
+                        match tokens.next() {
+                            Some(Token::Punct(')')) => break,
+                            Some(Token::Punct(',')) => {}
+                            Some(token) => args.push(token),
+                            None => return Err(invalid_path("expected `)`")),
+                        }
+                    }
+                    let Some(Token::Str(name)) = args.first().cloned() else {
+                        return Err(invalid_path("expected a string argument"));
+                    };
+                    let recursive = args.get(1) == Some(&Token::Ident(String::from("true")));
+                    match method.as_str() {
+                        "FindFirstChild" if recursive => InstancePathSegment::Descendant(name),
+                        "FindFirstChild" | "WaitForChild" => InstancePathSegment::Child(name),
+                        "FindFirstAncestor" => InstancePathSegment::Ancestor(name),
+                        "GetService" => InstancePathSegment::Service(name),
+                        "FindFirstChildOfClass" => InstancePathSegment::ChildOfClass(name),
+                        "FindFirstChildWhichIsA" => InstancePathSegment::ChildWhichIsA(name),
+                        _ => return Err(invalid_path(format!("unsupported method `{method}`"))),
+                    }
+                }
+                _ => return Err(invalid_path("expected `.`, `[`, or `:`")),
+            };
+            segments.push(segment);
+        }
+
+        Ok(Self { root, segments })
+    }
+}
+
+/**
+    The style to use for instance paths, see [`Dom::get_instance_path`].
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InstancePathStyle {
+    /// `game.ReplicatedStorage.Shared["My Module"]`
+    #[default]
+    Dot,
+    /// `game:GetService("ReplicatedStorage"):FindFirstChild("Shared"):FindFirstChild("My Module")`
+    FindFirstChild,
+    /// `game:GetService("ReplicatedStorage"):WaitForChild("Shared"):WaitForChild("My Module")`
+    WaitForChild,
+}
+
+impl Dom {
+    /**
+        Finds the instance at the given instance path, if it exists.
+
+        Paths starting with `script` are relative to the given script, and `game` is the
+        `DataModel` that contains the script, or the first root that is a `DataModel`.
+
+        Returns an error if the path starts with `script`, but no script was
+        given, or if the given script does not exist.
+    */
+    pub fn find_by_instance_path(
+        &self,
+        path: &InstancePath,
+        script: Option<Ref>,
+    ) -> DomResult<Option<Ref>> {
+        if let Some(script) = script {
+            if self.get_instance(script).is_none() {
+                return Err(DomError::not_found());
+            }
+        }
+
+        let root = match &path.root {
+            InstancePathRoot::Script => match script {
+                Some(script) => Some(script),
+                None => {
+                    return Err(DomError::new(
+                        DomErrorKind::InvalidValue,
+                        "Instance paths starting with `script` need a script",
+                    ))
+                }
+            },
+            InstancePathRoot::Game => script
+                .and_then(|script| self.get_root_ancestor(script))
+                .into_iter()
+                .chain(self.get_root_ids())
+                .find(|id| {
+                    self.get_instance(*id)
+                        .is_some_and(|inst| inst.class == DATA_MODEL_CLASS_NAME)
+                }),
+            InstancePathRoot::Named(name) => self.get_root_ids().into_iter().find(|id| {
+                self.get_instance(*id)
+                    .is_some_and(|inst| &inst.name == name)
+            }),
+        };
+
+        let mut current = root;
+        for segment in &path.segments {
+            current = current.and_then(|id| self.resolve_instance_path_segment(id, segment));
+        }
+        Ok(current)
+    }
+
+    pub(super) fn get_root_ancestor(&self, id: Ref) -> Option<Ref> {
+        let mut current = self.get_instance(id)?;
+        while let Some(parent) = self.get_instance(current.parent()) {
+            current = parent;
+        }
+        Some(current.referent())
+    }
+
+    fn resolve_instance_path_segment(&self, id: Ref, segment: &InstancePathSegment) -> Option<Ref> {
+        let inst = self.get_instance(id)?;
+        let find_child = |predicate: &dyn Fn(&Instance) -> bool| {
+            inst.children()
+                .iter()
+                .copied()
+                .find(|child_id| self.get_instance(*child_id).is_some_and(predicate))
+        };
+        match segment {
+            InstancePathSegment::Parent => Some(inst.parent()).filter(Ref::is_some),
+            InstancePathSegment::Child(name) => find_child(&|child| &child.name == name),
+            InstancePathSegment::Descendant(name) => {
+                self.subtree_ids(id).into_iter().skip(1).find(|id| {
+                    self.get_instance(*id)
+                        .is_some_and(|inst| &inst.name == name)
+                })
+            }
+            InstancePathSegment::Ancestor(name) => {
+                let mut current = self.get_instance(inst.parent());
+                while let Some(ancestor) = current {
+                    if &ancestor.name == name {
+                        return Some(ancestor.referent());
+                    }
+                    current = self.get_instance(ancestor.parent());
+                }
+                None
+            }
+            // NOTE: Services are almost always named after their class, but
+            // can be renamed, so we prefer to look for the class name first
+            InstancePathSegment::Service(class_name) => {
+                find_child(&|child| &child.class == class_name)
+                    .or_else(|| find_child(&|child| &child.name == class_name))
+            }
+            InstancePathSegment::ChildOfClass(class_name) => {
+                find_child(&|child| &child.class == class_name)
+            }
+            InstancePathSegment::ChildWhichIsA(class_name) => {
+                find_child(&|child| class_is_a(&child.class, class_name))
+            }
+        }
+    }
+
+    /**
+        Gets the path to an instance as a Luau expression, starting at `game` for instances
+        in a `DataModel`, or at the name of the root for instances in any other root.
+
+        Names that are not valid identifiers are quoted, such as `["My Module"]`, and names
+        that are the same as a property or method, such as `Parent`, use `FindFirstChild`.
+        Note that if siblings share the same name, the path always resolves to the first one.
+    */
+    pub fn get_instance_path(&self, id: Ref, style: InstancePathStyle) -> Option<String> {
+        let mut ids = vec![id];
+        let mut current = self.get_instance(id)?;
+        while let Some(parent) = self.get_instance(current.parent()) {
+            ids.push(parent.referent());
+            current = parent;
+        }
+        ids.reverse();
+
+        let mut path = if current.class == DATA_MODEL_CLASS_NAME {
+            String::from("game")
+        } else if is_identifier(&current.name) {
+            current.name.clone()
+        } else {
+            quote_string(&current.name)
+        };
+
+        for pair in ids.windows(2) {
+            let parent = self.get_instance(pair[0])?;
+            let child = self.get_instance(pair[1])?;
+            let is_service = parent.class == DATA_MODEL_CLASS_NAME
+                && class_is_service(&child.class)
+                && child.name == child.class;
+            let is_member = INSTANCE_MEMBERS.contains(&child.name.as_str())
+                || find_property_descriptor(&parent.class, &child.name).is_some();
+            let quoted = quote_string(&child.name);
+            match style {
+                InstancePathStyle::Dot if is_service => {
+                    path.push('.');
+                    path.push_str(&child.name);
+                }
+                InstancePathStyle::Dot if is_member => {
+                    path.push_str(&format!(":FindFirstChild({quoted})"));
+                }
+                InstancePathStyle::Dot if is_identifier(&child.name) => {
+                    path.push('.');
+                    path.push_str(&child.name);
+                }
+                InstancePathStyle::Dot => path.push_str(&format!("[{quoted}]")),
+                _ if is_service => {
+                    path.push_str(&format!(":GetService({quoted})"));
+                }
+                InstancePathStyle::FindFirstChild => {
+                    path.push_str(&format!(":FindFirstChild({quoted})"));
+                }
+                InstancePathStyle::WaitForChild => {
+                    path.push_str(&format!(":WaitForChild({quoted})"));
+                }
+            }
+        }
+
+        Some(path)
+    }
+}
+
+fn invalid_path(message: impl AsRef<str>) -> DomError {
+    DomError::new(
+        DomErrorKind::InvalidValue,
+        format!("Invalid instance path - {}", message.as_ref()),
+    )
+}
+
+/**
+    Checks if the given name is a valid Luau identifier, which can be used after a `.`
+*/
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !LUAU_KEYWORDS.contains(&name)
+}
+
+/**
+    Quotes a string as a Luau string literal, escaping any characters that need it.
+*/
+pub fn quote_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            // NOTE: Decimal escapes must be padded, since Luau reads up to
+            // three digits, and any digits following them would be included
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\{:03}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(String),
+    Dot,
+    Punct(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '.' => tokens.push(Token::Dot),
+            ':' | '[' | ']' | '(' | ')' | ',' => tokens.push(Token::Punct(c)),
+            '"' | '\'' => tokens.push(Token::Str(unescape_string(&mut chars, c)?)),
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut ident = String::from(c);
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    ident.push(c);
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            c if c.is_ascii_digit() => {
+                let mut number = String::from(c);
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '.') {
+                    number.push(c);
+                }
+                tokens.push(Token::Number(number));
+            }
+            c => return Err(format!("unexpected character `{c}`")),
+        }
+    }
+    Ok(tokens)
+}
+
+fn unescape_string(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    quote: char,
+) -> Result<String, String> {
+    let mut value = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c == quote => return Ok(value),
+            '\n' => break,
+            '\\' => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('r') => value.push('\r'),
+                Some('t') => value.push('\t'),
+                Some(c @ ('\\' | '"' | '\'')) => value.push(c),
+                Some(c) if c.is_ascii_digit() => {
+                    let mut code = c.to_digit(10).unwrap();
+                    for _ in 0..2 {
+                        match chars.next_if(char::is_ascii_digit) {
+                            Some(c) => code = code * 10 + c.to_digit(10).unwrap(),
+                            None => break,
+                        }
+                    }
+                    value.push(char::from_u32(code).ok_or("invalid escape sequence")?);
+                }
+                _ => return Err(String::from("unsupported escape sequence")),
+            },
+            c => value.push(c),
+        }
+    }
+    Err(String::from("unterminated string"))
+}
+
+#[test]
+fn parse_instance_paths() {
+    use InstancePathSegment::*;
+
+    let parse = |source: &str| InstancePath::parse(source).unwrap();
+    let child = |name: &str| Child(name.to_string());
+
+    assert_eq!(
+        parse("game.ReplicatedStorage.Shared.Util"),
+        InstancePath {
+            root: InstancePathRoot::Game,
+            segments: vec![child("ReplicatedStorage"), child("Shared"), child("Util")],
+        }
+    );
+    assert_eq!(
+        parse(r#"script.Parent:WaitForChild("Foo", 5)["Bar \"Baz\""]"#).segments,
+        vec![Parent, child("Foo"), child("Bar \"Baz\"")]
+    );
+    assert_eq!(
+        parse("game:GetService('Players'):FindFirstChild(\"Gui\", true)").segments,
+        vec![Service("Players".into()), Descendant("Gui".into())]
+    );
+    assert_eq!(
+        parse("workspace.Map").segments,
+        vec![Service("Workspace".into()), child("Map")]
+    );
+
+    assert!(InstancePath::parse("").is_err());
+    assert!(InstancePath::parse("game.").is_err());
+    assert!(InstancePath::parse("game:Destroy()").is_err());
+    assert!(InstancePath::parse("game[\"Unterminated]").is_err());
+}
+
+#[test]
+fn instance_path_round_trip() {
+    use super::{Config, InstanceNode};
+
+    let node = InstanceNode::for_test;
+
+    let mut dom = Dom::new(Config::default());
+    dom.apply_new_root(
+        0,
+        Some(node(
+            "DataModel",
+            "Project",
+            vec![node(
+                "ReplicatedStorage",
+                "ReplicatedStorage",
+                vec![node(
+                    "Folder",
+                    "Shared",
+                    vec![
+                        node("ModuleScript", "My Util", vec![]),
+                        node("ModuleScript", "\u{1}2", vec![]),
+                        node(
+                            "Folder",
+                            "Parent",
+                            vec![node("ModuleScript", "end", vec![])],
+                        ),
+                    ],
+                )],
+            )],
+        )),
+    );
+
+    let find = |source: &str, script: Option<Ref>| {
+        dom.find_by_instance_path(&InstancePath::parse(source).unwrap(), script)
+            .unwrap()
+    };
+
+    let util = find("game.ReplicatedStorage.Shared['My Util']", None).unwrap();
+    let end = find(
+        "game.ReplicatedStorage.Shared:FindFirstChild('end', true)",
+        None,
+    )
+    .unwrap();
+    let control = find(r#"game.ReplicatedStorage.Shared["\0012"]"#, None).unwrap();
+    assert_eq!(
+        dom.get_instance_path(control, InstancePathStyle::Dot)
+            .unwrap(),
+        r#"game.ReplicatedStorage.Shared["\0012"]"#
+    );
+    assert_eq!(
+        dom.get_instance_path(util, InstancePathStyle::Dot).unwrap(),
+        r#"game.ReplicatedStorage.Shared["My Util"]"#
+    );
+    assert_eq!(
+        dom.get_instance_path(end, InstancePathStyle::Dot).unwrap(),
+        r#"game.ReplicatedStorage.Shared:FindFirstChild("Parent")["end"]"#
+    );
+    assert_eq!(
+        dom.get_instance_path(util, InstancePathStyle::WaitForChild)
+            .unwrap(),
+        r#"game:GetService("ReplicatedStorage"):WaitForChild("Shared"):WaitForChild("My Util")"#
+    );
+
+    for style in [
+        InstancePathStyle::Dot,
+        InstancePathStyle::FindFirstChild,
+        InstancePathStyle::WaitForChild,
+    ] {
+        for id in [util, end, control] {
+            let path = dom.get_instance_path(id, style).unwrap();
+            assert_eq!(find(&path, None), Some(id));
+        }
+    }
+
+    assert_eq!(
+        find("script.Parent.Parent['My Util']", Some(end)),
+        Some(util)
+    );
+    assert_eq!(
+        find("script.Parent.Parent.Parent.Parent.Parent", Some(end)),
+        None
+    );
+    assert_eq!(find("game.ServerStorage.Missing", None), None);
+    assert!(dom
+        .find_by_instance_path(&InstancePath::parse("script").unwrap(), None)
+        .is_err());
+}
@@ -0,0 +1,391 @@
+use rbx_dom_weak::types::Ref;
+use rustc_hash::FxHashMap as HashMap;
+use serde_json::Value as JsonValue;
+
+use super::{
+    fs::{self, TrashedPath},
+    Dom, DomResult, InstanceNode,
+};
+
+/**
+    Maximum number of entries to keep around for undoing.
+
+    Files for deleted instances are kept in the trash directory
+    until their entries are dropped, so this should not be too large.
+*/
+const JOURNAL_LIMIT: usize = 64;
+
+/**
+    An instance that has been deleted, and all of the data needed to restore it.
+*/
+#[derive(Debug, Clone)]
+pub struct TrashedInstance {
+    /// The instance tree, with file paths from when it was deleted.
+    pub node: InstanceNode,
+    /// Ids of the instance and its descendants, in depth-first order.
+    pub ids: Vec<Ref>,
+    /// Files and directories that were deleted, and where they are in the trash.
+    pub paths: Vec<TrashedPath>,
+}
+
+/**
+    A single operation that can be applied to a dom.
+
+    Applying an operation always produces another operation that reverses it,
+    which is what makes it possible to undo and redo using the same operations.
+*/
+#[derive(Debug, Clone)]
+pub enum JournalOperation {
+    /// Restores a deleted instance into the given parent.
+    Insert {
+        parent_id: Ref,
+        trashed: TrashedInstance,
+    },
+    /// Deletes the given instance, and turns its parent back
+    /// into a file, if the parent became a directory for it.
+    Remove { id: Ref, collapse_parent: bool },
+    /// Renames the given instance.
+    Rename { id: Ref, name: String },
+    /// Moves the given instance into a new parent, turning the old parent back
+    /// into a file, if the old parent became a directory for the instance.
+    Move {
+        id: Ref,
+        parent_id: Ref,
+        collapse_parent: bool,
+    },
+    /// Sets a property of the given instance, or removes it if there is no value.
+    SetProperty {
+        id: Ref,
+        name: String,
+        value: Option<JsonValue>,
+    },
+}
+
+impl JournalOperation {
+    fn into_trashed_paths(self) -> Vec<TrashedPath> {
+        match self {
+            Self::Insert { trashed, .. } => trashed.paths,
+            _ => Vec::new(),
+        }
+    }
+
+    fn remap_ids(&mut self, id_map: &HashMap<Ref, Ref>) {
+        let remap = |id: &mut Ref| {
+            if let Some(new_id) = id_map.get(id) {
+                *id = *new_id;
+            }
+        };
+        match self {
+            Self::Insert { parent_id, .. } => remap(parent_id),
+            Self::Remove { id, .. } => remap(id),
+            Self::Rename { id, .. } => remap(id),
+            Self::SetProperty { id, .. } => remap(id),
+            Self::Move { id, parent_id, .. } => {
+                remap(id);
+                remap(parent_id);
+            }
+        }
+    }
+}
+
+/**
+    A journal of operations that can be undone and redone.
+
+    Each entry is a group of operations, which are undone or redone together.
+*/
+#[derive(Debug, Default)]
+pub struct Journal {
+    undo: Vec<Vec<JournalOperation>>,
+    redo: Vec<Vec<JournalOperation>>,
+}
+
+impl Journal {
+    /**
+        Records a new group of operations that reverse a change that was just made.
+
+        This clears out anything that could previously be redone.
+    */
+    pub fn record(&mut self, operations: Vec<JournalOperation>) {
+        if operations.is_empty() {
+            return;
+        }
+        self.undo.push(operations);
+        if self.undo.len() > JOURNAL_LIMIT {
+            discard_operations(self.undo.remove(0));
+        }
+        discard_operations(self.redo.drain(..).flatten().collect());
+    }
+
+    fn remap_ids(&mut self, id_map: &HashMap<Ref, Ref>) {
+        for operation in self.undo.iter_mut().chain(self.redo.iter_mut()).flatten() {
+            operation.remap_ids(id_map);
+        }
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        let trashed = self
+            .undo
+            .drain(..)
+            .chain(self.redo.drain(..))
+            .flatten()
+            .flat_map(JournalOperation::into_trashed_paths)
+            .collect::<Vec<_>>();
+        fs::purge_trashed_blocking(&trashed);
+    }
+}
+
+/**
+    Drops operations that will never be applied, removing
+    anything in the trash that only they could have restored.
+*/
+pub(super) fn discard_operations(operations: Vec<JournalOperation>) {
+    let trashed = operations
+        .into_iter()
+        .flat_map(JournalOperation::into_trashed_paths)
+        .collect::<Vec<_>>();
+    if trashed.is_empty() {
+        return;
+    }
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => {
+            handle.spawn(async move { fs::purge_trashed(&trashed).await });
+        }
+        Err(_) => fs::purge_trashed_blocking(&trashed),
+    }
+}
+
+impl Dom {
+    async fn apply_operation(
+        &mut self,
+        operation: JournalOperation,
+    ) -> DomResult<(JournalOperation, HashMap<Ref, Ref>)> {
+        match operation {
+            JournalOperation::Insert { parent_id, trashed } => {
+                let (id, changed_parent, id_map) =
+                    self.restore_instance_inner(parent_id, trashed).await?;
+                let reverse = JournalOperation::Remove {
+                    id,
+                    collapse_parent: changed_parent,
+                };
+                Ok((reverse, id_map))
+            }
+            JournalOperation::Remove {
+                id,
+                collapse_parent,
+            } => {
+                let (parent_id, trashed) = self.delete_instance_inner(id).await?;
+                if collapse_parent {
+                    self.collapse_instance_inner(parent_id).await;
+                }
+                let reverse = JournalOperation::Insert { parent_id, trashed };
+                Ok((reverse, HashMap::default()))
+            }
+            JournalOperation::Rename { id, name } => {
+                let previous_name = self.rename_instance_inner(id, name).await?;
+                let reverse = JournalOperation::Rename {
+                    id,
+                    name: previous_name,
+                };
+                Ok((reverse, HashMap::default()))
+            }
+            JournalOperation::Move {
+                id,
+                parent_id,
+                collapse_parent,
+            } => {
+                let (previous_parent_id, changed_parent) =
+                    self.move_instance_inner(id, parent_id).await?;
+                if collapse_parent {
+                    self.collapse_instance_inner(previous_parent_id).await;
+                }
+                let reverse = JournalOperation::Move {
+                    id,
+                    parent_id: previous_parent_id,
+                    collapse_parent: changed_parent,
+                };
+                Ok((reverse, HashMap::default()))
+            }
+            JournalOperation::SetProperty { id, name, value } => {
+                let previous = self.set_property_inner(id, name.clone(), value).await?;
+                let reverse = JournalOperation::SetProperty {
+                    id,
+                    name,
+                    value: previous,
+                };
+                Ok((reverse, HashMap::default()))
+            }
+        }
+    }
+
+    /**
+        Applies a group of operations in reverse order, returning the group that reverses
+        it, as well as the first error that occurred, if any of the operations failed.
+
+        Operations after a failed operation are still applied. Failed operations that
+        restore deleted instances are returned in a separate group, since their files
+        are still in the trash, and applying them can be tried again later.
+    */
+    pub(super) async fn apply_operations(
+        &mut self,
+        mut operations: Vec<JournalOperation>,
+    ) -> (Vec<JournalOperation>, Vec<JournalOperation>, DomResult<()>) {
+        let mut reversed = Vec::new();
+        let mut failed = Vec::new();
+        let mut result = Ok(());
+        while let Some(operation) = operations.pop() {
+            let retry =
+                matches!(operation, JournalOperation::Insert { .. }).then(|| operation.clone());
+            match self.apply_operation(operation).await {
+                Err(e) => {
+                    failed.extend(retry);
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+                Ok((reverse, id_map)) => {
+                    if !id_map.is_empty() {
+                        for operation in operations
+                            .iter_mut()
+                            .chain(reversed.iter_mut())
+                            .chain(failed.iter_mut())
+                        {
+                            operation.remap_ids(&id_map);
+                        }
+                        self.journal.remap_ids(&id_map);
+                    }
+                    reversed.push(reverse);
+                }
+            }
+        }
+        // Operations are applied in reverse order, failed ones
+        // must keep their original order to be applied again
+        failed.reverse();
+        (reversed, failed, result)
+    }
+
+    /**
+        Undoes the last recorded change, if any.
+
+        Returns `false` if there was no change to undo, or an
+        error if any part of the change could not be undone.
+        Deleted instances that could not be restored are kept
+        in the journal, so that this can be tried again.
+    */
+    pub async fn undo(&mut self) -> DomResult<bool> {
+        let operations = match self.journal.undo.pop() {
+            Some(operations) => operations,
+            None => return Ok(false),
+        };
+        let (reversed, failed, result) = self.apply_operations(operations).await;
+        if !failed.is_empty() {
+            self.journal.undo.push(failed);
+        }
+        if !reversed.is_empty() {
+            self.journal.redo.push(reversed);
+        }
+        result.map(|_| true)
+    }
+
+    /**
+        Redoes the last undone change, if any.
+
+        Returns `false` if there was no change to redo, or an
+        error if any part of the change could not be redone.
+        Deleted instances that could not be restored are kept
+        in the journal, so that this can be tried again.
+    */
+    pub async fn redo(&mut self) -> DomResult<bool> {
+        let operations = match self.journal.redo.pop() {
+            Some(operations) => operations,
+            None => return Ok(false),
+        };
+        let (reversed, failed, result) = self.apply_operations(operations).await;
+        if !failed.is_empty() {
+            self.journal.redo.push(failed);
+        }
+        if !reversed.is_empty() {
+            self.journal.undo.push(reversed);
+        }
+        result.map(|_| true)
+    }
+}
+
+#[tokio::test]
+async fn undo_redo_delete() {
+    let (mut dom, dir, module_id) = Dom::for_test("journal-delete");
+    let module_path = dir.join("src/Module.luau");
+    let trash_entries = || std::fs::read_dir(dir.join("trash")).unwrap().count();
+
+    dom.delete_instance(module_id).await.unwrap();
+    assert!(!module_path.exists());
+    assert_eq!(trash_entries(), 1);
+
+    assert!(dom.undo().await.unwrap());
+    assert!(module_path.exists());
+    assert_eq!(trash_entries(), 0);
+    let root_id = dom.get_root_id().unwrap();
+    let restored_id = dom.get_instance(root_id).unwrap().children()[0];
+    assert_eq!(dom.get_instance(restored_id).unwrap().name, "Module");
+
+    assert!(dom.redo().await.unwrap());
+    assert!(!module_path.exists());
+    assert!(dom.get_instance(restored_id).is_none());
+    assert_eq!(trash_entries(), 1);
+
+    // Anything left in the trash is removed once it can no longer be restored
+    drop(dom);
+    assert_eq!(trash_entries(), 0);
+}
+
+#[tokio::test]
+async fn undo_remaps_restored_ids() {
+    let (mut dom, dir, module_id) = Dom::for_test("journal-remap");
+    let src = dir.join("src");
+
+    dom.rename_instance(module_id, "Renamed".to_string())
+        .await
+        .unwrap();
+    dom.delete_instance(module_id).await.unwrap();
+    assert!(!src.join("Renamed.luau").exists());
+
+    // Restoring gives the instance a new id, which the earlier rename must be remapped to
+    assert!(dom.undo().await.unwrap());
+    assert!(dom.undo().await.unwrap());
+    assert!(src.join("Module.luau").exists());
+    assert!(!src.join("Renamed.luau").exists());
+    let root_id = dom.get_root_id().unwrap();
+    let restored_id = dom.get_instance(root_id).unwrap().children()[0];
+    assert_ne!(restored_id, module_id);
+    assert_eq!(dom.get_instance(restored_id).unwrap().name, "Module");
+
+    assert!(dom.redo().await.unwrap());
+    assert!(dom.redo().await.unwrap());
+    assert!(!src.join("Module.luau").exists());
+    assert!(!src.join("Renamed.luau").exists());
+    assert!(dom.undo().await.unwrap());
+    assert!(src.join("Renamed.luau").exists());
+}
+
+#[tokio::test]
+async fn undo_keeps_failed_restores() {
+    let (mut dom, dir, module_id) = Dom::for_test("journal-failed");
+    let module_path = dir.join("src/Module.luau");
+    let trash_entries = || std::fs::read_dir(dir.join("trash")).unwrap().count();
+
+    dom.delete_instance(module_id).await.unwrap();
+    std::fs::write(&module_path, "return nil").unwrap();
+
+    // Restoring onto an existing file fails, but the deleted files stay in the trash
+    let err = dom.undo().await.unwrap_err();
+    assert_eq!(err.kind(), super::DomErrorKind::NameCollision);
+    assert_eq!(trash_entries(), 1);
+    assert_eq!(std::fs::read_to_string(&module_path).unwrap(), "return nil");
+
+    // Undoing can be tried again once the file is out of the way
+    std::fs::remove_file(&module_path).unwrap();
+    assert!(dom.undo().await.unwrap());
+    assert_eq!(trash_entries(), 0);
+    assert_eq!(std::fs::read_to_string(&module_path).unwrap(), "return {}");
+}
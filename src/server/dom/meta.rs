@@ -1,11 +1,20 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use serde::Serialize;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
 use rbx_dom_weak::types::Ref;
 
-use crate::util::path::make_absolute_and_clean;
+use crate::util::{
+    path::make_absolute_and_clean,
+    rojo::is_roblox_model_path,
+    wally::{
+        WallyLockfile, WallyManifest, WallyRealm, WALLY_LOCKFILE_FILE_NAME,
+        WALLY_MANIFEST_FILE_NAME,
+    },
+};
 
 use super::util::*;
 use super::Dom;
@@ -19,6 +28,8 @@ pub struct InstanceMetadata {
     pub actions: Option<InstanceMetadataActions>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub paths: Option<InstanceMetadataPaths>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<InstanceMetadataProject>,
 }
 
 impl InstanceMetadata {
@@ -32,7 +43,6 @@ impl InstanceMetadata {
         which would have also meant that no useful props would have been serializable.
     */
     pub fn new(id: Ref, dom: &Dom, file_paths: &[PathBuf]) -> Option<Self> {
-        let mut package = None;
         let mut actions = InstanceMetadataActions::default();
         let mut paths = InstanceMetadataPaths::default();
 
@@ -43,9 +53,9 @@ impl InstanceMetadata {
             };
             // NOTE: We don't actually check the filesystem for if this is correct, but that's
             // completely fine, since we _should_ be getting valid paths from others already
-            if file_name == "wally.toml" {
+            if file_name == WALLY_MANIFEST_FILE_NAME {
                 paths.wally = Some(file_path.to_owned());
-            } else if file_name == "wally.lock" {
+            } else if file_name == WALLY_LOCKFILE_FILE_NAME {
                 paths.wally_lock = Some(file_path.to_owned());
             } else if file_name.ends_with(".project.json") {
                 paths.rojo = Some(file_path.to_owned());
@@ -58,6 +68,14 @@ impl InstanceMetadata {
             }
         }
 
+        // Instances with a folder of their own may also have children with files in it
+        let is_folder_instance = paths.folder.is_some()
+            || paths
+                .file
+                .as_deref()
+                .and_then(path_file_name)
+                .is_some_and(|name| name.starts_with("init."));
+
         // If we got a file path then that means we should also
         // know the parent folder path, so use that as a backup
         if paths.folder.is_none() {
@@ -83,22 +101,25 @@ impl InstanceMetadata {
         let parent = dom.get_instance(instance.parent());
         let parent_meta = parent.and_then(|inst| dom.get_metadata(inst.referent()));
 
-        let is_root = matches!(dom.get_root_id(), Some(i) if i == id);
+        let is_root = dom.is_root(id);
         let is_datamodel = instance.class == "DataModel";
 
-        if let Some(parent_package) = parent_meta.and_then(|meta| meta.package.as_ref()) {
-            // If the parent is part of a package, this instance must
-            // be too, so there's no need to do more complicated checks
-            package = Some(InstanceMetadataPackage::from_parent(parent_package));
-        } else if let Some(file) = paths.file.as_deref() {
-            // If we got a package file path, we may be able
-            // to parse useful Wally package metadata out of it
-            package = InstanceMetadataPackage::from_path(file);
-        }
+        // Instances inside of model files are read-only, since they are not file-backed
+        let is_model = paths.file.as_deref().is_some_and(is_roblox_model_path);
+        let is_in_model = parent_meta
+            .and_then(|meta| meta.paths.as_ref())
+            .and_then(|paths| paths.file.as_deref())
+            .is_some_and(is_roblox_model_path);
+
+        let package = InstanceMetadataPackage::from_parent_or_path(
+            parent_meta.and_then(|meta| meta.package.as_ref()),
+            paths.file.as_deref(),
+            dom,
+        );
 
         // If we *still* don't have a file or folder path, but we know that this instance
         // is a folder, and the parent has a folder path, we can derive a folder path
-        if paths.folder.is_none() && instance.class == "Folder" {
+        if paths.folder.is_none() && instance.class == "Folder" && !is_in_model {
             if let Some(parent_folder) = parent_meta
                 .and_then(|meta| meta.paths.as_ref())
                 .and_then(|paths| paths.folder.as_deref())
@@ -142,18 +163,75 @@ impl InstanceMetadata {
             .unwrap_or_default();
         actions.can_paste_into = actions.can_insert_object;
 
+        /*
+            - Instances can not be moved if any of their files or folders, or files
+              for children inside of their folder (if they have one), are protected
+            - Nothing can be inserted or pasted into a folder if its children would be protected
+            - Nothing can be done at all if the server is read-only
+
+            NOTE: We don't know the names of any children that may be created, so we check
+            a wildcard child path, which matches any pattern protecting children of a folder
+        */
+        let config = dom.config();
+        let can_modify_children = |folder: &Path| {
+            config.is_writable_path(folder) && config.is_writable_path(&folder.join("*"))
+        };
+        let can_modify_self = paths.into_iter().all(|path| config.is_writable_path(path));
+        let can_modify_folder =
+            !is_folder_instance || paths.folder.as_deref().is_some_and(can_modify_children);
+        if config.read_only || !can_modify_self || !can_modify_folder {
+            actions.can_move = false;
+        }
+        if !paths.folder.as_deref().is_some_and(can_modify_children) {
+            actions.can_insert_object = false;
+            actions.can_insert_service = false;
+            actions.can_paste_into = false;
+        }
+        if !parent_meta
+            .and_then(|meta| meta.paths.as_ref())
+            .and_then(|paths| paths.folder.as_deref())
+            .is_some_and(can_modify_children)
+            || is_in_model
+        {
+            actions.can_paste_sibling = false;
+        }
+        if is_model {
+            actions.can_insert_object = false;
+            actions.can_insert_service = false;
+            actions.can_paste_into = false;
+        }
+
         // Only return metadata if it actually has useful data inside of it
         let this = Self {
             package,
             actions: actions.data_or_none(),
             paths: paths.data_or_none().map(|p| p.make_absolute_and_clean()),
+            project: None,
         };
 
         this.data_or_none()
     }
 
+    /**
+        Sets the project files for this metadata, which should only be done for roots.
+
+        The Wally manifest and lockfile paths are always replaced by the ones in the
+        project, since those are watched and known to be up-to-date for roots.
+    */
+    pub fn with_project(mut self, project: &InstanceMetadataProject) -> Self {
+        let mut paths = self.paths.take().unwrap_or_default();
+        paths.wally = project.wally.as_ref().map(|file| file.path.clone());
+        paths.wally_lock = project.wally_lock.as_ref().map(|file| file.path.clone());
+        self.paths = paths.data_or_none();
+        self.project = project.clone().data_or_none();
+        self
+    }
+
     fn contains_data(&self) -> bool {
-        self.package.is_some() || self.actions.is_some() || self.paths.is_some()
+        self.package.is_some()
+            || self.actions.is_some()
+            || self.paths.is_some()
+            || self.project.is_some()
     }
 
     fn data_or_none(self) -> Option<Self> {
@@ -180,9 +258,48 @@ pub struct InstanceMetadataPackage {
     /// If this instance is the root instance of the Wally package or not.
     #[serde(skip_serializing_if = "is_false")]
     pub is_root: bool,
+    /// Realm of the Wally package, from the packages folder it is installed in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realm: Option<WallyRealm>,
+    /// Realm of the dependency in the Wally manifest, if it is a direct dependency.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dependency: Option<WallyRealm>,
+    /// Version requirement in the Wally manifest, if it is a direct dependency.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requirement: Option<Arc<String>>,
+    /// Version of the Wally package that the lockfile resolved, if there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_version: Option<Arc<String>>,
+    /// If the installed version does not match the lockfile, or the lockfile does not match the manifest.
+    #[serde(skip_serializing_if = "is_false")]
+    pub outdated: bool,
 }
 
 impl InstanceMetadataPackage {
+    /**
+        Creates package metadata for an instance, given the package metadata of its
+        parent, if any, and its file path, if it has one. Returns `None` if the
+        instance is not part of a package.
+
+        Information from the Wally manifest and lockfile is read when this is
+        called, so this should be called again if either of those change.
+    */
+    pub(super) fn from_parent_or_path(
+        parent_package: Option<&Self>,
+        file_path: Option<&Path>,
+        dom: &Dom,
+    ) -> Option<Self> {
+        if let Some(parent_package) = parent_package {
+            // If the parent is part of a package, this instance must
+            // be too, so there's no need to do more complicated checks
+            Some(Self::from_parent(parent_package))
+        } else {
+            // If we got a package file path, we may be able
+            // to parse useful Wally package metadata out of it
+            Self::from_path(file_path?, dom)
+        }
+    }
+
     fn from_parent(parent_meta: &Self) -> Self {
         if parent_meta.is_root {
             Self {
@@ -194,20 +311,20 @@ impl InstanceMetadataPackage {
         }
     }
 
-    fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+    fn from_path(path: impl AsRef<Path>, dom: &Dom) -> Option<Self> {
         let path = path.as_ref();
 
         // Look for a folder inside of another named '_Index'
-        let mut found_index = false;
+        let mut found_index = None;
         let mut found_folder = None;
         let mut found_inner = None;
-        for component in path.components() {
+        for (depth, component) in path.components().enumerate() {
             if let Some(component_str) = component.as_os_str().to_str() {
-                if !found_index && component_str == "_Index" {
-                    found_index = true;
-                } else if found_index && found_folder.is_none() {
+                if found_index.is_none() && component_str == "_Index" {
+                    found_index = Some(depth);
+                } else if found_index.is_some() && found_folder.is_none() {
                     found_folder = Some(component_str);
-                } else if found_index && found_folder.is_some() {
+                } else if found_index.is_some() && found_folder.is_some() {
                     found_inner = Some(component_str);
                     break;
                 }
@@ -217,21 +334,46 @@ impl InstanceMetadataPackage {
         }
 
         // Make sure we got both components
+        let index_depth = found_index?;
         let package_folder = found_folder?;
         let package_inner = found_inner?;
 
+        // The '_Index' folder is inside of a packages folder, which is next to the
+        // Wally manifest and lockfile, and has a different name for each realm
+        let packages_dir = path.components().take(index_depth).collect::<PathBuf>();
+        let realm = packages_dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(WallyRealm::from_packages_dir_name);
+
         // If we found a matching folder, split it by '_' and '@'
         // Example wally package folder name: 'scope_package@1.0.0'
         // Any instance with the exact name of the package inside of
         // that folder is also guaranteed to be the "root" of the package
         if let Some((scope, rest)) = package_folder.split_once('_') {
             if let Some((name, version)) = rest.split_once('@') {
-                return Some(Self {
+                let mut this = Self {
                     scope: Arc::new(scope.to_owned()),
                     name: Arc::new(name.to_owned()),
                     version: Arc::new(version.to_owned()),
                     is_root: package_inner == name,
-                });
+                    realm,
+                    dependency: None,
+                    requirement: None,
+                    resolved_version: None,
+                    outdated: false,
+                };
+                let project = packages_dir
+                    .parent()
+                    .and_then(|dir| dom.wally_projects().get(dir));
+                if let Some(project) = project {
+                    let package = project.package(scope, name, version);
+                    this.dependency = package.dependency;
+                    this.requirement = package.requirement.map(Arc::new);
+                    this.resolved_version = package.resolved_version.map(Arc::new);
+                    this.outdated = package.outdated;
+                }
+                return Some(this);
             }
         }
 
@@ -361,3 +503,246 @@ impl<'a> IntoIterator for &'a InstanceMetadataPaths {
         self.existing_paths().into_iter()
     }
 }
+
+/**
+    Files at the root of a project, such as its Wally manifest and toolchain
+    manager config, which are watched and summarized for root instances.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceMetadataProject {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wally: Option<InstanceMetadataProjectFile<ProjectWally>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wally_lock: Option<InstanceMetadataProjectFile<ProjectWallyLock>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aftman: Option<InstanceMetadataProjectFile<ProjectToolchain>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rokit: Option<InstanceMetadataProjectFile<ProjectToolchain>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selene: Option<InstanceMetadataProjectFile<ProjectSelene>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub luaurc: Option<InstanceMetadataProjectFile<ProjectLuaurc>>,
+}
+
+impl InstanceMetadataProject {
+    /**
+        All of the file names that are part of a project, which must be next to its project file.
+    */
+    pub const FILE_NAMES: &'static [&'static str] = &[
+        WALLY_MANIFEST_FILE_NAME,
+        WALLY_LOCKFILE_FILE_NAME,
+        "aftman.toml",
+        "rokit.toml",
+        "selene.toml",
+        ".luaurc",
+    ];
+
+    /**
+        Updates the file at the given path, given its new contents, or removes it if there are none.
+
+        Returns `true` if the path was for one of the files in the project, even if it did not change.
+    */
+    pub fn apply_file(&mut self, path: &Path, contents: Option<&str>) -> bool {
+        let path = make_absolute_and_clean(path);
+        match path_file_name(&path) {
+            Some(WALLY_MANIFEST_FILE_NAME) => self.wally = project_file(path, contents),
+            Some(WALLY_LOCKFILE_FILE_NAME) => self.wally_lock = project_file(path, contents),
+            Some("aftman.toml") => self.aftman = project_file(path, contents),
+            Some("rokit.toml") => self.rokit = project_file(path, contents),
+            Some("selene.toml") => self.selene = project_file(path, contents),
+            Some(".luaurc") => self.luaurc = project_file(path, contents),
+            _ => return false,
+        }
+        true
+    }
+
+    fn contains_data(&self) -> bool {
+        self.wally.is_some()
+            || self.wally_lock.is_some()
+            || self.aftman.is_some()
+            || self.rokit.is_some()
+            || self.selene.is_some()
+            || self.luaurc.is_some()
+    }
+
+    fn data_or_none(self) -> Option<Self> {
+        if self.contains_data() {
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+/**
+    A single file in a project, with a summary of its contents,
+    or an error message if its contents could not be parsed.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceMetadataProjectFile<T> {
+    pub path: PathBuf,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/**
+    A summary of the contents of a file in a project.
+*/
+trait ProjectFileSummary: Sized {
+    fn parse(contents: &str) -> Result<Self>;
+}
+
+fn project_file<T: ProjectFileSummary>(
+    path: PathBuf,
+    contents: Option<&str>,
+) -> Option<InstanceMetadataProjectFile<T>> {
+    let (summary, error) = match T::parse(contents?) {
+        Ok(summary) => (Some(summary), None),
+        Err(e) => (None, Some(format!("{e:#}"))),
+    };
+    Some(InstanceMetadataProjectFile {
+        path,
+        summary,
+        error,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectWally {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realm: Option<WallyRealm>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub server_dependencies: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub dev_dependencies: BTreeMap<String, String>,
+}
+
+impl ProjectFileSummary for ProjectWally {
+    fn parse(contents: &str) -> Result<Self> {
+        let manifest: WallyManifest = toml::from_str(contents)?;
+        let package = manifest.package.as_ref();
+        Ok(Self {
+            name: package.map(|p| p.name.clone()),
+            version: package.map(|p| p.version.clone()),
+            realm: package.and_then(|p| p.realm),
+            dependencies: manifest.dependencies,
+            server_dependencies: manifest.server_dependencies,
+            dev_dependencies: manifest.dev_dependencies,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectWallyLock {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Resolved packages, such as `scope/name@1.0.0`.
+    pub packages: Vec<String>,
+}
+
+impl ProjectFileSummary for ProjectWallyLock {
+    fn parse(contents: &str) -> Result<Self> {
+        let lockfile: WallyLockfile = toml::from_str(contents)?;
+        Ok(Self {
+            registry: lockfile.registry,
+            packages: lockfile
+                .packages
+                .into_iter()
+                .map(|p| format!("{}@{}", p.name, p.version))
+                .collect(),
+        })
+    }
+}
+
+/**
+    A toolchain manager config, such as `aftman.toml` or `rokit.toml`, which share the same format.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectToolchain {
+    /// Tools by alias, such as `rojo = "rojo-rbx/rojo@7.4.0"`.
+    #[serde(default)]
+    pub tools: BTreeMap<String, String>,
+}
+
+impl ProjectFileSummary for ProjectToolchain {
+    fn parse(contents: &str) -> Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectSelene {
+    /// Standard library, such as `roblox`, or multiple joined with `+`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub std: Option<String>,
+}
+
+impl ProjectFileSummary for ProjectSelene {
+    fn parse(contents: &str) -> Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectLuaurc {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_mode: Option<String>,
+    /// Require aliases, such as `"Packages": "Packages"` for `@Packages/...`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl ProjectFileSummary for ProjectLuaurc {
+    fn parse(contents: &str) -> Result<Self> {
+        Ok(serde_json::from_str(contents)?)
+    }
+}
+
+#[test]
+fn parse_project_files() {
+    let mut project = InstanceMetadataProject::default();
+
+    assert!(project.apply_file(
+        Path::new("rokit.toml"),
+        Some("[tools]\nrojo = \"rojo-rbx/rojo@7.4.4\"\n"),
+    ));
+    assert!(project.apply_file(
+        Path::new(".luaurc"),
+        Some(r#"{ "languageMode": "strict", "aliases": { "Packages": "Packages" } }"#),
+    ));
+    assert!(project.apply_file(Path::new("selene.toml"), Some("std = [")));
+    assert!(!project.apply_file(Path::new("default.project.json"), Some("{}")));
+
+    let rokit = project
+        .rokit
+        .as_ref()
+        .and_then(|f| f.summary.as_ref())
+        .unwrap();
+    assert_eq!(rokit.tools["rojo"], "rojo-rbx/rojo@7.4.4");
+    let luaurc = project
+        .luaurc
+        .as_ref()
+        .and_then(|f| f.summary.as_ref())
+        .unwrap();
+    assert_eq!(luaurc.language_mode.as_deref(), Some("strict"));
+    assert!(project.selene.as_ref().is_some_and(|f| f.error.is_some()));
+
+    // Removed files are no longer part of the project
+    project.apply_file(Path::new("rokit.toml"), None);
+    assert!(project.rokit.is_none());
+}
@@ -3,26 +3,55 @@
 use std::path::{Path, PathBuf};
 
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use rbx_dom_weak::{types::Ref, Instance, InstanceBuilder, WeakDom};
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    Instance, InstanceBuilder, WeakDom,
+};
 use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio_util::sync::CancellationToken;
 
+mod batch;
+mod error;
 mod fs;
+mod instance_path;
+mod journal;
 mod meta;
 mod node;
+mod properties;
 mod query;
+mod query_filter;
+mod requires;
+mod search;
 mod util;
+mod wally;
 
+pub use batch::*;
+pub use error::*;
+pub use instance_path::*;
 pub use meta::*;
 pub use node::*;
+pub use properties::*;
 pub use query::*;
+pub use query_filter::*;
+pub use requires::*;
+pub use search::*;
+pub use wally::*;
 
-use super::Config;
-use crate::util::path::make_absolute_and_clean;
+use journal::{Journal, JournalOperation, TrashedInstance};
 
-// NOTE: If anyone ever names their root instance this, things may break... let's hope they don't
+use super::Config;
+#[cfg(test)]
+use crate::util::path::TestDir;
+use crate::util::{
+    path::make_absolute_and_clean,
+    wally::{WALLY_LOCKFILE_FILE_NAME, WALLY_MANIFEST_FILE_NAME},
+};
+
+// NOTE: The root of the weak dom is never exposed, our roots are instances without a parent
 const DOM_ROOT_NAME_NONE: &str = "<|<|<|ROOT|>|>|>";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -41,6 +70,8 @@ pub enum DomNotification {
         class_name: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        properties: Option<Vec<String>>,
     },
     Added {
         #[serde(skip_serializing_if = "Option::is_none", rename = "parentId")]
@@ -58,12 +89,21 @@ pub enum DomNotification {
 
 #[derive(Debug)]
 pub struct Dom {
-    _config: Config,
+    config: Config,
     inner: WeakDom,
     ids: HashSet<Ref>,
     metas: HashMap<Ref, InstanceMetadata>,
     path_map: HashMap<PathBuf, Ref>,
-    root_meta: InstanceMetadata,
+    provider_ids: HashMap<String, Ref>,
+    provider_refs: HashMap<Ref, String>,
+    roots: Vec<Option<Ref>>,
+    root_projects: Vec<InstanceMetadataProject>,
+    clipboard: Vec<Ref>,
+    journal: Journal,
+    requires: ScriptRequires,
+    search: DomSearchIndex,
+    wally: WallyProjects,
+    notification_buffer: Option<Vec<DomNotification>>,
     notification_tx: UnboundedSender<DomNotification>,
     notification_rx: Option<UnboundedReceiver<DomNotification>>,
 }
@@ -71,38 +111,104 @@ pub struct Dom {
 impl Dom {
     pub fn new(config: Config) -> Self {
         let (notification_tx, notification_rx) = unbounded_channel();
+        let roots = vec![None; config.roots.len()];
+        let root_projects = vec![InstanceMetadataProject::default(); config.roots.len()];
         Self {
-            _config: config,
+            config,
             inner: WeakDom::new(InstanceBuilder::new(DOM_ROOT_NAME_NONE)),
             ids: HashSet::default(),
             metas: HashMap::default(),
             path_map: HashMap::default(),
-            root_meta: InstanceMetadata::default(),
+            provider_ids: HashMap::default(),
+            provider_refs: HashMap::default(),
+            roots,
+            root_projects,
+            clipboard: Vec::new(),
+            journal: Journal::default(),
+            requires: ScriptRequires::default(),
+            search: DomSearchIndex::default(),
+            wally: WallyProjects::default(),
+            notification_buffer: None,
             notification_tx,
             notification_rx: Some(notification_rx),
         }
     }
 
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn wally_projects(&self) -> &WallyProjects {
+        &self.wally
+    }
+
     pub fn take_notification_receiver(&mut self) -> Option<UnboundedReceiver<DomNotification>> {
         self.notification_rx.take()
     }
 
-    fn notify(&self, notification: DomNotification) {
-        // NOTE: Not having any listeners is fine and is the only error case
-        self.notification_tx.send(notification).ok();
+    fn notify(&mut self, notification: DomNotification) {
+        if let Some(buffer) = self.notification_buffer.as_mut() {
+            buffer.push(notification);
+        } else {
+            // NOTE: Not having any listeners is fine and is the only error case
+            self.notification_tx.send(notification).ok();
+        }
+    }
+
+    fn map_paths(&mut self, id: Ref) {
+        if let Some(paths) = self.get_metadata(id).and_then(|meta| meta.paths.as_ref()) {
+            let paths = paths.into_iter().map(Path::to_path_buf).collect::<Vec<_>>();
+            for path in paths {
+                self.path_map.insert(path, id);
+            }
+        }
+    }
+
+    fn unmap_paths(&mut self, id: Ref) {
+        if let Some(paths) = self.get_metadata(id).and_then(|meta| meta.paths.as_ref()) {
+            // NOTE: Folder paths may be shared with a parent or sibling,
+            // so we must only remove paths that actually point to us
+            let paths = paths
+                .into_iter()
+                .filter(|path| self.path_map.get(*path) == Some(&id))
+                .map(Path::to_path_buf)
+                .collect::<Vec<_>>();
+            for path in paths {
+                self.path_map.remove(&path);
+            }
+        }
+    }
+
+    fn map_provider_id(&mut self, id: Ref, provider_id: Option<String>) {
+        if self.provider_refs.get(&id) == provider_id.as_ref() {
+            return;
+        }
+        self.unmap_provider_id(id);
+        if let Some(provider_id) = provider_id {
+            self.provider_ids.insert(provider_id.clone(), id);
+            self.provider_refs.insert(id, provider_id);
+        }
+    }
+
+    fn unmap_provider_id(&mut self, id: Ref) {
+        if let Some(provider_id) = self.provider_refs.remove(&id) {
+            if self.provider_ids.get(&provider_id) == Some(&id) {
+                self.provider_ids.remove(&provider_id);
+            }
+        }
     }
 
     fn insert_instance_into_dom(&mut self, parent_id: Ref, node: InstanceNode) -> Ref {
-        let inst = InstanceBuilder::new(node.class_name).with_name(node.name);
+        let inst = InstanceBuilder::new(node.class_name)
+            .with_name(node.name)
+            .with_properties(node.properties.0);
         let id = self.inner.insert(parent_id, inst);
+        self.map_provider_id(id, node.provider_id);
+        self.search.index(&self.inner, id);
 
         if let Some(meta) = InstanceMetadata::new(id, self, &node.file_paths) {
-            if let Some(paths) = &meta.paths {
-                for path in paths {
-                    self.path_map.insert(path.to_path_buf(), id);
-                }
-            }
             self.metas.insert(id, meta);
+            self.map_paths(id);
         }
 
         // NOTE: Children must be inserted *after* this new instance, since proper
@@ -117,15 +223,11 @@ impl Dom {
 
     fn remove_instance_from_dom(&mut self, id: Ref) {
         self.ids.remove(&id);
+        self.unmap_provider_id(id);
+        self.search.remove(id);
+        self.unmap_paths(id);
         self.metas.remove(&id);
         if let Some(inst) = self.inner.get_by_ref(id) {
-            if let Some(meta) = self.metas.get(&id) {
-                if let Some(paths) = &meta.paths {
-                    for path in paths {
-                        self.path_map.remove(path);
-                    }
-                }
-            }
             for child_id in inst.children().to_vec() {
                 self.remove_instance_from_dom(child_id);
             }
@@ -150,46 +252,120 @@ impl Dom {
                 MatchFilter::Strict => inst.name == node.name && inst.class == node.class_name,
                 MatchFilter::Any => inst.name == node.name || inst.class == node.class_name,
             };
-        // NOTE: We iterate in reverse order since we may remove items during iteration
-        let cloned = known_ids.to_vec();
-        for (id_idx, id) in cloned.iter().enumerate().rev() {
+        if known_ids.is_empty() || new_nodes.is_empty() {
+            return;
+        }
+
+        // NOTE: Nodes are indexed by name (and class for the least strict filter) so that
+        // matching is linear in the amount of siblings, instead of being quadratic, since
+        // instances with thousands of children are common in larger projects
+        let mut nodes_by_name = HashMap::<&str, Vec<usize>>::default();
+        let mut nodes_by_class = HashMap::<&str, Vec<usize>>::default();
+        for (idx, node) in new_nodes.iter().enumerate() {
+            nodes_by_name.entry(&node.name).or_default().push(idx);
+            if filter == MatchFilter::Any {
+                nodes_by_class
+                    .entry(&node.class_name)
+                    .or_default()
+                    .push(idx);
+            }
+        }
+
+        // NOTE: We iterate in reverse order to match the order in which ids were matched before
+        let mut taken_nodes = vec![false; new_nodes.len()];
+        let mut matched_ids = vec![false; known_ids.len()];
+        let mut matches = Vec::new();
+        for (id_idx, id) in known_ids.iter().enumerate().rev() {
             let inst = self
                 .inner
                 .get_by_ref(*id)
                 .expect("unexpectedly missing instance");
-            let found = new_nodes.iter().enumerate().find_map(|(idx, node)| {
-                if match_instance_with_level(inst, node, filter) {
-                    Some(idx)
-                } else {
-                    None
+            let find_in = |candidates: Option<&Vec<usize>>| {
+                candidates?.iter().copied().find(|idx| {
+                    !taken_nodes[*idx] && match_instance_with_level(inst, &new_nodes[*idx], filter)
+                })
+            };
+            let found = match filter {
+                MatchFilter::Any => {
+                    let by_name = find_in(nodes_by_name.get(inst.name.as_str()));
+                    let by_class = find_in(nodes_by_class.get(inst.class.as_str()));
+                    by_name.into_iter().chain(by_class).min()
                 }
-            });
-            // Insert the match into results if we got one, and remove from ids + nodes
+                _ => find_in(nodes_by_name.get(inst.name.as_str())),
+            };
             if let Some(node_idx) = found {
-                let node = new_nodes.remove(node_idx);
-                result_map.insert(*id, node);
-                known_ids.remove(id_idx);
+                taken_nodes[node_idx] = true;
+                matched_ids[id_idx] = true;
+                matches.push((*id, node_idx));
             }
         }
+
+        // Insert matches into results, and remove them from ids + nodes
+        let mut nodes = new_nodes.drain(..).map(Some).collect::<Vec<_>>();
+        for (id, node_idx) in matches {
+            result_map.insert(id, nodes[node_idx].take().unwrap());
+        }
+        new_nodes.extend(nodes.into_iter().flatten());
+        let mut matched_ids = matched_ids.into_iter();
+        known_ids.retain(|_| !matched_ids.next().unwrap());
     }
 
     fn apply_metadata(&mut self, id: Ref, file_paths: &[PathBuf]) -> bool {
-        if id != self.inner.root_ref() {
-            let new_meta = InstanceMetadata::new(id, self, file_paths);
-            if self.get_metadata(id) != new_meta.as_ref() {
-                match new_meta {
-                    Some(meta) => self.metas.insert(id, meta),
-                    None => self.metas.remove(&id),
-                };
-                true
-            } else {
-                false
-            }
+        let mut new_meta = InstanceMetadata::new(id, self, file_paths);
+        if let Some(index) = self.roots.iter().position(|root| *root == Some(id)) {
+            // Roots always have metadata, even if it is empty, and also have project files
+            let meta = new_meta.take().unwrap_or_default();
+            new_meta = Some(meta.with_project(&self.root_projects[index]));
+        }
+        if self.get_metadata(id) != new_meta.as_ref() {
+            self.unmap_paths(id);
+            match new_meta {
+                Some(meta) => self.metas.insert(id, meta),
+                None => self.metas.remove(&id),
+            };
+            self.map_paths(id);
+            true
         } else {
             false
         }
     }
 
+    /**
+        Creates package metadata again for the given instance and all of its descendants
+        that are part of a package, emitting a changed notification for any that differ.
+    */
+    fn apply_package_metadata(&mut self, id: Ref) {
+        // NOTE: Subtree ids are in depth-first order, so parents always get
+        // their new package metadata before it is passed down to children
+        for id in self.subtree_ids(id) {
+            let Some(meta) = self.get_metadata(id) else {
+                continue;
+            };
+            if meta.package.is_none() {
+                continue;
+            }
+            let parent_package = self
+                .get_instance(id)
+                .and_then(|inst| self.get_metadata(inst.parent()))
+                .and_then(|meta| meta.package.as_ref());
+            let file_path = meta.paths.as_ref().and_then(|paths| paths.file.as_deref());
+            let package =
+                InstanceMetadataPackage::from_parent_or_path(parent_package, file_path, self);
+            if package == meta.package {
+                continue;
+            }
+            if let Some(meta) = self.metas.get_mut(&id) {
+                meta.package = package;
+            }
+            self.notify(DomNotification::Changed {
+                id,
+                class_name: None,
+                name: None,
+                properties: None,
+            });
+        }
+    }
+
     fn apply_changes(&mut self, id: Ref, node: &InstanceNode) -> Option<DomNotification> {
         let inst = self.inner.get_by_ref(id).unwrap();
 
@@ -205,9 +381,15 @@ impl Dom {
             None
         };
 
+        let changed_properties = changed_property_names(&inst.properties, &node.properties);
+
         let changed_meta = self.apply_metadata(id, &node.file_paths);
 
-        if changed_class_name.is_some() || changed_name.is_some() || changed_meta {
+        if changed_class_name.is_some()
+            || changed_name.is_some()
+            || changed_properties.is_some()
+            || changed_meta
+        {
             let inst_mut = self.inner.get_by_ref_mut(id).unwrap();
 
             if let Some(new_class_name) = changed_class_name {
@@ -216,11 +398,18 @@ impl Dom {
             if let Some(new_name) = changed_name {
                 new_name.clone_into(&mut inst_mut.name);
             }
+            if changed_properties.is_some() {
+                inst_mut.properties.clone_from(&node.properties.0);
+            }
+            if changed_name.is_some() {
+                self.search.index(&self.inner, id);
+            }
 
             Some(DomNotification::Changed {
                 id,
                 class_name: changed_class_name.map(ToOwned::to_owned),
                 name: changed_name.map(ToOwned::to_owned),
+                properties: changed_properties,
             })
         } else {
             None
@@ -229,7 +418,7 @@ impl Dom {
 
     fn apply_children(
         &mut self,
-        parent_id: Option<Ref>,
+        parent_id: Ref,
         mut ids: Vec<Ref>,
         mut nodes: Vec<InstanceNode>,
     ) -> Vec<DomNotification> {
@@ -240,68 +429,20 @@ impl Dom {
             // but just in case it does happen, we can safely ignore it
         } else if ids.is_empty() {
             // Case #2 - all children were added
-            if let Some(pid) = parent_id {
-                // Non-root children were added
-                for child_node in nodes {
-                    let child_id = self.insert_instance_into_dom(pid, child_node);
-                    notifications.push(DomNotification::Added {
-                        parent_id: Some(pid),
-                        child_id,
-                    })
-                }
-            } else {
-                // Root was added
-                let node = nodes.pop().unwrap();
-
-                let root_id = self.inner.root_ref();
-                let root = self.inner.root_mut();
-
-                // Overwrite root instance properties with the ones from root node
-                node.name.clone_into(&mut root.name);
-                node.class_name.clone_into(&mut root.class);
-
-                // Use our insert_instance method to create the entire instance tree
-                let temp_id = self.insert_instance_into_dom(Ref::none(), node);
-                let temp_child_ids = self.inner.get_by_ref(temp_id).unwrap().children().to_vec();
-
-                // Transfer over children from our temp instance to the real root
-                for child_id in temp_child_ids {
-                    self.inner.transfer_within(child_id, root_id)
-                }
-
+            for child_node in nodes {
+                let child_id = self.insert_instance_into_dom(parent_id, child_node);
                 notifications.push(DomNotification::Added {
-                    parent_id: None,
-                    child_id: root_id,
+                    parent_id: Some(parent_id),
+                    child_id,
                 })
             }
         } else if nodes.is_empty() {
             // Case #3 - all children were removed
-            if let Some(pid) = parent_id {
-                // Non-root children were removed
-                for child_id in ids {
-                    self.remove_instance_from_dom(child_id);
-                    notifications.push(DomNotification::Removed {
-                        parent_id: Some(pid),
-                        child_id,
-                    })
-                }
-            } else {
-                // Root was removed
-                let root_id = self.inner.root_ref();
-                let root = self.inner.root_mut();
-
-                // We cant remove the root of a weak dom completely, so we have to replace
-                // name & class with dummy properties and manually clear out its children
-                root.name = String::from(DOM_ROOT_NAME_NONE);
-                root.class = String::from(DOM_ROOT_NAME_NONE);
-                for child_id in root.children().to_vec() {
-                    self.inner.destroy(child_id);
-                }
-                self.metas.clear();
-
+            for child_id in ids {
+                self.remove_instance_from_dom(child_id);
                 notifications.push(DomNotification::Removed {
-                    parent_id: None,
-                    child_id: root_id,
+                    parent_id: Some(parent_id),
+                    child_id,
                 })
             }
         } else {
@@ -314,47 +455,46 @@ impl Dom {
             self.match_ids_to_nodes(&mut map, &mut ids, &mut nodes, MatchFilter::Strict);
             self.match_ids_to_nodes(&mut map, &mut ids, &mut nodes, MatchFilter::Any);
 
-            // Any old instance that was not matched must have been removed, and in that
-            // case we should also have a parent, since any handling of root nodes
-            // being added / removed should have been handled in cases #1 -> #3
-            if !ids.is_empty() {
-                let pid = parent_id.expect("what the heck");
-                for child_id in ids {
-                    self.remove_instance_from_dom(child_id);
-                    notifications.push(DomNotification::Removed {
-                        parent_id: Some(pid),
-                        child_id,
-                    })
-                }
+            // Any old instance that was not matched must have been removed
+            for child_id in ids {
+                self.remove_instance_from_dom(child_id);
+                notifications.push(DomNotification::Removed {
+                    parent_id: Some(parent_id),
+                    child_id,
+                })
             }
 
-            // Any new node that was not matched was probably added - same parent semantics as above
-            if !nodes.is_empty() {
-                let pid = parent_id.expect("what the heck");
-                for child_node in nodes {
-                    let child_id = self.insert_instance_into_dom(pid, child_node);
-                    notifications.push(DomNotification::Added {
-                        parent_id: Some(pid),
-                        child_id,
-                    })
-                }
+            // Any new node that was not matched was probably added
+            for child_node in nodes {
+                let child_id = self.insert_instance_into_dom(parent_id, child_node);
+                notifications.push(DomNotification::Added {
+                    parent_id: Some(parent_id),
+                    child_id,
+                })
             }
 
             // Everything else needs to be checked for changes
             for (id, node) in map {
-                notifications.extend(self.apply_changes(id, &node));
-                let inst = self.inner.get_by_ref(id).expect("missing child");
-                notifications.extend(self.apply_children(
-                    Some(id),
-                    inst.children().to_vec(),
-                    node.children,
-                ));
+                notifications.extend(self.apply_node(id, node));
             }
         }
 
         notifications
     }
 
+    /**
+        Applies changes from a node to an existing instance, and then
+        recursively to all of its children, returning any notifications.
+    */
+    fn apply_node(&mut self, id: Ref, node: InstanceNode) -> Vec<DomNotification> {
+        let mut notifications = Vec::new();
+        self.map_provider_id(id, node.provider_id.clone());
+        notifications.extend(self.apply_changes(id, &node));
+        let inst = self.inner.get_by_ref(id).expect("missing child");
+        notifications.extend(self.apply_children(id, inst.children().to_vec(), node.children));
+        notifications
+    }
+
     #[inline]
     pub fn get_instance(&self, id: Ref) -> Option<&Instance> {
         self.inner.get_by_ref(id)
@@ -362,99 +502,498 @@ impl Dom {
 
     #[inline]
     pub fn get_metadata(&self, id: Ref) -> Option<&InstanceMetadata> {
-        if id == self.inner.root_ref() {
-            Some(&self.root_meta)
-        } else {
-            self.metas.get(&id)
-        }
+        self.metas.get(&id)
     }
 
+    /**
+        Gets the id of the first root that currently exists, if any.
+    */
     #[inline]
     pub fn get_root_id(&self) -> Option<Ref> {
-        if self.inner.root().name != DOM_ROOT_NAME_NONE {
-            Some(self.inner.root_ref())
-        } else {
-            None
+        self.roots.iter().flatten().next().copied()
+    }
+
+    /**
+        Gets the ids of all roots that currently exist, in the same order as in the config.
+    */
+    pub fn get_root_ids(&self) -> Vec<Ref> {
+        self.roots.iter().flatten().copied().collect()
+    }
+
+    #[inline]
+    pub fn is_root(&self, id: Ref) -> bool {
+        self.roots.contains(&Some(id))
+    }
+
+    /**
+        Gets the full name of an instance, which is the names of the instance
+        and all of its ancestors, separated by periods, starting at the root.
+    */
+    pub fn get_full_name(&self, id: Ref) -> Option<String> {
+        let mut names = Vec::new();
+        let mut current = self.get_instance(id);
+        while let Some(inst) = current {
+            names.push(inst.name.as_str());
+            current = self.get_instance(inst.parent());
         }
+        if names.is_empty() {
+            return None;
+        }
+        names.reverse();
+        Some(names.join(&FULL_NAME_SEPARATOR.to_string()))
     }
 
     pub fn find_by_path(&self, path: impl AsRef<Path>) -> Option<Ref> {
         self.path_map.get(&make_absolute_and_clean(path)).cloned()
     }
 
-    pub fn find_by_query(&self, params: DomQueryParams) -> Vec<Ref> {
-        // FUTURE: Use some kind of precompiled search engine ?? but
-        // this seems to be good enough for now and is not too slow
-        let mut results = self
-            .ids
+    /**
+        Finds instances matching the given query, sorted by how well they match.
+
+        Only descendants of the instance given in the query are searched, if any, and
+        results are paginated using the cursor (the result to start after) and offset.
+
+        Returns an error if the query was cancelled using the given token before
+        it finished, or if the instance to search within does not exist.
+    */
+    pub fn find_by_query(
+        &self,
+        params: &DomQueryParams,
+        cancel: &CancellationToken,
+    ) -> DomResult<Vec<DomQueryResult>> {
+        let candidates = match params.within {
+            Some(within) => {
+                if self.inner.get_by_ref(within).is_none() {
+                    return Err(DomError::not_found());
+                }
+                self.subtree_ids(within)
+                    .into_iter()
+                    .filter(|id| *id != within && self.ids.contains(id))
+                    .collect::<Vec<_>>()
+            }
+            None => self.ids.iter().copied().collect::<Vec<_>>(),
+        };
+
+        // NOTE: The search index lets us skip almost all instances using only
+        // their character masks, before getting their full names (if needed) and
+        // scoring them, which is what makes queries fast even for huge projects
+        let pattern = params.pattern.as_ref();
+        let mut results = candidates
             .par_iter()
+            .filter(|_| !cancel.is_cancelled())
+            .filter(|id| pattern.is_none_or(|pattern| self.search.may_match(**id, pattern)))
             .filter_map(|id| {
                 self.inner
                     .get_by_ref(*id)
                     .map(|inst| (id, inst, self.metas.get(id)))
             })
             .filter_map(|(id, inst, meta)| {
+                let full_name = pattern
+                    .filter(|pattern| pattern.is_full_name())
+                    .and_then(|_| self.get_full_name(*id));
                 params
-                    .score(inst, meta)
+                    .score(inst, meta, full_name.as_deref())
                     .map(|s| DomQueryResult::new(s, *id))
             })
+            .filter(|result| params.after.as_ref().is_none_or(|after| result > after))
             .collect::<Vec<_>>();
 
+        if cancel.is_cancelled() {
+            return Err(DomError::cancelled());
+        }
+
         results.sort_unstable();
-        results.reverse();
 
-        results
+        Ok(results
             .into_iter()
+            .skip(params.offset)
             .take(params.limit())
-            .map(|result| result.id)
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>())
     }
 
-    pub fn apply_new_root(&mut self, node: Option<InstanceNode>) {
-        let root_ids = if self.inner.root().name != DOM_ROOT_NAME_NONE {
-            vec![self.inner.root_ref()]
-        } else {
-            Vec::new()
+    /**
+        Replaces the root at the given index with a new root, diffing
+        it against the current root if there is one, or removes it.
+    */
+    pub fn apply_new_root(&mut self, index: usize, node: Option<InstanceNode>) {
+        let Some(current) = self.roots.get(index).copied() else {
+            tracing::warn!("tried to apply root with unknown index {index}");
+            return;
         };
 
-        let root_nodes = if let Some(node) = node {
-            vec![node]
-        } else {
-            Vec::new()
+        let notifications = match (current, node) {
+            (None, None) => Vec::new(),
+            (None, Some(node)) => {
+                // NOTE: Root instances are never part of queries, and metadata
+                // must be applied again once we know that the instance is a root
+                let file_paths = node.file_paths.clone();
+                let root_id = self.insert_instance_into_dom(Ref::none(), node);
+                self.ids.remove(&root_id);
+                self.roots[index] = Some(root_id);
+                self.apply_metadata(root_id, &file_paths);
+                vec![DomNotification::Added {
+                    parent_id: None,
+                    child_id: root_id,
+                }]
+            }
+            (Some(root_id), None) => {
+                self.remove_instance_from_dom(root_id);
+                self.roots[index] = None;
+                vec![DomNotification::Removed {
+                    parent_id: None,
+                    child_id: root_id,
+                }]
+            }
+            (Some(root_id), Some(node)) => self.apply_node(root_id, node),
         };
 
-        let notifications = self.apply_children(None, root_ids, root_nodes);
         for notification in notifications {
             self.notify(notification);
         }
     }
 
-    pub async fn insert_instance(
+    /**
+        Applies a change to a project file (such as `wally.toml`) for the root at the
+        given index, updating the metadata of the root instance if it currently exists,
+        as well as the package metadata of instances in it, if a Wally file changed.
+    */
+    pub fn apply_project_file(&mut self, index: usize, path: &Path, contents: Option<&str>) {
+        let Some(project) = self.root_projects.get_mut(index) else {
+            tracing::warn!("tried to apply project file with unknown index {index}");
+            return;
+        };
+        if !project.apply_file(path, contents) {
+            return;
+        }
+
+        let Some(root_id) = self.roots[index] else {
+            return; // Root does not exist yet, project will be applied when it does
+        };
+        let current = self.get_metadata(root_id).cloned().unwrap_or_default();
+        let new_meta = current.with_project(&self.root_projects[index]);
+        if self.get_metadata(root_id) != Some(&new_meta) {
+            self.unmap_paths(root_id);
+            self.metas.insert(root_id, new_meta);
+            self.map_paths(root_id);
+            self.notify(DomNotification::Changed {
+                id: root_id,
+                class_name: None,
+                name: None,
+                properties: None,
+            });
+        }
+
+        // Package metadata uses the Wally manifest and lockfile, which may have changed
+        let file_name = path.file_name().and_then(|name| name.to_str());
+        if matches!(
+            file_name,
+            Some(WALLY_MANIFEST_FILE_NAME | WALLY_LOCKFILE_FILE_NAME)
+        ) {
+            self.apply_package_metadata(root_id);
+        }
+    }
+
+    /**
+        Applies an update from the instance provider for the root at the given index.
+    */
+    pub fn apply_update(&mut self, index: usize, update: InstanceUpdate) {
+        match update {
+            InstanceUpdate::Root(node) => self.apply_new_root(index, node),
+            InstanceUpdate::Patch(patch) => {
+                if !self.apply_patch(index, patch) {
+                    tracing::warn!("failed to apply instance patch, parent instance was not found");
+                }
+            }
+            InstanceUpdate::Changes(changes) => self.apply_provider_changes(index, changes),
+        }
+    }
+
+    /**
+        Applies changes to instances identified using their provider ids.
+
+        Changes to instances that are not known by their provider id are ignored.
+    */
+    pub fn apply_provider_changes(&mut self, index: usize, changes: InstanceChanges) {
+        for provider_id in changes.removed {
+            let id = match self.provider_ids.get(&provider_id) {
+                Some(id) => *id,
+                None => continue,
+            };
+            let parent_id = self.get_instance(id).map(|inst| inst.parent());
+            match parent_id {
+                Some(parent_id) if parent_id.is_some() => {
+                    self.remove_instance_from_dom(id);
+                    self.notify(DomNotification::Removed {
+                        parent_id: Some(parent_id),
+                        child_id: id,
+                    });
+                }
+                _ => self.apply_new_root(index, None),
+            }
+        }
+
+        for (parent_provider_id, node) in changes.added {
+            let parent_id = match self.provider_ids.get(&parent_provider_id) {
+                Some(id) => *id,
+                None => continue,
+            };
+            let child_id = self.insert_instance_into_dom(parent_id, node);
+            self.notify(DomNotification::Added {
+                parent_id: Some(parent_id),
+                child_id,
+            });
+        }
+
+        for change in changes.changed {
+            let id = match self.provider_ids.get(&change.id) {
+                Some(id) => *id,
+                None => continue,
+            };
+            let inst = match self.inner.get_by_ref_mut(id) {
+                Some(inst) => inst,
+                None => continue,
+            };
+            let name = change.name.filter(|name| name != &inst.name);
+            let class_name = change.class_name.filter(|class| class != &inst.class);
+            if name.is_none() && class_name.is_none() {
+                continue;
+            }
+            if let Some(name) = &name {
+                name.clone_into(&mut inst.name);
+            }
+            if let Some(class_name) = &class_name {
+                class_name.clone_into(&mut inst.class);
+            }
+            if name.is_some() {
+                self.search.index(&self.inner, id);
+            }
+            self.notify(DomNotification::Changed {
+                id,
+                class_name,
+                name,
+                properties: None,
+            });
+        }
+    }
+
+    /**
+        Applies a patch to a part of the instance tree, only diffing the
+        children in the patch instead of the entire instance tree.
+
+        Returns `false` if the parent instance for the patch was not found.
+    */
+    pub fn apply_patch(&mut self, index: usize, patch: InstancePatch) -> bool {
+        let mut parent_id = match self.roots.get(index).copied().flatten() {
+            Some(id) => id,
+            None => return false,
+        };
+        for name in &patch.parent {
+            let child_id = self.inner.get_by_ref(parent_id).and_then(|inst| {
+                inst.children().iter().copied().find(|child_id| {
+                    self.inner
+                        .get_by_ref(*child_id)
+                        .is_some_and(|child| &child.name == name)
+                })
+            });
+            match child_id {
+                Some(id) => parent_id = id,
+                None => return false,
+            }
+        }
+
+        let ids = self
+            .inner
+            .get_by_ref(parent_id)
+            .expect("missing parent")
+            .children()
+            .iter()
+            .copied()
+            .filter(|child_id| {
+                self.inner
+                    .get_by_ref(*child_id)
+                    .is_some_and(|child| child.name == patch.name)
+            })
+            .collect::<Vec<_>>();
+
+        let notifications = self.apply_children(parent_id, ids, patch.nodes);
+        for notification in notifications {
+            self.notify(notification);
+        }
+
+        true
+    }
+
+    fn apply_parent_paths(&mut self, parent: Ref, changed_paths: Option<Vec<PathBuf>>) -> bool {
+        match changed_paths {
+            None => false,
+            Some(changed_paths) => {
+                let changed_metadata = self.apply_metadata(parent, &changed_paths);
+                if changed_metadata {
+                    self.notify(DomNotification::Changed {
+                        id: parent,
+                        class_name: None,
+                        name: None,
+                        properties: None,
+                    });
+                }
+                true
+            }
+        }
+    }
+
+    fn subtree_ids(&self, id: Ref) -> Vec<Ref> {
+        let mut ids = Vec::new();
+        let mut pending = vec![id];
+        while let Some(current_id) = pending.pop() {
+            if let Some(inst) = self.get_instance(current_id) {
+                ids.push(current_id);
+                pending.extend(inst.children().iter().rev());
+            }
+        }
+        ids
+    }
+
+    fn relocated_node(&self, id: Ref, moves: &[(PathBuf, PathBuf)]) -> Option<InstanceNode> {
+        let inst = self.get_instance(id)?;
+        let file_paths = self
+            .get_metadata(id)
+            .and_then(|meta| meta.paths.as_ref())
+            .map(|paths| {
+                paths
+                    .into_iter()
+                    .map(|path| util::relocate_path(path, moves))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let children = inst
+            .children()
+            .iter()
+            .filter_map(|child_id| self.relocated_node(*child_id, moves))
+            .collect::<Vec<_>>();
+        Some(InstanceNode {
+            class_name: inst.class.to_string(),
+            name: inst.name.to_string(),
+            file_paths,
+            children,
+            provider_id: None,
+            properties: InstanceProperties(inst.properties.clone()),
+        })
+    }
+
+    fn relocate_metadata(&mut self, id: Ref, moves: &[(PathBuf, PathBuf)]) {
+        let current_paths = self
+            .get_metadata(id)
+            .and_then(|meta| meta.paths.as_ref())
+            .map(|paths| paths.into_iter().map(Path::to_path_buf).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        let new_paths = current_paths
+            .iter()
+            .map(|path| util::relocate_path(path, moves))
+            .collect::<Vec<_>>();
+
+        self.apply_metadata(id, &new_paths);
+
+        // NOTE: Same as in insert_instance_into_dom, children must be
+        // updated *after* their parent since metadata depends on it
+        let child_ids = self.inner.get_by_ref(id).unwrap().children().to_vec();
+        for child_id in child_ids {
+            self.relocate_metadata(child_id, moves);
+        }
+    }
+
+    fn get_file_paths(&self, id: Ref) -> DomResult<&InstanceMetadataPaths> {
+        if self.get_instance(id).is_none() {
+            return Err(DomError::not_found());
+        }
+        self.get_metadata(id)
+            .and_then(|meta| meta.paths.as_ref())
+            .ok_or_else(DomError::not_file_backed)
+    }
+
+    /**
+        Gets the file paths of an instance, and checks that they may be modified.
+
+        If a child name is given, the path of a child with that name inside
+        of the folder for the instance is also checked, meaning that a child
+        with that name may be created, or moved or copied into the instance.
+
+        If no child name is given, the instance itself is being modified, and
+        the paths of all of its descendants are checked, since those may be
+        modified together with the instance when it is renamed or moved.
+    */
+    fn get_writable_paths(
+        &self,
+        id: Ref,
+        child_name: Option<&str>,
+    ) -> DomResult<&InstanceMetadataPaths> {
+        if self.config.read_only {
+            return Err(DomError::new(
+                DomErrorKind::PermissionDenied,
+                "Files can not be modified while in read-only mode",
+            ));
+        }
+
+        let paths = self.get_file_paths(id)?;
+        let protected = match child_name {
+            Some(name) => paths
+                .into_iter()
+                .map(Path::to_path_buf)
+                .chain(paths.folder.as_ref().map(|folder| folder.join(name)))
+                .find(|path| !self.config.is_writable_path(path)),
+            None => self
+                .subtree_ids(id)
+                .into_iter()
+                .filter_map(|id| self.get_metadata(id))
+                .filter_map(|meta| meta.paths.as_ref())
+                .flat_map(|paths| paths.into_iter())
+                .find(|path| !self.config.is_writable_path(path))
+                .map(Path::to_path_buf),
+        };
+
+        match protected {
+            None => Ok(paths),
+            Some(path) => Err(DomError::new(
+                DomErrorKind::PermissionDenied,
+                "Path is protected and can not be modified",
+            )
+            .with_path(path)),
+        }
+    }
+
+    /**
+        Checks that no child of the given parent, other than the
+        instance being renamed or moved, already uses the given name.
+    */
+    fn ensure_unique_name(&self, parent: Ref, name: &str, id: Option<Ref>) -> DomResult<()> {
+        let parent_inst = self.get_instance(parent).ok_or_else(DomError::not_found)?;
+        let collides = parent_inst
+            .children()
+            .iter()
+            .filter(|child_id| Some(**child_id) != id)
+            .filter_map(|child_id| self.get_instance(*child_id))
+            .any(|child| child.name == name);
+        if collides {
+            Err(DomError::new(
+                DomErrorKind::NameCollision,
+                format!("An instance named '{name}' already exists"),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn insert_instance_inner(
         &mut self,
         parent: Ref,
         class_name: String,
         name: String,
-    ) -> Option<Ref> {
-        let parent_paths = match (
-            self.get_instance(parent),
-            self.get_metadata(parent)
-                .and_then(|meta| meta.paths.as_ref()),
-        ) {
-            (i, Some(paths)) if i.is_some() => paths,
-            _ => return None,
-        };
+    ) -> DomResult<(Ref, bool)> {
+        self.ensure_unique_name(parent, &name, None)?;
+        let parent_paths = self.get_writable_paths(parent, Some(&name))?;
 
         let (new_child_paths, changed_parent_paths) =
-            match fs::create_instance(parent_paths, &class_name, &name).await {
-                Ok((child_paths, parent_paths)) if !child_paths.is_empty() => {
-                    (child_paths, parent_paths)
-                }
-                Ok(_) => return None,
-                Err(e) => {
-                    tracing::error!("{}", e);
-                    return None;
-                }
-            };
+            fs::create_instance(parent_paths, &class_name, &name).await?;
 
         let child_id = self.insert_instance_into_dom(
             parent,
@@ -463,49 +1002,36 @@ impl Dom {
                 name,
                 file_paths: new_child_paths,
                 children: vec![],
+                provider_id: None,
+                properties: InstanceProperties::default(),
             },
         );
 
-        if let Some(changed_paths) = changed_parent_paths {
-            let changed_metadata = self.apply_metadata(parent, &changed_paths);
-            if changed_metadata {
-                self.notify(DomNotification::Changed {
-                    id: parent,
-                    class_name: None,
-                    name: None,
-                });
-            }
-        }
+        let changed_parent = self.apply_parent_paths(parent, changed_parent_paths);
 
         self.notify(DomNotification::Added {
             parent_id: Some(parent),
             child_id,
         });
 
-        Some(child_id)
+        Ok((child_id, changed_parent))
     }
 
-    pub async fn rename_instance(&mut self, id: Ref, name: String) -> bool {
-        let instance_paths = match (
-            self.get_instance(id),
-            self.get_metadata(id).and_then(|meta| meta.paths.as_ref()),
-        ) {
-            (i, Some(paths)) if i.is_some() => paths.clone(),
-            _ => return false,
-        };
+    async fn rename_instance_inner(&mut self, id: Ref, name: String) -> DomResult<String> {
+        let instance_paths = self.get_writable_paths(id, None)?.clone();
+        let parent = self.get_instance(id).unwrap().parent();
+        if parent.is_some() {
+            self.ensure_unique_name(parent, &name, Some(id))?;
+            self.get_writable_paths(parent, Some(&name))?;
+        }
 
         let instance = self.inner.get_by_ref_mut(id).unwrap();
         let instance_name = instance.name.as_str();
 
-        let changed_paths = match fs::rename_instance(&instance_paths, instance_name, &name).await {
-            Ok(paths) => paths,
-            Err(e) => {
-                tracing::error!("{}", e);
-                return false;
-            }
-        };
+        let changed_paths = fs::rename_instance(&instance_paths, instance_name, &name).await?;
 
-        instance.name.clone_from(&name);
+        let previous_name = std::mem::replace(&mut instance.name, name.clone());
+        self.search.index(&self.inner, id);
 
         let changed_metadata = self.apply_metadata(id, &changed_paths);
         if changed_metadata {
@@ -513,34 +1039,97 @@ impl Dom {
                 id,
                 class_name: None,
                 name: Some(name),
+                properties: None,
             });
         }
 
-        true
+        Ok(previous_name)
     }
 
-    pub async fn delete_instance(&mut self, id: Ref) -> bool {
-        let parent = match self.get_instance(id).map(|inst| inst.parent()) {
-            Some(parent) => parent,
-            None => return false,
+    /**
+        Resolves a property value for an instance, checking that the property exists for
+        its class in the reflection database and that the value is valid for that property.
+    */
+    fn resolve_instance_property(
+        &self,
+        id: Ref,
+        name: &str,
+        value: &JsonValue,
+    ) -> DomResult<Variant> {
+        let inst = self.get_instance(id).ok_or_else(DomError::not_found)?;
+        if find_property_descriptor(&inst.class, name).is_none() {
+            return Err(DomError::new(
+                DomErrorKind::InvalidValue,
+                format!("Unknown property '{name}' for class '{}'", inst.class),
+            ));
+        }
+        resolve_property_value(&inst.class, name, value.clone())
+            .map_err(|e| DomError::new(DomErrorKind::InvalidValue, format!("{e:#}")))
+    }
+
+    async fn set_property_inner(
+        &mut self,
+        id: Ref,
+        name: String,
+        value: Option<JsonValue>,
+    ) -> DomResult<Option<JsonValue>> {
+        let resolved = match &value {
+            Some(value) => Some(self.resolve_instance_property(id, &name, value)?),
+            None => None,
         };
 
-        let instance_paths = match (
-            self.get_instance(id),
-            self.get_metadata(id).and_then(|meta| meta.paths.as_ref()),
-        ) {
-            (i, Some(paths)) if i.is_some() => paths,
-            _ => return false,
+        let instance_paths = self.get_file_paths(id)?.clone();
+        let property_path = fs::get_property_file_path(&instance_paths)?;
+        if self.config.read_only || !self.config.is_writable_path(&property_path) {
+            return Err(DomError::new(
+                DomErrorKind::PermissionDenied,
+                "Path is protected and can not be modified",
+            )
+            .with_path(property_path));
+        }
+
+        let (previous, created) = fs::set_property(&property_path, &name, value.as_ref()).await?;
+        if created {
+            let mut file_paths = instance_paths
+                .into_iter()
+                .map(Path::to_path_buf)
+                .collect::<Vec<_>>();
+            file_paths.push(property_path);
+            self.apply_metadata(id, &file_paths);
+        }
+
+        let inst = self.inner.get_by_ref_mut(id).unwrap();
+        match resolved {
+            Some(resolved) => inst.properties.insert(name.clone(), resolved),
+            None => inst.properties.remove(&name),
         };
 
-        match fs::delete_instance(instance_paths).await {
-            Ok(_) => {}
-            Err(e) => {
-                tracing::error!("{}", e);
-                return false;
-            }
+        self.notify(DomNotification::Changed {
+            id,
+            class_name: None,
+            name: None,
+            properties: Some(vec![name]),
+        });
+
+        Ok(previous)
+    }
+
+    async fn delete_instance_inner(&mut self, id: Ref) -> DomResult<(Ref, TrashedInstance)> {
+        let instance_paths = self.get_writable_paths(id, None)?.clone();
+        let parent = self.get_instance(id).unwrap().parent();
+        if parent.is_none() {
+            return Err(DomError::new(
+                DomErrorKind::InvalidOperation,
+                "Can not delete the root instance",
+            ));
         }
 
+        // Snapshot the instance tree before deleting anything, so that it can be restored
+        let node = self.relocated_node(id, &[]).unwrap();
+        let ids = self.subtree_ids(id);
+
+        let paths = fs::trash_instance(&instance_paths, &self.config.trash_dir).await?;
+
         self.remove_instance_from_dom(id);
 
         self.notify(DomNotification::Removed {
@@ -548,11 +1137,491 @@ impl Dom {
             child_id: id,
         });
 
-        false
+        Ok((parent, TrashedInstance { node, ids, paths }))
+    }
+
+    async fn restore_instance_inner(
+        &mut self,
+        parent: Ref,
+        trashed: TrashedInstance,
+    ) -> DomResult<(Ref, bool, HashMap<Ref, Ref>)> {
+        let parent_paths = self
+            .get_writable_paths(parent, Some(&trashed.node.name))?
+            .clone();
+
+        let (dir_move, changed_parent_paths) =
+            fs::restore_instance(&trashed.paths, &parent_paths).await?;
+
+        let changed_parent = self.apply_parent_paths(parent, changed_parent_paths);
+
+        let mut node = trashed.node;
+        node.relocate(&[dir_move]);
+        let child_id = self.insert_instance_into_dom(parent, node);
+
+        self.notify(DomNotification::Added {
+            parent_id: Some(parent),
+            child_id,
+        });
+
+        // Restored instances get new ids, we keep track of which old ids
+        // they correspond to, so that any references to them can be updated
+        let id_map = trashed
+            .ids
+            .into_iter()
+            .zip(self.subtree_ids(child_id))
+            .collect::<HashMap<_, _>>();
+
+        Ok((child_id, changed_parent, id_map))
+    }
+
+    async fn move_instance_inner(&mut self, id: Ref, new_parent_id: Ref) -> DomResult<(Ref, bool)> {
+        let instance_paths = self.get_writable_paths(id, None)?.clone();
+        let name = self.get_instance(id).unwrap().name.clone();
+        let parent_paths = self.get_writable_paths(new_parent_id, Some(&name))?.clone();
+
+        let parent = self.get_instance(id).unwrap().parent();
+        if parent.is_none() {
+            return Err(DomError::new(
+                DomErrorKind::InvalidOperation,
+                "Can not move the root instance",
+            ));
+        }
+
+        if parent == new_parent_id {
+            return Ok((parent, false));
+        }
+
+        self.ensure_unique_name(new_parent_id, &name, Some(id))?;
+
+        // Make sure we are not trying to move an instance into itself or its descendants
+        let mut current = Some(new_parent_id);
+        while let Some(current_id) = current.take() {
+            if current_id == id {
+                return Err(DomError::new(
+                    DomErrorKind::InvalidOperation,
+                    "Can not move an instance into itself",
+                ));
+            }
+            match self.get_instance(current_id) {
+                Some(inst) if inst.parent().is_some() => current = Some(inst.parent()),
+                Some(_) => {}
+                None => return Err(DomError::not_found()),
+            }
+        }
+
+        let (mut moves, changed_parent_paths) =
+            fs::move_instance(&instance_paths, &parent_paths).await?;
+
+        self.inner.transfer_within(id, new_parent_id);
+        self.search.index(&self.inner, id);
+
+        let changed_parent = self.apply_parent_paths(new_parent_id, changed_parent_paths);
+
+        util::push_folder_move(&mut moves, instance_paths.folder.as_deref());
+        self.relocate_metadata(id, &moves);
+
+        self.notify(DomNotification::Removed {
+            parent_id: Some(parent),
+            child_id: id,
+        });
+        self.notify(DomNotification::Added {
+            parent_id: Some(new_parent_id),
+            child_id: id,
+        });
+
+        Ok((parent, changed_parent))
+    }
+
+    async fn copy_instance_inner(&mut self, id: Ref, new_parent_id: Ref) -> DomResult<(Ref, bool)> {
+        let instance_paths = self.get_file_paths(id)?.clone();
+        let instance_name = self.get_instance(id).unwrap().name.clone();
+        let parent_paths = self
+            .get_writable_paths(new_parent_id, Some(&instance_name))?
+            .clone();
+
+        // NOTE: Siblings that are not backed by files, such as ones from project
+        // files, can not conflict on disk, but must still not share a name
+        let sibling_names = self
+            .get_instance(new_parent_id)
+            .ok_or_else(DomError::not_found)?
+            .children()
+            .iter()
+            .filter_map(|child_id| self.get_instance(*child_id))
+            .map(|child| child.name.clone())
+            .collect::<Vec<_>>();
+
+        let (name, mut copies, changed_parent_paths) = fs::copy_instance(
+            &instance_paths,
+            &parent_paths,
+            &instance_name,
+            &sibling_names,
+        )
+        .await?;
+
+        util::push_folder_move(&mut copies, instance_paths.folder.as_deref());
+        let mut node = self.relocated_node(id, &copies).unwrap();
+        node.name = name;
+
+        let changed_parent = self.apply_parent_paths(new_parent_id, changed_parent_paths);
+
+        let child_id = self.insert_instance_into_dom(new_parent_id, node);
+
+        self.notify(DomNotification::Added {
+            parent_id: Some(new_parent_id),
+            child_id,
+        });
+
+        Ok((child_id, changed_parent))
     }
 
-    pub async fn move_instance(&mut self, _id: Ref, _new_parent_id: Ref) -> bool {
-        // TODO: Implement this
-        false
+    async fn collapse_instance_inner(&mut self, id: Ref) -> bool {
+        let instance_paths = match self.get_writable_paths(id, None) {
+            Ok(paths) => paths.clone(),
+            Err(_) => return false,
+        };
+
+        // NOTE: Collapsing is only ever done to tidy up after another
+        // operation, so failing to do it should not fail that operation
+        match fs::collapse_instance(&instance_paths).await {
+            Ok(changed_paths) => self.apply_parent_paths(id, changed_paths),
+            Err(e) => {
+                tracing::error!("{}", e);
+                false
+            }
+        }
+    }
+
+    pub async fn insert_instance(
+        &mut self,
+        parent: Ref,
+        class_name: String,
+        name: String,
+    ) -> DomResult<Ref> {
+        let (child_id, changed_parent) =
+            self.insert_instance_inner(parent, class_name, name).await?;
+        self.journal.record(vec![JournalOperation::Remove {
+            id: child_id,
+            collapse_parent: changed_parent,
+        }]);
+        Ok(child_id)
+    }
+
+    pub async fn rename_instance(&mut self, id: Ref, name: String) -> DomResult<()> {
+        let previous_name = self.rename_instance_inner(id, name).await?;
+        self.journal.record(vec![JournalOperation::Rename {
+            id,
+            name: previous_name,
+        }]);
+        Ok(())
+    }
+
+    /**
+        Sets a property of an instance to a value in the same format that Rojo uses for
+        properties in meta and model files, or removes the property if no value is given.
+    */
+    pub async fn set_property(
+        &mut self,
+        id: Ref,
+        name: String,
+        value: Option<JsonValue>,
+    ) -> DomResult<()> {
+        let previous = self.set_property_inner(id, name.clone(), value).await?;
+        self.journal.record(vec![JournalOperation::SetProperty {
+            id,
+            name,
+            value: previous,
+        }]);
+        Ok(())
+    }
+
+    pub async fn delete_instance(&mut self, id: Ref) -> DomResult<()> {
+        let (parent, trashed) = self.delete_instance_inner(id).await?;
+        self.journal.record(vec![JournalOperation::Insert {
+            parent_id: parent,
+            trashed,
+        }]);
+        Ok(())
+    }
+
+    pub async fn move_instance(&mut self, id: Ref, new_parent_id: Ref) -> DomResult<()> {
+        let (parent, changed_parent) = self.move_instance_inner(id, new_parent_id).await?;
+        if parent != new_parent_id {
+            self.journal.record(vec![JournalOperation::Move {
+                id,
+                parent_id: parent,
+                collapse_parent: changed_parent,
+            }]);
+        }
+        Ok(())
+    }
+
+    pub async fn copy_instance(&mut self, id: Ref, new_parent_id: Ref) -> DomResult<Ref> {
+        let (child_id, changed_parent) = self.copy_instance_inner(id, new_parent_id).await?;
+        self.journal.record(vec![JournalOperation::Remove {
+            id: child_id,
+            collapse_parent: changed_parent,
+        }]);
+        Ok(child_id)
+    }
+
+    pub fn set_clipboard(&mut self, ids: Vec<Ref>) -> DomResult<()> {
+        if ids.iter().all(|id| self.ids.contains(id)) {
+            self.clipboard = ids;
+            Ok(())
+        } else {
+            Err(DomError::not_found())
+        }
+    }
+
+    /**
+        Pastes all instances in the clipboard into the given parent.
+
+        If pasting any of the instances fails, instances that were
+        already pasted are kept, and can be undone as a single change.
+    */
+    pub async fn paste_instances(&mut self, new_parent_id: Ref) -> DomResult<Vec<Ref>> {
+        let mut pasted = Vec::new();
+        let mut operations = Vec::new();
+        let mut result = Ok(());
+        for id in self.clipboard.clone() {
+            match self.copy_instance_inner(id, new_parent_id).await {
+                Ok((child_id, changed_parent)) => {
+                    pasted.push(child_id);
+                    operations.push(JournalOperation::Remove {
+                        id: child_id,
+                        collapse_parent: changed_parent,
+                    });
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        self.journal.record(operations);
+        result.map(|_| pasted)
+    }
+}
+
+#[cfg(test)]
+impl Dom {
+    /**
+        Creates a dom in a new temporary directory with the given name, with a single
+        root folder at `src` that contains a `Module.luau` file, and a trash directory.
+
+        Returns the dom, the temporary directory, and the id of the module.
+    */
+    pub(crate) fn for_test(name: &str) -> (Self, TestDir, Ref) {
+        let dir = TestDir::new(&format!("dom-{name}"));
+        let src = dir.join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("Module.luau"), "return {}").unwrap();
+
+        let mut module = InstanceNode::for_test("ModuleScript", "Module", Vec::new());
+        module.file_paths = vec![src.join("Module.luau")];
+        let mut root = InstanceNode::for_test("Folder", "src", vec![module]);
+        root.file_paths = vec![src];
+
+        let mut dom = Self::new(Config {
+            trash_dir: dir.join("trash"),
+            ..Config::default()
+        });
+        dom.apply_new_root(0, Some(root));
+
+        let root_id = dom.get_root_id().unwrap();
+        let module_id = dom.get_instance(root_id).unwrap().children()[0];
+        (dom, dir, module_id)
+    }
+}
+
+#[tokio::test]
+async fn copy_instance_names() {
+    let (mut dom, dir, module_id) = Dom::for_test("copy-names");
+    let root_id = dom.get_root_id().unwrap();
+
+    // Siblings without any files, such as ones from project files, are still taken names
+    dom.insert_instance_into_dom(
+        root_id,
+        InstanceNode::for_test("Folder", "Module2", Vec::new()),
+    );
+    let copy_id = dom.copy_instance(module_id, root_id).await.unwrap();
+    assert_eq!(dom.get_instance(copy_id).unwrap().name, "Module3");
+    assert!(dir.join("src/Module3.luau").exists());
+    assert!(!dir.join("src/Module2.luau").exists());
+}
+
+#[test]
+fn apply_wally_files_to_packages() {
+    let dir = TestDir::new("dom-wally");
+    let package_dir = dir.join("Packages/_Index/evaera_promise@4.0.0/promise");
+    std::fs::create_dir_all(&package_dir).unwrap();
+    std::fs::write(package_dir.join("init.luau"), "return {}").unwrap();
+    std::fs::write(package_dir.join("Util.luau"), "return {}").unwrap();
+
+    let mut util = InstanceNode::for_test("ModuleScript", "Util", Vec::new());
+    util.file_paths = vec![package_dir.join("Util.luau")];
+    let mut promise = InstanceNode::for_test("ModuleScript", "promise", vec![util]);
+    promise.file_paths = vec![package_dir.join("init.luau")];
+    let mut root = InstanceNode::for_test("Folder", "Game", vec![promise]);
+    root.file_paths = vec![dir.to_path_buf()];
+
+    let mut dom = Dom::new(Config::default());
+    let mut notifications = dom.take_notification_receiver().unwrap();
+    dom.apply_new_root(0, Some(root));
+    let root_id = dom.get_root_id().unwrap();
+    let promise_id = dom.get_instance(root_id).unwrap().children()[0];
+    let util_id = dom.get_instance(promise_id).unwrap().children()[0];
+    let package = |dom: &Dom, id| dom.get_metadata(id).unwrap().package.clone().unwrap();
+    assert_eq!(package(&dom, promise_id).requirement, None);
+    while notifications.try_recv().is_ok() {}
+
+    // Packages get their requirements once the manifest has them, and are marked as changed
+    let manifest = "[dependencies]\nPromise = \"evaera/promise@^5.0.0\"\n";
+    let manifest_path = dir.join("wally.toml");
+    std::fs::write(&manifest_path, manifest).unwrap();
+    dom.apply_project_file(0, &manifest_path, Some(manifest));
+    for id in [promise_id, util_id] {
+        let package = package(&dom, id);
+        assert_eq!(
+            package.requirement.as_deref().map(String::as_str),
+            Some("^5.0.0")
+        );
+        assert!(package.outdated);
+    }
+    assert!(package(&dom, promise_id).is_root);
+    assert!(!package(&dom, util_id).is_root);
+
+    let mut changed = Vec::new();
+    while let Ok(notification) = notifications.try_recv() {
+        if let DomNotification::Changed { id, .. } = notification {
+            changed.push(id);
+        }
+    }
+    assert!(changed.contains(&promise_id));
+    assert!(changed.contains(&util_id));
+}
+
+#[test]
+#[ignore = "benchmark, run using `cargo test --release -- --ignored --nocapture`"]
+fn bench_apply_patch() {
+    use std::time::Instant;
+
+    fn folder(index: usize, extra_module: bool) -> InstanceNode {
+        let mut modules = (0..100)
+            .map(|m| InstanceNode::for_test("ModuleScript", format!("Module{m}"), Vec::new()))
+            .collect::<Vec<_>>();
+        if extra_module {
+            modules.push(InstanceNode::for_test("ModuleScript", "Added", Vec::new()));
+        }
+        InstanceNode::for_test("Folder", format!("Folder{index}"), modules)
+    }
+
+    // Roughly 40k instances - 4 services with 100 folders each, with 100 modules each
+    let tree = |extra_module: bool| {
+        let services = [
+            "ReplicatedStorage",
+            "ServerScriptService",
+            "StarterPlayer",
+            "Workspace",
+        ]
+        .into_iter()
+        .map(|service| {
+            let folders = (0..100)
+                .map(|f| folder(f, extra_module && service == "Workspace" && f == 50))
+                .collect();
+            InstanceNode::for_test(service, service.to_string(), folders)
+        })
+        .collect();
+        InstanceNode::for_test("DataModel", "Game", services)
+    };
+
+    let mut full_dom = Dom::new(Config::default());
+    let mut patch_dom = Dom::new(Config::default());
+    full_dom.apply_new_root(0, Some(tree(false)));
+    patch_dom.apply_new_root(0, Some(tree(true)));
+    patch_dom.apply_new_root(0, Some(tree(false)));
+    assert!(full_dom.ids.len() > 40_000);
+
+    let new_root = tree(true);
+    let start = Instant::now();
+    full_dom.apply_new_root(0, Some(new_root));
+    let full_elapsed = start.elapsed();
+
+    let patch = InstancePatch {
+        parent: vec!["Workspace".to_string()],
+        name: "Folder50".to_string(),
+        nodes: vec![folder(50, true)],
+    };
+    let start = Instant::now();
+    assert!(patch_dom.apply_patch(0, patch));
+    let patch_elapsed = start.elapsed();
+
+    println!("apply_new_root: {full_elapsed:?}");
+    println!("apply_patch:    {patch_elapsed:?}");
+    assert_eq!(full_dom.ids.len(), patch_dom.ids.len());
+    assert!(patch_elapsed < full_elapsed);
+}
+
+#[test]
+#[ignore = "benchmark, run using `cargo test --release -- --ignored --nocapture`"]
+fn bench_find_by_query() {
+    use std::time::Instant;
+
+    // Roughly 100k instances - 10 services with 100 folders each, with 100 modules each
+    let services = (0..10)
+        .map(|s| {
+            let folders = (0..100)
+                .map(|f| {
+                    let modules = (0..100)
+                        .map(|m| {
+                            InstanceNode::for_test("ModuleScript", format!("Module{m}"), Vec::new())
+                        })
+                        .collect();
+                    InstanceNode::for_test("Folder", format!("Folder{f}"), modules)
+                })
+                .collect();
+            InstanceNode::for_test("Folder", format!("Service{s}"), folders)
+        })
+        .collect();
+    let tree = InstanceNode::for_test("DataModel", "Game", services);
+
+    let mut dom = Dom::new(Config::default());
+    let start = Instant::now();
+    dom.apply_new_root(0, Some(tree));
+    println!("apply_new_root:      {:?}", start.elapsed());
+    assert!(dom.ids.len() > 100_000);
+
+    let cancel = CancellationToken::new();
+    for query in ["module42", "mdl42", "folder", "service3.f50.m42", "xyz"] {
+        let mut params = DomQueryParams::from_str(query);
+        params.skip_non_files = false;
+
+        let start = Instant::now();
+        let results = dom.find_by_query(&params, &cancel).unwrap();
+        let indexed_elapsed = start.elapsed();
+
+        // Scoring every single instance without using the index, for comparison
+        let start = Instant::now();
+        let scanned = dom
+            .ids
+            .par_iter()
+            .filter_map(|id| {
+                let full_name = params
+                    .pattern
+                    .as_ref()
+                    .filter(|pattern| pattern.is_full_name())
+                    .and_then(|_| dom.get_full_name(*id));
+                params.score(
+                    dom.inner.get_by_ref(*id)?,
+                    dom.metas.get(id),
+                    full_name.as_deref(),
+                )
+            })
+            .count();
+        let scan_elapsed = start.elapsed();
+
+        println!("{query:<20} indexed: {indexed_elapsed:?}, full scan: {scan_elapsed:?}");
+        assert!(results.len() <= scanned);
+        assert_eq!(results.is_empty(), scanned == 0);
     }
 }
@@ -1,7 +1,15 @@
-use std::{cmp::Ordering, path::PathBuf};
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    path::PathBuf,
+};
 
+use rbx_dom_weak::types::Variant;
 use serde::{Deserialize, Serialize};
 
+use super::util::relocate_path;
+
 /**
     A node representing an instance and its children.
 
@@ -18,19 +26,34 @@ pub struct InstanceNode {
     pub file_paths: Vec<PathBuf>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub children: Vec<InstanceNode>,
+    /**
+        An id given to this instance by the instance provider, if any.
+
+        Used by providers that can refer to instances by id in later updates.
+    */
+    #[serde(skip)]
+    pub provider_id: Option<String>,
+    /**
+        Properties loaded for this instance, if any.
+
+        These are never part of sourcemaps, and get loaded
+        from files after being emitted by instance providers.
+    */
+    #[serde(skip)]
+    pub properties: InstanceProperties,
 }
 
 impl InstanceNode {
-    fn sort_inner(&mut self) {
+    pub(crate) fn sort_recursive(&mut self) {
         for child in &mut self.children {
-            child.sort_inner();
+            child.sort_recursive();
         }
         self.children.sort();
     }
 
     pub fn from_json(json: impl AsRef<str>) -> Result<Self, serde_json::Error> {
         let mut node = serde_json::from_str::<Self>(json.as_ref())?;
-        node.sort_inner();
+        node.sort_recursive();
         Ok(node)
     }
 
@@ -58,6 +81,120 @@ impl InstanceNode {
             }
         }
     }
+
+    /**
+        Relocates all file paths in this node and its children, recursively,
+        using the given list of `(old, new)` path pairs, in order.
+    */
+    pub(crate) fn relocate(&mut self, moves: &[(PathBuf, PathBuf)]) {
+        for path in self.file_paths.iter_mut() {
+            *path = relocate_path(path, moves);
+        }
+        for child in self.children.iter_mut() {
+            child.relocate(moves);
+        }
+    }
+}
+
+#[cfg(test)]
+impl InstanceNode {
+    /**
+        Creates a node with the given children, and no file paths or properties.
+    */
+    pub(crate) fn for_test(class_name: &str, name: impl Into<String>, children: Vec<Self>) -> Self {
+        Self {
+            class_name: class_name.to_string(),
+            name: name.into(),
+            file_paths: Vec::new(),
+            children,
+            provider_id: None,
+            properties: InstanceProperties::default(),
+        }
+    }
+}
+
+/**
+    Properties of an instance node, keyed by property name.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InstanceProperties(pub HashMap<String, Variant>);
+
+// NOTE: Property values are only PartialEq since they may contain floats, but
+// they are only ever compared to check for changes, where NaN != NaN is fine
+impl Eq for InstanceProperties {}
+
+impl Deref for InstanceProperties {
+    type Target = HashMap<String, Variant>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for InstanceProperties {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/**
+    An update to the instance tree, emitted by instance providers.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstanceUpdate {
+    /**
+        A new root for the entire instance tree, or `None` to clear it.
+    */
+    Root(Option<InstanceNode>),
+    /**
+        A patch for a small part of the instance tree.
+    */
+    Patch(InstancePatch),
+    /**
+        Changes to instances identified using their provider ids.
+    */
+    Changes(InstanceChanges),
+}
+
+/**
+    A patch replacing all children with a specific name in a parent instance.
+
+    The parent instance is found using the names of its ancestors, starting at
+    (but not including) the root, meaning an empty list is the root itself.
+
+    Nodes may be empty to remove children, or contain more than one node
+    if there are multiple children with the same name in the parent.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstancePatch {
+    pub parent: Vec<String>,
+    pub name: String,
+    pub nodes: Vec<InstanceNode>,
+}
+
+/**
+    Changes to instances that are identified using ids given by the instance provider,
+    such as the ids of instances in a Rojo serve session.
+
+    Changes are applied in order - removed instances first, then added, then changed.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstanceChanges {
+    pub removed: Vec<String>,
+    /**
+        Added instances, along with the provider id of their parent.
+    */
+    pub added: Vec<(String, InstanceNode)>,
+    pub changed: Vec<InstanceChange>,
+}
+
+/**
+    A change to the name and / or class name of an instance identified using its provider id.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceChange {
+    pub id: String,
+    pub name: Option<String>,
+    pub class_name: Option<String>,
 }
 
 impl Ord for InstanceNode {
@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use rbx_dom_weak::{
+    types::{
+        Attributes, BinaryString, CFrame, Color3, Color3uint8, Content, Enum, Matrix3, NumberRange,
+        Rect, Tags, UDim, UDim2, Variant, VariantType, Vector2, Vector2int16, Vector3,
+        Vector3int16,
+    },
+    Instance,
+};
+use rbx_reflection::{ClassTag, DataType, PropertyDescriptor, PropertyKind, ReflectionDatabase};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+static CLASS_DATABASE: Lazy<&ReflectionDatabase> = Lazy::new(rbx_reflection_database::get);
+
+/**
+    Finds the descriptor for a property of the given class, or any of its superclasses.
+
+    Aliased properties, such as `Color3uint8` for `Color` on parts, resolve to the
+    descriptor of the canonical property that they are an alias for.
+*/
+pub fn find_property_descriptor(
+    class_name: &str,
+    property_name: &str,
+) -> Option<&'static PropertyDescriptor<'static>> {
+    let mut current = CLASS_DATABASE.classes.get(class_name);
+    while let Some(class) = current {
+        if let Some(descriptor) = class.properties.get(property_name) {
+            return match &descriptor.kind {
+                PropertyKind::Alias { alias_for } => class.properties.get(alias_for.as_ref()),
+                _ => Some(descriptor),
+            };
+        }
+        current = class
+            .superclass
+            .as_ref()
+            .and_then(|superclass| CLASS_DATABASE.classes.get(superclass.as_ref()));
+    }
+    None
+}
+
+/**
+    Checks if the given class is the same as, or a subclass of, the given superclass.
+
+    Class names are compared case-insensitively, so `is:basepart` matches a `Part`.
+*/
+pub fn class_is_a(class_name: &str, superclass_name: &str) -> bool {
+    if class_name.eq_ignore_ascii_case(superclass_name) {
+        return true;
+    }
+    let mut current = CLASS_DATABASE.classes.get(class_name);
+    while let Some(class) = current {
+        if class.name.eq_ignore_ascii_case(superclass_name) {
+            return true;
+        }
+        current = class
+            .superclass
+            .as_ref()
+            .and_then(|superclass| CLASS_DATABASE.classes.get(superclass.as_ref()));
+    }
+    false
+}
+
+/**
+    Checks if the given class is a service, meaning that it can be
+    retrieved from the `DataModel` using `game:GetService(className)`.
+*/
+pub fn class_is_service(class_name: &str) -> bool {
+    CLASS_DATABASE
+        .classes
+        .get(class_name)
+        .is_some_and(|class| class.tags.contains(&ClassTag::Service))
+}
+
+/**
+    Gets the default values of all properties for the given class and its superclasses.
+
+    Properties of a class take precedence over properties with the same name in superclasses.
+*/
+pub fn default_properties(class_name: &str) -> Vec<(&'static str, &'static Variant)> {
+    let mut defaults = Vec::<(&str, &Variant)>::new();
+    let mut current = CLASS_DATABASE.classes.get(class_name);
+    while let Some(class) = current {
+        for (name, value) in &class.default_properties {
+            if !defaults
+                .iter()
+                .any(|(existing, _)| *existing == name.as_ref())
+            {
+                defaults.push((name.as_ref(), value));
+            }
+        }
+        current = class
+            .superclass
+            .as_ref()
+            .and_then(|superclass| CLASS_DATABASE.classes.get(superclass.as_ref()));
+    }
+    defaults
+}
+
+/**
+    Gets the name of the data type and the name of the enum (if any) for a property value.
+
+    Uses the property descriptor from the reflection database when it is known,
+    falling back to the type of the value itself for unknown properties.
+*/
+pub fn property_data_type(
+    class_name: &str,
+    property_name: &str,
+    value: &Variant,
+) -> (String, Option<String>) {
+    match find_property_descriptor(class_name, property_name).map(|d| &d.data_type) {
+        Some(DataType::Enum(enum_name)) => (String::from("Enum"), Some(enum_name.to_string())),
+        Some(DataType::Value(ty)) => (format!("{ty:?}"), None),
+        _ => (format!("{:?}", value.ty()), None),
+    }
+}
+
+/**
+    Gets the name of an enum item, given the name of its enum and its value.
+*/
+pub fn enum_item_name(enum_name: &str, value: u32) -> Option<&'static str> {
+    CLASS_DATABASE
+        .enums
+        .get(enum_name)?
+        .items
+        .iter()
+        .find(|(_, item_value)| **item_value == value)
+        .map(|(item_name, _)| item_name.as_ref())
+}
+
+/**
+    Gets the sorted names of all properties that were added, changed, or removed, or `None` if none were.
+*/
+pub fn changed_property_names(
+    current: &HashMap<String, Variant>,
+    new: &HashMap<String, Variant>,
+) -> Option<Vec<String>> {
+    let mut names = current
+        .iter()
+        .filter(|(name, value)| new.get(*name) != Some(*value))
+        .map(|(name, _)| name.clone())
+        .chain(
+            new.keys()
+                .filter(|name| !current.contains_key(*name))
+                .cloned(),
+        )
+        .collect::<Vec<_>>();
+    if names.is_empty() {
+        None
+    } else {
+        names.sort_unstable();
+        Some(names)
+    }
+}
+
+/**
+    Gets the tags of an instance, from its `Tags` property.
+*/
+pub fn instance_tags(inst: &Instance) -> impl Iterator<Item = &str> {
+    let tags = match inst.properties.get("Tags") {
+        Some(Variant::Tags(tags)) => Some(tags),
+        _ => None,
+    };
+    tags.into_iter().flat_map(|tags| tags.iter())
+}
+
+/**
+    Gets the attributes of an instance, from its `Attributes` property.
+*/
+pub fn instance_attributes(inst: &Instance) -> Option<&Attributes> {
+    match inst.properties.get("Attributes") {
+        Some(Variant::Attributes(attributes)) => Some(attributes),
+        _ => None,
+    }
+}
+
+/**
+    Resolves a property value in the same format that Rojo uses for project,
+    meta, and model files, into a typed value for a property of the given class.
+
+    Values may be explicit, such as `{ "Vector3": [1, 2, 3] }`, or implicit, such as
+    `[1, 2, 3]`, in which case the type is found using the reflection database.
+*/
+pub fn resolve_property_value(
+    class_name: &str,
+    property_name: &str,
+    value: JsonValue,
+) -> Result<Variant> {
+    let data_type = find_property_descriptor(class_name, property_name).map(|d| &d.data_type);
+    resolve_value(data_type, value)
+        .with_context(|| format!("invalid value for {class_name}.{property_name}"))
+}
+
+/**
+    Resolves attributes in the same format that Rojo uses for meta and model files.
+
+    Attributes have no known types, so implicit values may only be bools, numbers, and strings.
+*/
+pub fn resolve_attributes(attributes: JsonMap<String, JsonValue>) -> Result<Attributes> {
+    let mut resolved = Attributes::new();
+    for (name, value) in attributes {
+        let value = resolve_value(None, value)
+            .with_context(|| format!("invalid value for attribute '{name}'"))?;
+        resolved.insert(name, value);
+    }
+    Ok(resolved)
+}
+
+fn resolve_value(data_type: Option<&DataType>, value: JsonValue) -> Result<Variant> {
+    if value.is_object() {
+        return serde_json::from_value(value).context("invalid explicit value");
+    }
+    match (data_type, value) {
+        (Some(DataType::Enum(enum_name)), JsonValue::String(item_name)) => {
+            let descriptor = CLASS_DATABASE
+                .enums
+                .get(enum_name.as_ref())
+                .with_context(|| format!("unknown enum '{enum_name}'"))?;
+            let item_value = descriptor
+                .items
+                .get(item_name.as_str())
+                .with_context(|| format!("unknown item '{item_name}' for enum '{enum_name}'"))?;
+            Ok(Variant::Enum(Enum::from_u32(*item_value)))
+        }
+        (Some(DataType::Enum(_)), JsonValue::Number(number)) => number
+            .as_u64()
+            .and_then(|n| u32::try_from(n).ok())
+            .map(|n| Variant::Enum(Enum::from_u32(n)))
+            .context("expected a positive integer enum value"),
+        (Some(DataType::Value(ty)), value) => resolve_typed_value(*ty, value),
+        (None, JsonValue::Bool(b)) => Ok(Variant::Bool(b)),
+        (None, JsonValue::Number(n)) => Ok(Variant::Float64(n.as_f64().unwrap_or_default())),
+        (None, JsonValue::String(s)) => Ok(Variant::String(s)),
+        (_, value) => bail!("ambiguous value {value}, an explicit type is required"),
+    }
+}
+
+fn resolve_typed_value(ty: VariantType, value: JsonValue) -> Result<Variant> {
+    Ok(match (ty, value) {
+        (VariantType::Bool, JsonValue::Bool(b)) => Variant::Bool(b),
+        (VariantType::Float32, JsonValue::Number(n)) => {
+            Variant::Float32(n.as_f64().unwrap_or_default() as f32)
+        }
+        (VariantType::Float64, JsonValue::Number(n)) => {
+            Variant::Float64(n.as_f64().unwrap_or_default())
+        }
+        (VariantType::Int32, JsonValue::Number(n)) => Variant::Int32(
+            n.as_i64()
+                .and_then(|n| i32::try_from(n).ok())
+                .context("expected a 32-bit integer")?,
+        ),
+        (VariantType::Int64, JsonValue::Number(n)) => {
+            Variant::Int64(n.as_i64().context("expected a 64-bit integer")?)
+        }
+        (VariantType::String, JsonValue::String(s)) => Variant::String(s),
+        (VariantType::Content, JsonValue::String(s)) => Variant::Content(Content::from(s)),
+        (VariantType::BinaryString, JsonValue::String(s)) => {
+            Variant::BinaryString(BinaryString::from(s.into_bytes()))
+        }
+        (VariantType::Tags, JsonValue::Array(items)) => {
+            let tags = items
+                .into_iter()
+                .map(|item| match item {
+                    JsonValue::String(tag) => Ok(tag),
+                    _ => bail!("expected tags to be strings"),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Variant::Tags(Tags::from(tags))
+        }
+        (ty, JsonValue::Array(items)) => resolve_array_value(ty, &items)?,
+        (ty, value) => bail!("expected a value of type {ty:?}, got {value}"),
+    })
+}
+
+fn resolve_array_value(ty: VariantType, items: &[JsonValue]) -> Result<Variant> {
+    let numbers = items
+        .iter()
+        .map(JsonValue::as_f64)
+        .collect::<Option<Vec<_>>>();
+    let nested = items
+        .iter()
+        .map(|item| {
+            item.as_array()?
+                .iter()
+                .map(JsonValue::as_f64)
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Option<Vec<_>>>();
+
+    // NOTE: Values are always given as f64 in json, so these
+    // casts are intentionally lossy, same as they are in Rojo
+    Ok(match (ty, numbers.as_deref(), nested.as_deref()) {
+        (VariantType::Vector2, Some(&[x, y]), _) => Vector2::new(x as f32, y as f32).into(),
+        (VariantType::Vector3, Some(&[x, y, z]), _) => {
+            Vector3::new(x as f32, y as f32, z as f32).into()
+        }
+        (VariantType::Vector2int16, Some(&[x, y]), _) => {
+            Vector2int16::new(x as i16, y as i16).into()
+        }
+        (VariantType::Vector3int16, Some(&[x, y, z]), _) => {
+            Vector3int16::new(x as i16, y as i16, z as i16).into()
+        }
+        (VariantType::Color3, Some(&[r, g, b]), _) => {
+            Color3::new(r as f32, g as f32, b as f32).into()
+        }
+        (VariantType::Color3uint8, Some(&[r, g, b]), _) => {
+            Color3uint8::from(Color3::new(r as f32, g as f32, b as f32)).into()
+        }
+        (VariantType::UDim, Some(&[scale, offset]), _) => {
+            UDim::new(scale as f32, offset as i32).into()
+        }
+        (VariantType::NumberRange, Some(&[min, max]), _) => {
+            NumberRange::new(min as f32, max as f32).into()
+        }
+        (VariantType::CFrame, Some(&[x, y, z, r00, r01, r02, r10, r11, r12, r20, r21, r22]), _) => {
+            CFrame::new(
+                Vector3::new(x as f32, y as f32, z as f32),
+                Matrix3::new(
+                    Vector3::new(r00 as f32, r01 as f32, r02 as f32),
+                    Vector3::new(r10 as f32, r11 as f32, r12 as f32),
+                    Vector3::new(r20 as f32, r21 as f32, r22 as f32),
+                ),
+            )
+            .into()
+        }
+        (VariantType::UDim2, _, Some([x, y])) => match (x.as_slice(), y.as_slice()) {
+            (&[xs, xo], &[ys, yo]) => UDim2::new(
+                UDim::new(xs as f32, xo as i32),
+                UDim::new(ys as f32, yo as i32),
+            )
+            .into(),
+            _ => bail!("expected a value of type UDim2, got {items:?}"),
+        },
+        (VariantType::Rect, _, Some([min, max])) => match (min.as_slice(), max.as_slice()) {
+            (&[x0, y0], &[x1, y1]) => Rect::new(
+                Vector2::new(x0 as f32, y0 as f32),
+                Vector2::new(x1 as f32, y1 as f32),
+            )
+            .into(),
+            _ => bail!("expected a value of type Rect, got {items:?}"),
+        },
+        (ty, _, _) => bail!("expected a value of type {ty:?}, got {items:?}"),
+    })
+}
+
+#[test]
+fn resolve_implicit_and_explicit_values() {
+    use serde_json::json;
+
+    let resolve = |class_name: &str, property_name: &str, value: JsonValue| {
+        resolve_property_value(class_name, property_name, value).unwrap()
+    };
+
+    assert_eq!(
+        resolve("Part", "Size", json!([1, 2, 3])),
+        Variant::Vector3(Vector3::new(1.0, 2.0, 3.0))
+    );
+    assert_eq!(
+        resolve("Part", "Anchored", json!(true)),
+        Variant::Bool(true)
+    );
+    assert!(matches!(
+        resolve("Part", "Material", json!("Neon")),
+        Variant::Enum(_)
+    ));
+    assert_eq!(
+        resolve("Frame", "Size", json!([[1, 0], [0.5, 10]])),
+        Variant::UDim2(UDim2::new(UDim::new(1.0, 0), UDim::new(0.5, 10)))
+    );
+    assert_eq!(
+        resolve("StringValue", "Value", json!("Hello")),
+        Variant::String(String::from("Hello"))
+    );
+    assert_eq!(
+        resolve("Model", "Unknown", json!({ "Vector2": [1, 2] })),
+        Variant::Vector2(Vector2::new(1.0, 2.0))
+    );
+    assert_eq!(
+        resolve("Part", "Tags", json!(["Enemy"])),
+        Variant::Tags(Tags::from(vec![String::from("Enemy")]))
+    );
+    assert!(resolve_property_value("Part", "Size", json!("big")).is_err());
+
+    let (data_type, enum_name) =
+        property_data_type("Part", "Material", &resolve("Part", "Material", json!(256)));
+    assert_eq!(data_type, "Enum");
+    assert_eq!(enum_name.as_deref(), Some("Material"));
+}
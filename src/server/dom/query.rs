@@ -1,7 +1,7 @@
-use rbx_dom_weak::{types::Ref, Instance};
-use strsim::normalized_levenshtein;
+use std::{cmp::Ordering, str::FromStr};
 
-use super::InstanceMetadata;
+use super::{DomQueryFilter, DomSearchPattern, InstanceMetadata};
+use rbx_dom_weak::{types::Ref, Instance};
 
 pub const QUERY_LIMIT_DEFAULT: usize = 20;
 pub const QUERY_LIMIT_MAXIMUM: usize = 100;
@@ -13,63 +13,111 @@ pub struct DomQueryParams {
     pub skip_packages: bool,
     pub class_name: Option<String>,
     pub query_low: Option<String>,
+    pub pattern: Option<DomSearchPattern>,
+    pub filter: Option<DomQueryFilter>,
+    pub within: Option<Ref>,
+    pub after: Option<DomQueryResult>,
+    pub offset: usize,
     pub limit: Option<usize>,
 }
 
 impl DomQueryParams {
+    /**
+        Creates new query params from a query string.
+
+        Filters such as `is:BasePart`, `classname:Part`, `name:Door`, `tag:Enemy`, and `attr:Health`
+        may be combined using `and`, `or`, `not`, and parentheses, see [`DomQueryFilter::parse`].
+        Any other words are used to fuzzy match instance names, or full names if they
+        contain a period, such as `rs.door` for `Game.ReplicatedStorage.Door`.
+    */
     pub fn from_str(query_string: &str) -> Self {
-        let query_low = query_string.to_ascii_lowercase();
-        Self {
-            query_low: Some(query_low),
-            ..Default::default()
+        let (filter, words) = DomQueryFilter::parse(query_string);
+
+        let mut params = Self::default();
+        if !words.is_empty() || filter.is_none() {
+            let query_low = words.join(" ").to_ascii_lowercase();
+            params.pattern = DomSearchPattern::new(&query_low);
+            params.query_low = Some(query_low);
         }
+
+        // NOTE: Filters are commonly used to find instances inside of
+        // model files, which we would normally skip since they are not files
+        if filter.is_some() {
+            params.skip_non_files = false;
+        }
+
+        params.filter = filter;
+        params
     }
 
     pub fn limit(&self) -> usize {
         self.limit
             .unwrap_or(QUERY_LIMIT_DEFAULT)
-            .max(QUERY_LIMIT_MAXIMUM)
+            .min(QUERY_LIMIT_MAXIMUM)
+    }
+
+    /**
+        Gets the ranges of characters in the given instance name that match the
+        text of this query, as UTF-16 offsets (the same as JavaScript strings use).
+    */
+    pub fn matched_ranges(&self, name: &str) -> Vec<(usize, usize)> {
+        let Some(pattern) = self.pattern.as_ref() else {
+            return Vec::new();
+        };
+        let utf16_offset = |byte_offset: usize| name[..byte_offset].encode_utf16().count();
+        pattern
+            .matched_ranges(name)
+            .into_iter()
+            .map(|range| (utf16_offset(range.start), utf16_offset(range.end)))
+            .collect()
     }
 
-    pub fn score(&self, inst: &Instance, meta: Option<&InstanceMetadata>) -> Option<f64> {
+    /**
+        Scores how well an instance matches this query, or returns `None` if it does not.
+
+        The full name of the instance is only used, and must only be given, if
+        the query matches full names, see [`DomSearchPattern::is_full_name`].
+    */
+    pub fn score(
+        &self,
+        inst: &Instance,
+        meta: Option<&InstanceMetadata>,
+        full_name: Option<&str>,
+    ) -> Option<f64> {
         // NOTE: Metadata scoring is used here for "extra" sorting
         // with instances that we already think match our query, or
         // for skipping instances completely using any extra filters
         // Metadata matching is also slightly faster than scoring
         // so we do that first to get a bit of extra perf
         if let Some(meta_score) = self.metadata_score(meta) {
-            if let Some(inst_score) = self.instance_score(inst) {
+            if let Some(inst_score) = self.instance_score(inst, full_name) {
                 return Some(inst_score + meta_score);
             }
         }
         None
     }
 
-    fn instance_score(&self, inst: &Instance) -> Option<f64> {
+    fn instance_score(&self, inst: &Instance, full_name: Option<&str>) -> Option<f64> {
         if let Some(class_name) = self.class_name.as_deref() {
             if !class_name.eq_ignore_ascii_case(&inst.class) {
                 return None; // No class name match, filtered out
             }
         }
 
-        let mut score = 0.0f64;
-        if let Some(query) = self.query_low.as_deref() {
-            let inst_name_low = inst.name.to_ascii_lowercase();
-            if query == inst_name_low {
-                // Exact name match should have max score
-                score += 1.0;
-            } else {
-                // Nonexact matches are weighted slightly towards finding exact substrings
-                score += 0.75 * normalized_levenshtein(query, &inst_name_low);
-                if inst_name_low.contains(query) {
-                    // Boost exact substrings for nonexact matches, making the boost
-                    // slightly stronger per how long the query is, up to 8 characters
-                    score += 0.25f64.min(0.25 * ((query.len() as f64) / 8.0));
-                }
+        if let Some(filter) = self.filter.as_ref() {
+            if !filter.matches(inst) {
+                return None; // No filter match, filtered out
             }
         }
 
-        if score >= self.minimum_score {
+        let mut score = 0.0f64;
+        if let Some(pattern) = self.pattern.as_ref() {
+            score += pattern.score(&inst.name, full_name)?;
+        }
+
+        // NOTE: Queries with only filters have no
+        // name to score, and should match any name
+        if self.query_low.is_none() || score >= self.minimum_score {
             Some(score)
         } else {
             None
@@ -115,35 +163,154 @@ impl Default for DomQueryParams {
             skip_packages: true,
             class_name: None,
             query_low: None,
+            pattern: None,
+            filter: None,
+            within: None,
+            after: None,
+            offset: 0,
             limit: None,
         }
     }
 }
 
+/**
+    A single result of a query.
+
+    Results are ordered with the best matches first, and results with
+    the same score are ordered by their ids, so that results are always
+    in the same order, and can be used as cursors to paginate queries.
+*/
 #[derive(Debug, Clone, PartialEq)]
 pub struct DomQueryResult {
     pub score: f64,
     pub id: Ref,
+    id_key: String,
 }
 
 impl DomQueryResult {
     pub fn new(score: f64, id: Ref) -> Self {
-        Self { score, id }
+        Self {
+            score,
+            id,
+            id_key: id.to_string(),
+        }
+    }
+
+    /**
+        Gets an opaque cursor for this result, which can be parsed
+        back into a result using [`DomQueryResult::from_str`].
+    */
+    pub fn cursor(&self) -> String {
+        format!("{}:{}", self.score, self.id_key)
+    }
+}
+
+impl FromStr for DomQueryResult {
+    type Err = &'static str;
+
+    fn from_str(cursor: &str) -> Result<Self, Self::Err> {
+        let (score, id) = cursor.split_once(':').ok_or("missing separator")?;
+        let score = score.parse::<f64>().map_err(|_| "invalid score")?;
+        let id = id.parse::<Ref>().map_err(|_| "invalid id")?;
+        if !score.is_finite() {
+            return Err("invalid score");
+        }
+        Ok(Self::new(score, id))
     }
 }
 
 impl Eq for DomQueryResult {}
 
 impl Ord for DomQueryResult {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.score
-            .partial_cmp(&other.score)
-            .expect("scores must not be inf or nan")
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .score
+            .total_cmp(&self.score)
+            .then_with(|| self.id_key.cmp(&other.id_key))
     }
 }
 
 impl PartialOrd for DomQueryResult {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
+
+#[test]
+fn query_filters() {
+    use rbx_dom_weak::{
+        types::{Attributes, Tags, Variant},
+        InstanceBuilder, WeakDom,
+    };
+
+    let mut dom = WeakDom::new(InstanceBuilder::new("Folder"));
+    let mut attributes = Attributes::new();
+    attributes.insert(String::from("Health"), Variant::Float64(100.0));
+    let enemy = dom.insert(
+        dom.root_ref(),
+        InstanceBuilder::new("Model")
+            .with_name("Zombie")
+            .with_property("Tags", Tags::from(vec![String::from("Enemy")]))
+            .with_property("Attributes", attributes),
+    );
+    let other = dom.insert(
+        dom.root_ref(),
+        InstanceBuilder::new("Model").with_name("Zombie"),
+    );
+    let enemy = dom.get_by_ref(enemy).unwrap();
+    let other = dom.get_by_ref(other).unwrap();
+
+    let params = DomQueryParams::from_str("tag:enemy");
+    assert_eq!(params.query_low, None);
+    assert!(params.instance_score(enemy, None).is_some());
+    assert!(params.instance_score(other, None).is_none());
+
+    let params = DomQueryParams::from_str("zombie attr:Health");
+    assert_eq!(params.query_low.as_deref(), Some("zombie"));
+    assert!(params.instance_score(enemy, None).is_some());
+    assert!(params.instance_score(other, None).is_none());
+
+    let params = DomQueryParams::from_str("zombie attr:Speed");
+    assert!(params.instance_score(enemy, None).is_none());
+
+    let params = DomQueryParams::from_str("is:pvinstance -tag:Enemy");
+    assert!(params.instance_score(enemy, None).is_none());
+    assert!(params.instance_score(other, None).is_some());
+    assert!(DomQueryParams::from_str("is:BasePart")
+        .instance_score(other, None)
+        .is_none());
+
+    let params = DomQueryParams::from_str("zombie");
+    assert!(params.instance_score(other, None).is_some());
+    assert!(DomQueryParams::from_str("")
+        .instance_score(other, None)
+        .is_none());
+}
+
+#[test]
+fn query_limits_and_ranges() {
+    let mut params = DomQueryParams::from_str("door");
+    assert_eq!(params.limit(), QUERY_LIMIT_DEFAULT);
+    params.limit = Some(5);
+    assert_eq!(params.limit(), 5);
+    params.limit = Some(1000);
+    assert_eq!(params.limit(), QUERY_LIMIT_MAXIMUM);
+
+    assert_eq!(params.matched_ranges("FrontDoor"), vec![(5, 9)]);
+    assert_eq!(
+        params.matched_ranges("DropOffRoom"),
+        vec![(0, 1), (2, 3), (4, 5), (7, 8)]
+    );
+    assert_eq!(params.matched_ranges("Window"), vec![]);
+    assert_eq!(
+        DomQueryParams::from_str("door").matched_ranges("🚪Door"),
+        vec![(2, 6)]
+    );
+
+    let first = DomQueryResult::new(2.0, Ref::new());
+    let second = DomQueryResult::new(1.0, Ref::new());
+    assert!(first < second);
+    let cursor = second.cursor().parse::<DomQueryResult>().unwrap();
+    assert_eq!(cursor, second);
+    assert!("1.0".parse::<DomQueryResult>().is_err());
+}
@@ -0,0 +1,320 @@
+use rbx_dom_weak::Instance;
+
+use super::{class_is_a, instance_attributes, instance_tags};
+
+/**
+    A filter for instances, parsed from a query string.
+
+    Filters are combined using boolean logic, and all name
+    and class name comparisons are case-insensitive.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DomQueryFilter {
+    /// Matches instances with exactly the given class name - `classname:Part`
+    ClassName(String),
+    /// Matches instances with the given class name, or a subclass of it - `is:BasePart`
+    IsA(String),
+    /// Matches instances with names containing the given (lowercase) string - `name:foo`
+    Name(String),
+    /// Matches instances with the given tag - `tag:Enemy`
+    Tag(String),
+    /// Matches instances with the given attribute - `attr:Health`
+    Attribute(String),
+    /// Matches instances that do not match the inner filter - `-is:Script`, `!is:Script`, `not is:Script`
+    Not(Box<Self>),
+    /// Matches instances that match all of the inner filters - `is:BasePart and tag:Enemy`
+    And(Vec<Self>),
+    /// Matches instances that match any of the inner filters - `is:Script or is:ModuleScript`
+    Or(Vec<Self>),
+}
+
+impl DomQueryFilter {
+    /**
+        Parses a query string into a filter, and any words that are not part of
+        a filter, which should instead be used to fuzzy match instance names.
+
+        Filters are matched using `and` by default, and `or` has lower precedence than
+        `and`, the same as in the Roblox Studio explorer. Groups may be created using
+        parentheses, and values containing spaces may be quoted, such as `name:"My Part"`.
+
+        Parsing never fails, since queries are usually typed one character at a time,
+        so any unbalanced parentheses or dangling operators are ignored instead.
+
+        Returns `None` for the filter if the query contains no filters.
+    */
+    pub fn parse(query: &str) -> (Option<Self>, Vec<String>) {
+        let mut parser = Parser {
+            tokens: tokenize(query),
+            position: 0,
+            words: Vec::new(),
+        };
+        let mut filter = None;
+        while parser.peek().is_some() {
+            // NOTE: A stray closing parenthesis ends the expression early, so we skip
+            // past it and combine the rest with what we already parsed, using `and`
+            let next = parser.parse_or();
+            filter = combine(filter, next, false);
+            if matches!(parser.peek(), Some(Token::Close)) {
+                parser.position += 1;
+            }
+        }
+        (filter, parser.words)
+    }
+
+    pub fn matches(&self, inst: &Instance) -> bool {
+        match self {
+            Self::ClassName(class_name) => inst.class.eq_ignore_ascii_case(class_name),
+            Self::IsA(class_name) => class_is_a(&inst.class, class_name),
+            Self::Name(name_low) => inst.name.to_ascii_lowercase().contains(name_low),
+            Self::Tag(tag) => instance_tags(inst).any(|t| t.eq_ignore_ascii_case(tag)),
+            Self::Attribute(attr) => instance_attributes(inst)
+                .is_some_and(|attrs| attrs.iter().any(|(a, _)| a.eq_ignore_ascii_case(attr))),
+            Self::Not(inner) => !inner.matches(inst),
+            Self::And(inner) => inner.iter().all(|filter| filter.matches(inst)),
+            Self::Or(inner) => inner.iter().any(|filter| filter.matches(inst)),
+        }
+    }
+
+    fn from_prefixed(prefix: &str, value: &str) -> Option<Self> {
+        if value.is_empty() {
+            return None;
+        }
+        let value = value.to_string();
+        Some(match prefix.to_ascii_lowercase().as_str() {
+            "classname" => Self::ClassName(value),
+            "is" => Self::IsA(value),
+            "name" => Self::Name(value.to_ascii_lowercase()),
+            "tag" => Self::Tag(value),
+            "attr" => Self::Attribute(value),
+            _ => return None,
+        })
+    }
+}
+
+/**
+    Combines two optional filters using `and` or `or`, flattening them if they already use the same one.
+*/
+fn combine(
+    left: Option<DomQueryFilter>,
+    right: Option<DomQueryFilter>,
+    is_or: bool,
+) -> Option<DomQueryFilter> {
+    let (left, right) = match (left, right) {
+        (None, filter) | (filter, None) => return filter,
+        (Some(left), Some(right)) => (left, right),
+    };
+    let mut filters = Vec::new();
+    for filter in [left, right] {
+        match filter {
+            DomQueryFilter::Or(inner) if is_or => filters.extend(inner),
+            DomQueryFilter::And(inner) if !is_or => filters.extend(inner),
+            filter => filters.push(filter),
+        }
+    }
+    Some(if is_or {
+        DomQueryFilter::Or(filters)
+    } else {
+        DomQueryFilter::And(filters)
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word { text: String, quoted: bool },
+    Open,
+    Close,
+    Not,
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Self::Word { text, quoted: false } if text.eq_ignore_ascii_case(keyword))
+    }
+}
+
+fn tokenize(query: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '-' | '!' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            _ => {
+                // Words end at whitespace or parentheses, unless those are
+                // quoted, and quotes may start anywhere in the word
+                let mut text = String::new();
+                let mut quoted = false;
+                let mut in_quotes = false;
+                while let Some(&c) = chars.peek() {
+                    if c == '"' {
+                        quoted = true;
+                        in_quotes = !in_quotes;
+                    } else if !in_quotes && (c.is_whitespace() || c == '(' || c == ')') {
+                        break;
+                    } else {
+                        text.push(c);
+                    }
+                    chars.next();
+                }
+                tokens.push(Token::Word { text, quoted });
+            }
+        }
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    words: Vec<String>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Option<DomQueryFilter> {
+        let mut filter = self.parse_and();
+        while self.peek().is_some_and(|t| t.is_keyword("or")) {
+            self.position += 1;
+            let start = self.position;
+            let right = self.parse_and();
+            if self.position == start {
+                continue; // Dangling `or` without anything after it
+            }
+            // NOTE: If either side has no filters, it matches any instance, and
+            // so does the entire `or` - words are still used for fuzzy matching
+            filter = match (filter, right) {
+                (Some(left), Some(right)) => combine(Some(left), Some(right), true),
+                _ => None,
+            };
+        }
+        filter
+    }
+
+    fn parse_and(&mut self) -> Option<DomQueryFilter> {
+        let mut filter = None;
+        while let Some(token) = self.peek() {
+            if matches!(token, Token::Close) || token.is_keyword("or") {
+                break;
+            } else if token.is_keyword("and") {
+                self.position += 1;
+                continue;
+            }
+            let next = self.parse_unary();
+            filter = combine(filter, next, false);
+        }
+        filter
+    }
+
+    fn parse_unary(&mut self) -> Option<DomQueryFilter> {
+        match self.next()? {
+            Token::Not => self.parse_negated(),
+            token if token.is_keyword("not") => self.parse_negated(),
+            Token::Open => {
+                let filter = self.parse_or();
+                if matches!(self.peek(), Some(Token::Close)) {
+                    self.position += 1;
+                }
+                filter
+            }
+            Token::Close => None,
+            Token::Word { text, .. } => match text.split_once(':') {
+                Some((prefix, value)) => match DomQueryFilter::from_prefixed(prefix, value) {
+                    Some(filter) => Some(filter),
+                    None => {
+                        self.words.push(text);
+                        None
+                    }
+                },
+                None => {
+                    self.words.push(text);
+                    None
+                }
+            },
+        }
+    }
+
+    fn parse_negated(&mut self) -> Option<DomQueryFilter> {
+        // NOTE: Negated words can not be used for fuzzy matching,
+        // so we turn them into name filters, excluding any matches
+        let filter = match self.peek() {
+            Some(Token::Word { text, .. }) if !text.contains(':') => {
+                let name = text.to_ascii_lowercase();
+                self.position += 1;
+                (!name.is_empty()).then_some(DomQueryFilter::Name(name))
+            }
+            Some(_) => self.parse_unary(),
+            None => None,
+        };
+        filter.map(|filter| DomQueryFilter::Not(Box::new(filter)))
+    }
+}
+
+#[test]
+fn parse_query_filters() {
+    use DomQueryFilter::*;
+
+    let parse = |query: &str| DomQueryFilter::parse(query);
+    let is_a = |class_name: &str| IsA(class_name.to_string());
+
+    assert_eq!(parse("foo bar"), (None, vec!["foo".into(), "bar".into()]));
+    assert_eq!(parse("is:BasePart"), (Some(is_a("BasePart")), vec![]));
+    assert_eq!(
+        parse("door is:BasePart -classname:Part"),
+        (
+            Some(And(vec![
+                is_a("BasePart"),
+                Not(Box::new(ClassName("Part".into())))
+            ])),
+            vec!["door".into()]
+        )
+    );
+    assert_eq!(
+        parse("is:Script or is:ModuleScript and not tag:Ignore").0,
+        Some(Or(vec![
+            is_a("Script"),
+            And(vec![
+                is_a("ModuleScript"),
+                Not(Box::new(Tag("Ignore".into())))
+            ])
+        ]))
+    );
+    assert_eq!(
+        parse("(is:Script or is:ModuleScript) and -test").0,
+        Some(And(vec![
+            Or(vec![is_a("Script"), is_a("ModuleScript")]),
+            Not(Box::new(Name("test".into())))
+        ]))
+    );
+    assert_eq!(parse(r#"name:"My Part""#).0, Some(Name("my part".into())));
+
+    // Incomplete queries are parsed as far as possible
+    assert_eq!(parse("(is:Script or").0, Some(is_a("Script")));
+    assert_eq!(parse("is:Script or foo").0, None);
+    assert_eq!(parse("is:Script )").0, Some(is_a("Script")));
+    assert_eq!(
+        parse("is: foo:bar"),
+        (None, vec!["is:".into(), "foo:bar".into()])
+    );
+}
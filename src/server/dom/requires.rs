@@ -0,0 +1,412 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rbx_dom_weak::types::{Ref, Variant};
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use tokio_util::sync::CancellationToken;
+
+use crate::util::{
+    luau::{scan_requires, LuauRequire, LuauRequireArgument},
+    path::make_absolute_and_clean,
+};
+
+use super::{class_is_a, Dom, DomError, DomErrorKind, DomResult, InstancePath};
+
+const SCRIPT_CLASS_NAME: &str = "LuaSourceContainer";
+const SCRIPT_FILE_EXTENSIONS: &[&str] = &["luau", "lua"];
+const SCRIPT_INIT_FILE_STEM: &str = "init";
+const REQUIRE_ALIAS_SELF: &str = "self";
+
+type CachedRequires = (Option<SystemTime>, Arc<Vec<LuauRequire>>);
+
+/**
+    Requires that have been scanned, by the path of the script file that they are in.
+
+    Script files are scanned when they are first needed, and scanned again if they
+    have been modified since, so that building a require graph for an entire
+    project only needs to read the scripts that actually changed.
+
+    NOTE: Requires are needed while handling read-only requests, which only
+    have shared access to the dom, so the cache uses interior mutability.
+*/
+#[derive(Debug, Default)]
+pub struct ScriptRequires {
+    cache: Mutex<HashMap<PathBuf, CachedRequires>>,
+}
+
+impl ScriptRequires {
+    /**
+        Gets the requires in the script file at the given path, scanning it if necessary.
+
+        Files that can not be read have no requires.
+    */
+    pub fn get(&self, path: &Path) -> Arc<Vec<LuauRequire>> {
+        let modified = path.metadata().and_then(|m| m.modified()).ok();
+        if let Some((cached, requires)) = self.lock().get(path) {
+            if *cached == modified {
+                return requires.clone();
+            }
+        }
+
+        // NOTE: Files are read and scanned without holding the lock,
+        // so that many scripts can be scanned in parallel at once
+        let requires = Arc::new(
+            fs::read_to_string(path)
+                .map(|source| scan_requires(&source))
+                .unwrap_or_default(),
+        );
+        self.lock()
+            .insert(path.to_path_buf(), (modified, requires.clone()));
+        requires
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, CachedRequires>> {
+        self.cache
+            .lock()
+            .expect("script requires cache was poisoned")
+    }
+}
+
+/**
+    A call to `require` in a script, and the instance that it requires, if it could be resolved.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomRequire {
+    /// The script that contains the call to `require`.
+    pub script: Ref,
+    /// Zero-based line number of the call in the script.
+    pub line: usize,
+    /// The argument of the call, as written in the script.
+    pub expression: String,
+    /// The instance that is required, if it exists.
+    pub target: Option<Ref>,
+}
+
+impl Dom {
+    /**
+        Checks if the given instance is a script, meaning it may contain calls to `require`.
+    */
+    pub fn is_script(&self, id: Ref) -> bool {
+        self.get_instance(id)
+            .is_some_and(|inst| class_is_a(&inst.class, SCRIPT_CLASS_NAME))
+    }
+
+    /**
+        Gets all calls to `require` in the given script, in the order they appear.
+
+        Requires may be instance expressions, such as `script.Parent.Util`, which
+        may go through local variables, or strings, such as `"./Util"` or
+        `"@Packages/Promise"`, using aliases from the `.luaurc` of the project.
+
+        Returns an error if the instance does not exist, or is not a script.
+    */
+    pub fn get_requires(&self, id: Ref) -> DomResult<Vec<DomRequire>> {
+        if self.get_instance(id).is_none() {
+            return Err(DomError::not_found());
+        }
+        let requires = self.scan_script_requires(id).ok_or_else(|| {
+            DomError::new(DomErrorKind::InvalidOperation, "Instance is not a script")
+        })?;
+        Ok(requires
+            .iter()
+            .map(|require| self.resolve_require(id, require))
+            .collect())
+    }
+
+    /**
+        Gets all calls to `require` in any script that require the given instance,
+        sorted by the full names of the scripts and then by line numbers.
+
+        Returns an error if the instance does not exist, or if the
+        operation was cancelled using the given token before it finished.
+    */
+    pub fn get_required_by(
+        &self,
+        id: Ref,
+        cancel: &CancellationToken,
+    ) -> DomResult<Vec<DomRequire>> {
+        if self.get_instance(id).is_none() {
+            return Err(DomError::not_found());
+        }
+        let mut requires = self
+            .get_all_requires(cancel)?
+            .into_iter()
+            .filter(|require| require.target == Some(id))
+            .map(|require| (self.get_full_name(require.script), require))
+            .collect::<Vec<_>>();
+        requires.sort_by(|(a_name, a), (b_name, b)| a_name.cmp(b_name).then(a.line.cmp(&b.line)));
+        Ok(requires.into_iter().map(|(_, require)| require).collect())
+    }
+
+    /**
+        Finds cycles of scripts that require each other, where each script
+        in a cycle requires the next one, and the last requires the first.
+
+        Every group of scripts that require each other is part of at least one of
+        the returned cycles, but not every possible cycle within a group is returned.
+
+        Returns an error if the operation was cancelled using the given token before it finished.
+    */
+    pub fn find_require_cycles(&self, cancel: &CancellationToken) -> DomResult<Vec<Vec<Ref>>> {
+        let mut graph = HashMap::<Ref, Vec<Ref>>::default();
+        for require in self.get_all_requires(cancel)? {
+            if let Some(target) = require.target {
+                let targets = graph.entry(require.script).or_default();
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+        }
+
+        // NOTE: Depth-first search where every edge back to a script that is
+        // still on the stack closes a cycle - iterative, since require chains
+        // in large projects can be deep enough to overflow the call stack
+        let mut cycles = Vec::new();
+        let mut visited = HashSet::<Ref>::default();
+        let mut stack_indices = HashMap::<Ref, usize>::default();
+        for start in graph.keys().copied() {
+            if !visited.insert(start) {
+                continue;
+            }
+            let mut stack = vec![(start, 0)];
+            stack_indices.insert(start, 0);
+            while let Some((id, edge)) = stack.last().copied() {
+                stack.last_mut().unwrap().1 += 1;
+                match graph
+                    .get(&id)
+                    .and_then(|targets| targets.get(edge))
+                    .copied()
+                {
+                    Some(target) => {
+                        if let Some(index) = stack_indices.get(&target) {
+                            cycles.push(stack[*index..].iter().map(|(id, _)| *id).collect());
+                        } else if visited.insert(target) {
+                            stack_indices.insert(target, stack.len());
+                            stack.push((target, 0));
+                        }
+                    }
+                    None => {
+                        stack_indices.remove(&id);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        Ok(cycles)
+    }
+
+    fn get_all_requires(&self, cancel: &CancellationToken) -> DomResult<Vec<DomRequire>> {
+        let ids = self.ids.iter().copied().collect::<Vec<_>>();
+        let requires = ids
+            .par_iter()
+            .filter(|_| !cancel.is_cancelled())
+            .filter_map(|id| {
+                self.scan_script_requires(*id)
+                    .map(|requires| (*id, requires))
+            })
+            .flat_map_iter(|(id, requires)| {
+                requires
+                    .iter()
+                    .map(|require| self.resolve_require(id, require))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        if cancel.is_cancelled() {
+            return Err(DomError::cancelled());
+        }
+        Ok(requires)
+    }
+
+    /**
+        Scans the source of a script for requires, either from its file, or its
+        `Source` property if it has no file, or returns `None` if it is not a script.
+    */
+    fn scan_script_requires(&self, id: Ref) -> Option<Arc<Vec<LuauRequire>>> {
+        let inst = self.get_instance(id)?;
+        if !class_is_a(&inst.class, SCRIPT_CLASS_NAME) {
+            return None;
+        }
+        match self.get_script_file(id) {
+            Some(file) => Some(self.requires.get(file)),
+            None => match inst.properties.get("Source") {
+                Some(Variant::String(source)) => Some(Arc::new(scan_requires(source))),
+                _ => Some(Arc::default()),
+            },
+        }
+    }
+
+    fn get_script_file(&self, id: Ref) -> Option<&Path> {
+        let file = self.get_metadata(id)?.paths.as_ref()?.file.as_deref()?;
+        let extension = file.extension()?.to_str()?;
+        SCRIPT_FILE_EXTENSIONS.contains(&extension).then_some(file)
+    }
+
+    fn resolve_require(&self, script: Ref, require: &LuauRequire) -> DomRequire {
+        let target = match &require.argument {
+            LuauRequireArgument::Instance(expression) => InstancePath::parse(expression)
+                .ok()
+                .and_then(|path| self.find_by_instance_path(&path, Some(script)).ok())
+                .flatten(),
+            LuauRequireArgument::Path(path) => self.resolve_require_path(script, path),
+        };
+        DomRequire {
+            script,
+            line: require.line,
+            expression: require.expression.clone(),
+            target,
+        }
+    }
+
+    /**
+        Resolves a string require to a script, using the same rules as Luau:
+
+        - `./Util` and `../Util` are relative to the directory of the script file,
+          or to the parent of that directory for `init` scripts
+        - `@self/Util` is relative to the directory of the script file
+        - `@Alias/Util` is relative to the path of the alias in the `.luaurc` of the project
+    */
+    fn resolve_require_path(&self, script: Ref, path: &str) -> Option<Ref> {
+        let file = self.get_script_file(script);
+        let file_dir = file.and_then(Path::parent);
+
+        let full_path = match path.strip_prefix('@') {
+            Some(aliased) => {
+                let (alias, rest) = aliased.split_once('/').unwrap_or((aliased, ""));
+                let base = if alias.eq_ignore_ascii_case(REQUIRE_ALIAS_SELF) {
+                    file_dir?.to_path_buf()
+                } else {
+                    self.resolve_require_alias(script, alias)?
+                };
+                base.join(rest)
+            }
+            None => {
+                let is_init = file
+                    .and_then(Path::file_stem)
+                    .is_some_and(|stem| stem == SCRIPT_INIT_FILE_STEM);
+                let base = if is_init {
+                    file_dir.and_then(Path::parent)
+                } else {
+                    file_dir
+                };
+                base?.join(path)
+            }
+        };
+
+        let full_path = make_absolute_and_clean(full_path);
+        let file_name = full_path.file_name()?.to_str()?;
+        let candidates =
+            SCRIPT_FILE_EXTENSIONS
+                .iter()
+                .map(|extension| full_path.with_file_name(format!("{file_name}.{extension}")))
+                .chain(SCRIPT_FILE_EXTENSIONS.iter().map(|extension| {
+                    full_path.join(format!("{SCRIPT_INIT_FILE_STEM}.{extension}"))
+                }))
+                .chain([full_path.clone()]);
+        candidates
+            .filter_map(|candidate| self.find_by_path(candidate))
+            .find(|id| self.is_script(*id))
+    }
+
+    fn resolve_require_alias(&self, script: Ref, alias: &str) -> Option<PathBuf> {
+        let root = self.get_root_ancestor(script)?;
+        let index = self.roots.iter().position(|id| *id == Some(root))?;
+        let luaurc = self.root_projects[index].luaurc.as_ref()?;
+        let (_, alias_path) = luaurc
+            .summary
+            .as_ref()?
+            .aliases
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(alias))?;
+        Some(luaurc.path.parent()?.join(alias_path))
+    }
+}
+
+#[test]
+fn require_graph() {
+    use super::{Config, InstanceNode};
+
+    fn node(
+        class_name: &str,
+        name: &str,
+        source: &str,
+        children: Vec<InstanceNode>,
+    ) -> InstanceNode {
+        let mut node = InstanceNode::for_test(class_name, name, children);
+        if !source.is_empty() {
+            node.properties
+                .insert(String::from("Source"), Variant::String(source.to_string()));
+        }
+        node
+    }
+
+    let mut dom = Dom::new(Config::default());
+    dom.apply_new_root(
+        0,
+        Some(node(
+            "DataModel",
+            "Project",
+            "",
+            vec![node(
+                "ReplicatedStorage",
+                "ReplicatedStorage",
+                "",
+                vec![
+                    node(
+                        "ModuleScript",
+                        "A",
+                        "local Shared = game:GetService('ReplicatedStorage')\nreturn require(Shared.B)",
+                        vec![],
+                    ),
+                    node("ModuleScript", "B", "require(script.Parent.A)", vec![]),
+                    node(
+                        "Script",
+                        "Main",
+                        "require(script.Parent.A)\nrequire(script.Missing)",
+                        vec![],
+                    ),
+                    node("Folder", "Folder", "", vec![]),
+                ],
+            )],
+        )),
+    );
+
+    let find = |source: &str| {
+        dom.find_by_instance_path(&InstancePath::parse(source).unwrap(), None)
+            .unwrap()
+            .unwrap()
+    };
+    let a = find("game.ReplicatedStorage.A");
+    let b = find("game.ReplicatedStorage.B");
+    let main = find("game.ReplicatedStorage.Main");
+    let folder = find("game.ReplicatedStorage.Folder");
+
+    let targets = |id: Ref| {
+        dom.get_requires(id)
+            .unwrap()
+            .into_iter()
+            .map(|require| (require.line, require.target))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(targets(a), vec![(1, Some(b))]);
+    assert_eq!(targets(main), vec![(0, Some(a)), (1, None)]);
+    assert!(dom.get_requires(folder).is_err());
+
+    let cancel = CancellationToken::new();
+    let required_by = dom
+        .get_required_by(a, &cancel)
+        .unwrap()
+        .into_iter()
+        .map(|require| require.script)
+        .collect::<Vec<_>>();
+    assert_eq!(required_by, vec![b, main]);
+
+    let cycles = dom.find_require_cycles(&cancel).unwrap();
+    assert_eq!(cycles.len(), 1);
+    assert_eq!(cycles[0].len(), 2);
+    assert!(cycles[0].contains(&a) && cycles[0].contains(&b));
+}
@@ -0,0 +1,353 @@
+use std::ops::Range;
+
+use rbx_dom_weak::{types::Ref, WeakDom};
+use rustc_hash::FxHashMap as HashMap;
+
+/// Separator between instance names in full names, such as `Game.ReplicatedStorage.Foo`
+pub const FULL_NAME_SEPARATOR: char = '.';
+
+/**
+    An index of instance names and full names, used to quickly skip
+    instances that can not possibly match a query, without looking at them.
+
+    Each instance gets a mask of the characters in its name, and of the characters
+    in its full name, meaning its own name and the names of all of its ancestors.
+    Queries are subsequence (fzf-style) matches where all characters of the query
+    must appear in order, so any instance with a mask that is missing one of
+    the characters of the query is skipped, which is almost all of them.
+
+    NOTE: Trigram indexes only work for finding substrings, not subsequences, and
+    keeping a mask per instance is cheap enough to update on every single change.
+*/
+#[derive(Debug, Default)]
+pub struct DomSearchIndex {
+    entries: HashMap<Ref, DomSearchEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DomSearchEntry {
+    name_mask: u64,
+    full_name_mask: u64,
+}
+
+impl DomSearchIndex {
+    /**
+        Indexes an instance and all of its descendants.
+
+        This must be called whenever an instance is inserted, renamed,
+        or moved, since the full names of its descendants change too.
+    */
+    pub fn index(&mut self, dom: &WeakDom, id: Ref) {
+        let Some(inst) = dom.get_by_ref(id) else {
+            return;
+        };
+        let parent_mask = self
+            .entries
+            .get(&inst.parent())
+            .map(|parent| parent.full_name_mask | char_bit(FULL_NAME_SEPARATOR));
+
+        let mut pending = vec![(id, parent_mask.unwrap_or_default())];
+        while let Some((id, parent_mask)) = pending.pop() {
+            let Some(inst) = dom.get_by_ref(id) else {
+                continue;
+            };
+            let name_mask = char_mask(&inst.name);
+            let full_name_mask = parent_mask | name_mask;
+            self.entries.insert(
+                id,
+                DomSearchEntry {
+                    name_mask,
+                    full_name_mask,
+                },
+            );
+            let child_mask = full_name_mask | char_bit(FULL_NAME_SEPARATOR);
+            pending.extend(inst.children().iter().map(|child| (*child, child_mask)));
+        }
+    }
+
+    pub fn remove(&mut self, id: Ref) {
+        self.entries.remove(&id);
+    }
+
+    /**
+        Checks if the instance may match the given pattern, using only its masks.
+
+        Instances that are not indexed may always match.
+    */
+    pub fn may_match(&self, id: Ref, pattern: &DomSearchPattern) -> bool {
+        self.entries.get(&id).is_none_or(|entry| {
+            let mask = if pattern.is_full_name() {
+                entry.full_name_mask
+            } else {
+                entry.name_mask
+            };
+            mask & pattern.mask == pattern.mask
+                && entry.name_mask & pattern.name_mask == pattern.name_mask
+        })
+    }
+}
+
+/**
+    A compiled pattern for subsequence (fzf-style) matching of instance names.
+
+    Patterns containing a `.` are matched against full names instead, such as
+    `Game.ReplicatedStorage.Foo`, and anything after the last `.` must then
+    also match the name of the instance itself, so that `rs.foo` finds
+    `Foo` inside of `ReplicatedStorage`, but not any of its descendants.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomSearchPattern {
+    text_low: String,
+    chars: Vec<char>,
+    mask: u64,
+    name_start: Option<usize>,
+    name_mask: u64,
+}
+
+impl DomSearchPattern {
+    /**
+        Creates a new pattern from a lowercase query, ignoring any whitespace.
+
+        Returns `None` if the query has no characters to match.
+    */
+    pub fn new(query_low: &str) -> Option<Self> {
+        let chars = query_low
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<Vec<_>>();
+        if chars.is_empty() {
+            return None;
+        }
+        let name_start = chars
+            .iter()
+            .rposition(|c| *c == FULL_NAME_SEPARATOR)
+            .map(|index| index + 1);
+        let name_mask = chars[name_start.unwrap_or_default()..]
+            .iter()
+            .fold(0, |mask, c| mask | char_bit(*c));
+        Some(Self {
+            text_low: query_low.trim().to_string(),
+            mask: chars.iter().fold(0, |mask, c| mask | char_bit(*c)),
+            chars,
+            name_start,
+            name_mask,
+        })
+    }
+
+    /**
+        Checks if this pattern should be matched against full names instead of names.
+    */
+    pub fn is_full_name(&self) -> bool {
+        self.name_start.is_some()
+    }
+
+    fn name_chars(&self) -> &[char] {
+        &self.chars[self.name_start.unwrap_or_default()..]
+    }
+
+    /**
+        Scores how well the given name matches this pattern, from `0.0` to `1.0`,
+        or returns `None` if it does not match. Full name patterns must also be
+        given the full name of the instance, or they will never match.
+
+        Exact matches always score `1.0`, and other matches score higher the more
+        of the name they cover, the fewer separate runs of characters they are
+        split into, and the more of those runs start at the start of a word.
+    */
+    pub fn score(&self, name: &str, full_name: Option<&str>) -> Option<f64> {
+        if self.is_full_name() {
+            let full_name = full_name?;
+            if !self.name_chars().is_empty() {
+                find_best_match(self.name_chars(), name)?;
+            }
+            find_best_match(&self.chars, full_name).map(|(score, _)| score)
+        } else if name.eq_ignore_ascii_case(&self.text_low) {
+            Some(1.0)
+        } else {
+            find_best_match(&self.chars, name).map(|(score, _)| score)
+        }
+    }
+
+    /**
+        Gets the byte ranges of characters in the given name that match this pattern.
+
+        Full name patterns only match the part of the pattern after the last `.`.
+    */
+    pub fn matched_ranges(&self, name: &str) -> Vec<Range<usize>> {
+        if self.name_chars().is_empty() {
+            return Vec::new();
+        }
+        find_best_match(self.name_chars(), name)
+            .map(|(_, ranges)| ranges)
+            .unwrap_or_default()
+    }
+}
+
+/**
+    Finds the best way to match the given lowercase characters, in order, to the given text.
+
+    Every occurrence of the first character is tried as a starting point, matching the
+    rest as early as possible after it, which finds substrings and word starts
+    without needing to try every possible combination of positions.
+
+    Returns the score of the match and the byte ranges of matched characters.
+*/
+fn find_best_match(pattern: &[char], text: &str) -> Option<(f64, Vec<Range<usize>>)> {
+    let chars = text.char_indices().collect::<Vec<_>>();
+    let first = *pattern.first()?;
+    if pattern.len() > chars.len() {
+        return None;
+    }
+
+    let mut best: Option<(f64, Vec<usize>)> = None;
+    let starts = (0..chars.len()).filter(|i| chars[*i].1.to_ascii_lowercase() == first);
+    for start in starts {
+        let mut positions = vec![start];
+        let mut next = start + 1;
+        for c in &pattern[1..] {
+            match (next..chars.len()).find(|i| chars[*i].1.to_ascii_lowercase() == *c) {
+                Some(position) => {
+                    positions.push(position);
+                    next = position + 1;
+                }
+                None => break,
+            }
+        }
+        if positions.len() < pattern.len() {
+            // NOTE: Starting any later can not match more characters
+            break;
+        }
+        let score = score_positions(&chars, &positions);
+        if best
+            .as_ref()
+            .is_none_or(|(best_score, _)| score > *best_score)
+        {
+            best = Some((score, positions));
+        }
+    }
+
+    let (score, positions) = best?;
+    let mut ranges = Vec::<Range<usize>>::new();
+    for position in positions {
+        let (start, c) = chars[position];
+        let end = start + c.len_utf8();
+        match ranges.last_mut() {
+            Some(last) if last.end == start => last.end = end,
+            _ => ranges.push(start..end),
+        }
+    }
+    Some((score, ranges))
+}
+
+fn score_positions(chars: &[(usize, char)], positions: &[usize]) -> f64 {
+    let mut runs = 0;
+    let mut word_starts = 0;
+    for (index, position) in positions.iter().enumerate() {
+        if index > 0 && positions[index - 1] + 1 == *position {
+            continue;
+        }
+        runs += 1;
+        if is_word_start(chars, *position) {
+            word_starts += 1;
+        }
+    }
+    let coverage = positions.len() as f64 / chars.len() as f64;
+    let runs = runs as f64;
+    0.5 * coverage + 0.25 / runs + 0.25 * (word_starts as f64 / runs)
+}
+
+/**
+    Checks if the character at the given position starts a word, meaning that it is
+    the first character, comes after a separator, or is an uppercase letter in camelCase.
+*/
+fn is_word_start(chars: &[(usize, char)], position: usize) -> bool {
+    let current = chars[position].1;
+    match position.checked_sub(1).map(|previous| chars[previous].1) {
+        None => true,
+        Some(previous) => {
+            !current.is_alphanumeric()
+                || !previous.is_alphanumeric()
+                || (previous.is_lowercase() && current.is_uppercase())
+                || (previous.is_numeric() && current.is_alphabetic())
+        }
+    }
+}
+
+fn char_mask(text: &str) -> u64 {
+    text.chars().fold(0, |mask, c| mask | char_bit(c))
+}
+
+/**
+    Gets the bit for a character in a mask - letters and digits get their own
+    bits, other ascii characters share the remaining bits, and anything else
+    shares the very last bit. Shared bits only make masks less precise.
+*/
+fn char_bit(c: char) -> u64 {
+    let c = c.to_ascii_lowercase();
+    let bit = match c {
+        'a'..='z' => c as u32 - 'a' as u32,
+        '0'..='9' => 26 + (c as u32 - '0' as u32),
+        c if c.is_ascii() => 36 + (c as u32 % 27),
+        _ => 63,
+    };
+    1 << bit
+}
+
+#[test]
+fn search_patterns() {
+    let pattern = DomSearchPattern::new("door").unwrap();
+    assert!(!pattern.is_full_name());
+    assert_eq!(pattern.score("Door", None), Some(1.0));
+    assert!(pattern.score("FrontDoor", None) > pattern.score("Outdoors", None));
+    assert!(pattern.score("FrontDoor", None) > pattern.score("DropOffRoom", None));
+    assert_eq!(pattern.score("Window", None), None);
+    assert_eq!(pattern.matched_ranges("FrontDoor"), vec![5..9]);
+    assert_eq!(
+        pattern.matched_ranges("DropOffRoom"),
+        vec![0..1, 2..3, 4..5, 7..8]
+    );
+
+    let pattern = DomSearchPattern::new("rs.foo").unwrap();
+    assert!(pattern.is_full_name());
+    assert_eq!(pattern.score("Foo", None), None);
+    assert!(pattern
+        .score("Foo", Some("Game.ReplicatedStorage.Foo"))
+        .is_some());
+    assert!(pattern
+        .score("Bar", Some("Game.ReplicatedStorage.Foo.Bar"))
+        .is_none());
+    assert_eq!(pattern.matched_ranges("Foo"), vec![0..3]);
+
+    assert!(DomSearchPattern::new("  ").is_none());
+}
+
+#[test]
+fn search_index() {
+    use rbx_dom_weak::InstanceBuilder;
+
+    let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_name("Game"));
+    let storage = dom.insert(
+        dom.root_ref(),
+        InstanceBuilder::new("ReplicatedStorage").with_name("ReplicatedStorage"),
+    );
+    let door = dom.insert(storage, InstanceBuilder::new("Model").with_name("Door"));
+
+    let mut index = DomSearchIndex::default();
+    index.index(&dom, dom.root_ref());
+
+    let pattern = |query: &str| DomSearchPattern::new(query).unwrap();
+    assert!(index.may_match(door, &pattern("dr")));
+    assert!(!index.may_match(door, &pattern("dx")));
+    assert!(!index.may_match(door, &pattern("model")));
+    assert!(index.may_match(door, &pattern("game.rs.door")));
+    assert!(!index.may_match(door, &pattern("workspace.door")));
+
+    // Renaming an ancestor changes the full names of all of its descendants
+    dom.get_by_ref_mut(storage).unwrap().name = String::from("Xylophone");
+    index.index(&dom, storage);
+    assert!(!index.may_match(door, &pattern("game.rs.door")));
+    assert!(index.may_match(door, &pattern("xylo.door")));
+
+    index.remove(door);
+    assert!(index.may_match(door, &pattern("anything")));
+}
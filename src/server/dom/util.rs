@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 const FILE_PATH_SUFFIXES: &[&str] = &[".luau", ".lua", ".rbxmx", ".rbxm", ".txt", ".csv", ".json"];
 
@@ -23,3 +23,29 @@ pub fn is_openable_file(file_name: &str) -> bool {
 pub fn is_false(v: &bool) -> bool {
     v == &false
 }
+
+pub fn relocate_path(path: &Path, moves: &[(PathBuf, PathBuf)]) -> PathBuf {
+    moves
+        .iter()
+        .find_map(|(old, new)| path.strip_prefix(old).ok().map(|rest| new.join(rest)))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/**
+    Adds a move for the containing folder of an instance created from a file.
+
+    Instances created from files have their containing folder as a path too,
+    which is not moved itself, but must still point to the new folder.
+
+    This must be called after all other moves have been added,
+    since paths are matched against moves in order.
+*/
+pub fn push_folder_move(moves: &mut Vec<(PathBuf, PathBuf)>, folder: Option<&Path>) {
+    if let (Some(folder), Some((_, new_path))) = (folder, moves.first()) {
+        if !moves.iter().any(|(old, _)| old == folder) {
+            if let Some(new_folder) = new_path.parent().map(Path::to_path_buf) {
+                moves.push((folder.to_path_buf(), new_folder));
+            }
+        }
+    }
+}
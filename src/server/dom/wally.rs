@@ -0,0 +1,231 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use rustc_hash::FxHashMap as HashMap;
+use serde::Serialize;
+use tracing::debug;
+
+use crate::util::{
+    path::make_absolute_and_clean,
+    wally::{
+        WallyLockfile, WallyManifest, WallyPackageSpec, WallyRealm, WALLY_LOCKFILE_FILE_NAME,
+        WALLY_MANIFEST_FILE_NAME,
+    },
+};
+
+use super::util::is_false;
+
+type ModifiedTimes = (Option<SystemTime>, Option<SystemTime>);
+type CachedProject = (ModifiedTimes, Option<Arc<WallyProject>>);
+
+/**
+    Wally projects that have been read, by the directory that they are in.
+
+    Projects are read when they are first needed, and read
+    again if their manifest or lockfile has been modified since.
+
+    NOTE: Projects are needed while creating instance metadata, which only has
+    shared access to the dom, so the cache uses interior mutability for reads.
+*/
+#[derive(Debug, Default)]
+pub struct WallyProjects {
+    cache: Mutex<HashMap<PathBuf, CachedProject>>,
+}
+
+impl WallyProjects {
+    /**
+        Gets the Wally project in the given directory, reading it if necessary.
+
+        Returns `None` if the directory has neither a manifest nor a lockfile.
+    */
+    pub fn get(&self, dir: impl AsRef<Path>) -> Option<Arc<WallyProject>> {
+        let dir = make_absolute_and_clean(dir);
+        let manifest_path = dir.join(WALLY_MANIFEST_FILE_NAME);
+        let lockfile_path = dir.join(WALLY_LOCKFILE_FILE_NAME);
+        let modified = (modified_time(&manifest_path), modified_time(&lockfile_path));
+
+        let mut cache = self.cache.lock().expect("wally project cache was poisoned");
+        if let Some((cached, project)) = cache.get(&dir) {
+            if *cached == modified {
+                return project.clone();
+            }
+        }
+
+        let project = match modified {
+            (None, None) => None,
+            _ => Some(Arc::new(WallyProject::read(&dir, modified))),
+        };
+        cache.insert(dir, (modified, project.clone()));
+        project
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|m| m.modified()).ok()
+}
+
+/**
+    A Wally project, which has a manifest, a lockfile, or both.
+*/
+#[derive(Debug, Default)]
+pub struct WallyProject {
+    pub dir: PathBuf,
+    pub manifest: Option<WallyManifest>,
+    pub lockfile: Option<WallyLockfile>,
+}
+
+impl WallyProject {
+    fn read(dir: &Path, modified: ModifiedTimes) -> Self {
+        let manifest = modified.0.and_then(|_| {
+            WallyManifest::read(dir.join(WALLY_MANIFEST_FILE_NAME))
+                .map_err(|e| debug!("{e:#}"))
+                .ok()
+        });
+        let lockfile = modified.1.and_then(|_| {
+            WallyLockfile::read(dir.join(WALLY_LOCKFILE_FILE_NAME))
+                .map_err(|e| debug!("{e:#}"))
+                .ok()
+        });
+        Self {
+            dir: dir.to_path_buf(),
+            manifest,
+            lockfile,
+        }
+    }
+
+    pub fn manifest_path(&self) -> Option<PathBuf> {
+        self.manifest
+            .as_ref()
+            .map(|_| self.dir.join(WALLY_MANIFEST_FILE_NAME))
+    }
+
+    pub fn lockfile_path(&self) -> Option<PathBuf> {
+        self.lockfile
+            .as_ref()
+            .map(|_| self.dir.join(WALLY_LOCKFILE_FILE_NAME))
+    }
+
+    /**
+        Gets information about a package in this project, given the version that is installed.
+
+        A package is outdated if its resolved version in the lockfile does not satisfy the
+        requirement in the manifest, or if the installed version is not in the lockfile.
+    */
+    pub fn package(&self, scope: &str, name: &str, installed_version: &str) -> WallyPackage {
+        let mut package = WallyPackage {
+            scope: scope.to_owned(),
+            name: name.to_owned(),
+            version: installed_version.to_owned(),
+            ..Default::default()
+        };
+
+        if let Some(lockfile) = self.lockfile.as_ref() {
+            let mut locked = lockfile.find_packages(scope, name).peekable();
+            let first = locked.peek().map(|p| p.version.clone());
+            let installed = locked.find(|p| p.version == installed_version);
+            package.dependencies = installed
+                .map(|p| {
+                    p.dependencies
+                        .iter()
+                        .map(|(_, spec)| spec.clone())
+                        .collect()
+                })
+                .unwrap_or_default();
+            package.outdated = first.is_some() && installed.is_none();
+            package.resolved_version = installed.map(|p| p.version.clone()).or(first);
+        }
+
+        if let Some((realm, alias, spec)) = self
+            .manifest
+            .as_ref()
+            .and_then(|manifest| manifest.find_dependency(scope, name))
+        {
+            let resolved = package
+                .resolved_version
+                .as_deref()
+                .unwrap_or(installed_version);
+            package.outdated |= spec.is_satisfied_by(resolved) == Some(false);
+            package.dependency = Some(realm);
+            package.alias = Some(alias.to_owned());
+            package.requirement = Some(spec.version);
+        }
+
+        package
+    }
+
+    /**
+        Gets all packages in the lockfile of this project, except the
+        project itself, which are the nodes of the dependency graph.
+
+        Packages that are not installed in any of the packages folders have no realm.
+    */
+    pub fn packages(&self) -> Vec<WallyPackage> {
+        let Some(lockfile) = self.lockfile.as_ref() else {
+            return Vec::new();
+        };
+        let own_name = self
+            .manifest
+            .as_ref()
+            .and_then(|manifest| manifest.package.as_ref())
+            .map(|package| package.name.as_str());
+        lockfile
+            .packages
+            .iter()
+            .filter(|locked| Some(locked.name.as_str()) != own_name)
+            .filter_map(|locked| {
+                let spec = WallyPackageSpec::parse(&format!("{}@{}", locked.name, locked.version))?;
+                let mut package = self.package(&spec.scope, &spec.name, &spec.version);
+                package.realm = WallyRealm::ALL.into_iter().find(|realm| {
+                    self.dir
+                        .join(realm.packages_dir_name())
+                        .join("_Index")
+                        .join(index_folder_name(&spec))
+                        .exists()
+                });
+                Some(package)
+            })
+            .collect()
+    }
+}
+
+/**
+    Gets the name of the folder that Wally installs a package into, inside of an `_Index` folder.
+*/
+fn index_folder_name(spec: &WallyPackageSpec) -> String {
+    format!("{}_{}@{}", spec.scope, spec.name, spec.version)
+}
+
+/**
+    A single package in a Wally project.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WallyPackage {
+    pub scope: String,
+    pub name: String,
+    /// The installed version of the package, or the version that the lockfile resolved.
+    pub version: String,
+    /// The realm that the package is installed in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realm: Option<WallyRealm>,
+    /// The realm of the dependency in the manifest, if it is a direct dependency.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dependency: Option<WallyRealm>,
+    /// The alias of the dependency in the manifest, if it is a direct dependency.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// The version requirement in the manifest, if it is a direct dependency.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requirement: Option<String>,
+    /// The version that the lockfile resolved, if there is a lockfile.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_version: Option<String>,
+    /// Resolved dependencies of the package, such as `scope/name@1.0.0`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub outdated: bool,
+}
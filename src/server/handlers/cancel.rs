@@ -0,0 +1,111 @@
+use dashmap::DashMap;
+use serde::Deserialize;
+use tokio_util::sync::CancellationToken;
+
+use crate::server::rpc::{RpcId, RpcMessage};
+
+/**
+    Keeps track of requests that are currently being handled, so that they can be cancelled.
+*/
+#[derive(Debug, Default)]
+pub struct InFlightRequests {
+    tokens: DashMap<RpcId, CancellationToken>,
+}
+
+impl InFlightRequests {
+    /**
+        Starts tracking the given request, returning a token that
+        will be cancelled if a cancellation request is received.
+
+        Notifications can not be cancelled, and are not tracked.
+    */
+    pub fn begin(&self, msg: &RpcMessage) -> CancellationToken {
+        let token = CancellationToken::new();
+        if let Some(id) = msg.get_id().filter(|_| !msg.is_notification()) {
+            self.tokens.insert(id.clone(), token.clone());
+        }
+        token
+    }
+
+    /**
+        Stops tracking the given request, once it has been responded to.
+    */
+    pub fn finish(&self, id: &RpcId) {
+        self.tokens.remove(id);
+    }
+
+    /**
+        Cancels the request with the given id, returning `true` if it was still in flight.
+    */
+    pub fn cancel(&self, id: &RpcId) -> bool {
+        match self.tokens.remove(id) {
+            Some((_, token)) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct CancelRequest {
+    id: RpcId,
+}
+
+impl CancelRequest {
+    pub fn respond_to(self, msg: &RpcMessage, requests: &InFlightRequests) -> RpcMessage {
+        let was_cancelled = requests.cancel(&self.id);
+        msg.respond()
+            .with_data(was_cancelled)
+            .expect("bool is always serializable")
+    }
+}
+
+#[tokio::test]
+async fn cancel_requests() {
+    use crate::server::{dom::Dom, rpc::RpcProtocol, Config};
+
+    async fn decode(line: &str) -> RpcMessage {
+        let input = RpcProtocol::JsonRpc.read_from(&mut line.as_bytes()).await;
+        input.unwrap().unwrap().messages.remove(0)
+    }
+    let requests = InFlightRequests::default();
+
+    // Cancelling a request cancels its token, and stops tracking it
+    let query =
+        decode(r#"{"jsonrpc":"2.0","id":1,"method":"dom/findByQuery","params":{"query":"a"}}"#)
+            .await;
+    let token = requests.begin(&query);
+    let cancel =
+        decode(r#"{"jsonrpc":"2.0","id":2,"method":"$/cancelRequest","params":{"id":1}}"#).await;
+    let response = super::handle_cancel_request(cancel.clone(), &requests).unwrap();
+    assert!(response.get_data::<bool>().unwrap());
+    assert!(token.is_cancelled());
+    assert!(requests.tokens.is_empty());
+    let response = super::handle_cancel_request(cancel, &requests).unwrap();
+    assert!(!response.get_data::<bool>().unwrap());
+
+    // Cancelled reads respond with the request cancelled error code
+    let dom = Dom::new(Config::default());
+    let response = super::handle_read_request(query.clone(), &dom, &token)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut output = Vec::new();
+    RpcProtocol::JsonRpc
+        .write_message_to(&mut output, response)
+        .await
+        .unwrap();
+    let output = serde_json::from_slice::<serde_json::Value>(&output).unwrap();
+    assert_eq!(output["id"], 1);
+    assert_eq!(output["error"]["code"], -32800);
+
+    // Finished requests are no longer tracked, and can not be cancelled
+    let token = requests.begin(&query);
+    requests.finish(query.get_id().unwrap());
+    assert!(requests.tokens.is_empty());
+    assert!(!requests.cancel(query.get_id().unwrap()));
+    assert!(!token.is_cancelled());
+}
@@ -3,20 +3,26 @@ use std::path::PathBuf;
 use anyhow::{Context, Result};
 use rbx_dom_weak::types::Ref;
 use serde::Deserialize;
+use tokio_util::sync::CancellationToken;
 
 use crate::server::{
-    dom::{Dom, DomQueryParams},
+    dom::{
+        default_properties, Dom, DomError, DomErrorKind, DomQueryParams, DomQueryResult,
+        InstancePath, InstancePathStyle,
+    },
     rpc::RpcMessage,
 };
 
-use super::util::ResponseInstance;
+use super::util::{
+    respond_with_result, ResponseInstance, ResponseProperty, ResponseQueryResult, ResponseRequire,
+};
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(super) struct RootRequest {}
 
 impl RootRequest {
-    pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &Dom) -> Result<RpcMessage> {
         let instance = dom
             .get_root_id()
             .and_then(|id| dom.get_instance(id))
@@ -28,6 +34,25 @@ impl RootRequest {
     }
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RootsRequest {}
+
+impl RootsRequest {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &Dom) -> Result<RpcMessage> {
+        let instances = dom
+            .get_root_ids()
+            .into_iter()
+            .filter_map(|id| dom.get_instance(id))
+            .map(ResponseInstance::from_dom_instance)
+            .map(|inst| inst.with_dom_metadata(dom))
+            .collect::<Vec<_>>();
+        msg.respond()
+            .with_data(instances)
+            .context("failed to serialize response")
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(super) struct GetRequest {
@@ -35,7 +60,7 @@ pub(super) struct GetRequest {
 }
 
 impl GetRequest {
-    pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &Dom) -> Result<RpcMessage> {
         let instance = dom
             .get_instance(self.id)
             .map(ResponseInstance::from_dom_instance)
@@ -46,6 +71,44 @@ impl GetRequest {
     }
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct PropertiesRequest {
+    id: Ref,
+    #[serde(default)]
+    include_defaults: bool,
+}
+
+impl PropertiesRequest {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &Dom) -> Result<RpcMessage> {
+        let Some(inst) = dom.get_instance(self.id) else {
+            return msg
+                .respond()
+                .with_data(None::<()>)
+                .context("failed to serialize response");
+        };
+
+        let mut properties = inst
+            .properties
+            .iter()
+            .map(|(name, value)| ResponseProperty::from_dom_property(&inst.class, name, value))
+            .collect::<Result<Vec<_>>>()?;
+        if self.include_defaults {
+            for (name, value) in default_properties(&inst.class) {
+                if !inst.properties.contains_key(name) {
+                    let prop = ResponseProperty::from_dom_property(&inst.class, name, value)?;
+                    properties.push(prop.with_default(true));
+                }
+            }
+        }
+        properties.sort_by(|a, b| a.name().cmp(b.name()));
+
+        msg.respond()
+            .with_data(Some(properties))
+            .context("failed to serialize response")
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(super) struct ChildrenRequest {
@@ -53,7 +116,7 @@ pub(super) struct ChildrenRequest {
 }
 
 impl ChildrenRequest {
-    pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &Dom) -> Result<RpcMessage> {
         let child_ids = dom
             .get_instance(self.id)
             .map(|inst| inst.children())
@@ -77,7 +140,7 @@ pub(super) struct AncestorsRequest {
 }
 
 impl AncestorsRequest {
-    pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &Dom) -> Result<RpcMessage> {
         let mut current = Some(self.id);
         let mut ancestor_ids = Vec::new();
         while let Some(current_id) = current.take() {
@@ -113,7 +176,7 @@ pub(super) struct FindByPathRequest {
 }
 
 impl FindByPathRequest {
-    pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &Dom) -> Result<RpcMessage> {
         let instance = dom
             .find_by_path(self.path)
             .and_then(|id| dom.get_instance(id))
@@ -125,28 +188,169 @@ impl FindByPathRequest {
     }
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct FindByInstancePathRequest {
+    path: String,
+    script_id: Option<Ref>,
+}
+
+impl FindByInstancePathRequest {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &Dom) -> Result<RpcMessage> {
+        let instance = InstancePath::parse(&self.path)
+            .and_then(|path| dom.find_by_instance_path(&path, self.script_id))
+            .map(|id| {
+                id.and_then(|id| dom.get_instance(id))
+                    .map(ResponseInstance::from_dom_instance)
+                    .map(|inst| inst.with_dom_metadata(dom))
+            });
+        respond_with_result(&msg, instance)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct GetInstancePathRequest {
+    id: Ref,
+    #[serde(default)]
+    style: InstancePathStyle,
+}
+
+impl GetInstancePathRequest {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &Dom) -> Result<RpcMessage> {
+        let path = dom.get_instance_path(self.id, self.style);
+        msg.respond()
+            .with_data(path)
+            .context("failed to serialize response")
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(super) struct FindByQueryRequest {
     query: String,
     limit: Option<usize>,
+    offset: Option<usize>,
+    cursor: Option<String>,
+    within_id: Option<Ref>,
+    minimum_score: Option<f64>,
+    skip_non_files: Option<bool>,
+    skip_packages: Option<bool>,
+    class_name: Option<String>,
 }
 
 impl FindByQueryRequest {
-    pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
+    pub async fn respond_to(
+        self,
+        msg: RpcMessage,
+        dom: &Dom,
+        cancel: &CancellationToken,
+    ) -> Result<RpcMessage> {
         let mut params = DomQueryParams::from_str(&self.query);
         params.limit = self.limit;
+        params.offset = self.offset.unwrap_or_default();
+        params.within = self.within_id;
+        params.class_name = self.class_name;
+        if let Some(minimum_score) = self.minimum_score {
+            params.minimum_score = minimum_score;
+        }
+        if let Some(skip_non_files) = self.skip_non_files {
+            params.skip_non_files = skip_non_files;
+        }
+        if let Some(skip_packages) = self.skip_packages {
+            params.skip_packages = skip_packages;
+        }
+        if let Some(cursor) = self.cursor.as_deref() {
+            match cursor.parse::<DomQueryResult>() {
+                Ok(after) => params.after = Some(after),
+                Err(e) => {
+                    let err = DomError::new(
+                        DomErrorKind::InvalidValue,
+                        format!("Invalid query cursor '{cursor}' - {e}"),
+                    );
+                    return Ok(msg.respond().with_error(err));
+                }
+            }
+        }
 
-        let instances = dom
-            .find_by_query(params)
-            .iter()
-            .filter_map(|id| dom.get_instance(*id))
-            .map(ResponseInstance::from_dom_instance)
-            .map(|inst| inst.with_dom_metadata(dom))
-            .collect::<Vec<_>>();
+        let results = dom.find_by_query(&params, cancel).map(|results| {
+            results
+                .iter()
+                .filter_map(|result| ResponseQueryResult::from_dom_result(dom, &params, result))
+                .collect::<Vec<_>>()
+        });
 
-        msg.respond()
-            .with_data(instances)
-            .context("failed to serialize response")
+        respond_with_result(&msg, results)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RequiresRequest {
+    id: Ref,
+}
+
+impl RequiresRequest {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &Dom) -> Result<RpcMessage> {
+        let requires = dom.get_requires(self.id).map(|requires| {
+            requires
+                .iter()
+                .map(|require| ResponseRequire::from_dom_require(dom, require, require.target))
+                .collect::<Vec<_>>()
+        });
+        respond_with_result(&msg, requires)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RequiredByRequest {
+    id: Ref,
+}
+
+impl RequiredByRequest {
+    pub async fn respond_to(
+        self,
+        msg: RpcMessage,
+        dom: &Dom,
+        cancel: &CancellationToken,
+    ) -> Result<RpcMessage> {
+        let requires = dom.get_required_by(self.id, cancel).map(|requires| {
+            requires
+                .iter()
+                .map(|require| {
+                    ResponseRequire::from_dom_require(dom, require, Some(require.script))
+                })
+                .collect::<Vec<_>>()
+        });
+        respond_with_result(&msg, requires)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RequireCyclesRequest {}
+
+impl RequireCyclesRequest {
+    pub async fn respond_to(
+        self,
+        msg: RpcMessage,
+        dom: &Dom,
+        cancel: &CancellationToken,
+    ) -> Result<RpcMessage> {
+        let cycles = dom.find_require_cycles(cancel).map(|cycles| {
+            cycles
+                .iter()
+                .map(|cycle| {
+                    cycle
+                        .iter()
+                        .filter_map(|id| dom.get_instance(*id))
+                        .map(ResponseInstance::from_dom_instance)
+                        .map(|inst| inst.with_dom_metadata(dom))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        });
+        respond_with_result(&msg, cycles)
     }
 }
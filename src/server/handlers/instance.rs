@@ -1,9 +1,14 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use rbx_dom_weak::types::Ref;
 use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use tokio_util::sync::CancellationToken;
 
-use super::util::ResponseInstance;
-use crate::server::{dom::Dom, rpc::RpcMessage};
+use super::util::{respond_with_result, ResponseInstance};
+use crate::server::{
+    dom::{Dom, DomBatchOperation, DomError, DomErrorKind, DomResult, InstanceMetadataActions},
+    rpc::RpcMessage,
+};
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -15,15 +20,15 @@ pub(super) struct InsertRequest {
 
 impl InsertRequest {
     pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
-        let inserted_instance_id_opt = dom
+        let inserted_instance = dom
             .insert_instance(self.parent_id, self.class_name, self.name)
             .await
-            .and_then(|id| dom.get_instance(id))
-            .map(ResponseInstance::from_dom_instance)
-            .map(|inst| inst.with_dom_metadata(dom));
-        msg.respond()
-            .with_data(inserted_instance_id_opt)
-            .context("failed to serialize response")
+            .map(|id| {
+                dom.get_instance(id)
+                    .map(ResponseInstance::from_dom_instance)
+                    .map(|inst| inst.with_dom_metadata(dom))
+            });
+        respond_with_result(&msg, inserted_instance)
     }
 }
 
@@ -36,10 +41,27 @@ pub(super) struct RenameRequest {
 
 impl RenameRequest {
     pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
-        let was_renamed = dom.rename_instance(self.id, self.name).await;
-        msg.respond()
-            .with_data(was_renamed)
-            .context("failed to serialize response")
+        let was_renamed = dom.rename_instance(self.id, self.name).await.map(|_| true);
+        respond_with_result(&msg, was_renamed)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct SetPropertyRequest {
+    id: Ref,
+    name: String,
+    #[serde(default)]
+    value: Option<JsonValue>,
+}
+
+impl SetPropertyRequest {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
+        let was_set = dom
+            .set_property(self.id, self.name, self.value)
+            .await
+            .map(|_| true);
+        respond_with_result(&msg, was_set)
     }
 }
 
@@ -51,10 +73,8 @@ pub(super) struct DeleteRequest {
 
 impl DeleteRequest {
     pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
-        let was_deleted = dom.delete_instance(self.id).await;
-        msg.respond()
-            .with_data(was_deleted)
-            .context("failed to serialize response")
+        let was_deleted = dom.delete_instance(self.id).await.map(|_| true);
+        respond_with_result(&msg, was_deleted)
     }
 }
 
@@ -67,9 +87,160 @@ pub(super) struct MoveRequest {
 
 impl MoveRequest {
     pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
-        let was_deleted = dom.move_instance(self.id, self.parent_id).await;
-        msg.respond()
-            .with_data(was_deleted)
-            .context("failed to serialize response")
+        let was_moved = dom
+            .move_instance(self.id, self.parent_id)
+            .await
+            .map(|_| true);
+        respond_with_result(&msg, was_moved)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct CopyRequest {
+    ids: Vec<Ref>,
+}
+
+impl CopyRequest {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
+        let was_copied = dom.set_clipboard(self.ids).map(|_| true);
+        respond_with_result(&msg, was_copied)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct PasteRequest {
+    parent_id: Ref,
+}
+
+impl PasteRequest {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
+        let pasted =
+            match ensure_action(dom, self.parent_id, |a| a.can_paste_into, PASTE_INTO_DENIED) {
+                Ok(()) => dom.paste_instances(self.parent_id).await,
+                Err(e) => Err(e),
+            };
+        let instances = pasted.map(|ids| {
+            ids.iter()
+                .filter_map(|id| dom.get_instance(*id))
+                .map(ResponseInstance::from_dom_instance)
+                .map(|inst| inst.with_dom_metadata(dom))
+                .collect::<Vec<_>>()
+        });
+        respond_with_result(&msg, instances)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct DuplicateRequest {
+    id: Ref,
+    parent_id: Option<Ref>,
+}
+
+impl DuplicateRequest {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
+        // NOTE: Duplicating without a parent pastes next to the instance
+        // itself, otherwise it is the same as pasting into the given parent
+        let allowed = match self.parent_id {
+            Some(parent_id) => {
+                ensure_action(dom, parent_id, |a| a.can_paste_into, PASTE_INTO_DENIED)
+            }
+            None => ensure_action(
+                dom,
+                self.id,
+                |a| a.can_paste_sibling,
+                "Can not paste next to this instance",
+            ),
+        };
+        let parent_id = self
+            .parent_id
+            .or_else(|| dom.get_instance(self.id).map(|inst| inst.parent()));
+        let duplicated_instance = match (allowed, parent_id) {
+            (Err(e), _) => Err(e),
+            (Ok(()), Some(parent_id)) => dom.copy_instance(self.id, parent_id).await,
+            (Ok(()), None) => Err(DomError::not_found()),
+        }
+        .map(|id| {
+            dom.get_instance(id)
+                .map(ResponseInstance::from_dom_instance)
+                .map(|inst| inst.with_dom_metadata(dom))
+        });
+        respond_with_result(&msg, duplicated_instance)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct UndoRequest {}
+
+impl UndoRequest {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
+        let was_undone = dom.undo().await;
+        respond_with_result(&msg, was_undone)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RedoRequest {}
+
+impl RedoRequest {
+    pub async fn respond_to(self, msg: RpcMessage, dom: &mut Dom) -> Result<RpcMessage> {
+        let was_redone = dom.redo().await;
+        respond_with_result(&msg, was_redone)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct BatchRequest {
+    operations: Vec<DomBatchOperation>,
+}
+
+impl BatchRequest {
+    pub async fn respond_to(
+        self,
+        msg: RpcMessage,
+        dom: &mut Dom,
+        cancel: &CancellationToken,
+    ) -> Result<RpcMessage> {
+        let instances = dom.apply_batch(self.operations, cancel).await.map(|ids| {
+            ids.into_iter()
+                .map(|id_opt| {
+                    id_opt
+                        .and_then(|id| dom.get_instance(id))
+                        .map(ResponseInstance::from_dom_instance)
+                        .map(|inst| inst.with_dom_metadata(dom))
+                })
+                .collect::<Vec<_>>()
+        });
+        respond_with_result(&msg, instances)
+    }
+}
+
+const PASTE_INTO_DENIED: &str = "Can not paste into this instance";
+
+/**
+    Checks that the given action is allowed for an instance, using its metadata.
+*/
+fn ensure_action(
+    dom: &Dom,
+    id: Ref,
+    action: impl Fn(&InstanceMetadataActions) -> bool,
+    message: &str,
+) -> DomResult<()> {
+    if dom.get_instance(id).is_none() {
+        return Err(DomError::not_found());
+    }
+    let allowed = dom
+        .get_metadata(id)
+        .and_then(|meta| meta.actions.as_ref())
+        .is_some_and(action);
+    if allowed {
+        Ok(())
+    } else {
+        Err(DomError::new(DomErrorKind::InvalidOperation, message))
     }
 }
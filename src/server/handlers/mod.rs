@@ -1,66 +1,292 @@
-use anyhow::{bail, Context, Result};
+use anyhow::Result;
+use tokio_util::sync::CancellationToken;
 
-use super::{dom::Dom, rpc::RpcMessage};
+use super::{
+    config::Config,
+    dom::Dom,
+    provider::InstanceProviderKind,
+    rpc::{RpcError, RpcErrorCode, RpcErrorData, RpcMessage},
+};
 
+mod cancel;
 mod dom;
 mod instance;
+mod packages;
+mod server;
 mod util;
 
-pub async fn handle_rpc_message(msg: RpcMessage, dom: &mut Dom) -> Result<()> {
-    let method = msg.get_method().trim().to_ascii_lowercase();
-    let ctx = || format!("failed to deserialize {}", method.as_str());
-
-    // Handle any incoming requests that match a handler method
-    if matches!(msg, RpcMessage::Request(_)) {
-        let response = match method.as_str() {
-            "dom/root" => {
-                let req = dom::RootRequest {};
-                req.respond_to(msg, dom).await?
-            }
-            "dom/get" => {
-                let req = msg.get_data::<dom::GetRequest>();
-                req.with_context(ctx)?.respond_to(msg, dom).await?
-            }
-            "dom/children" => {
-                let req = msg.get_data::<dom::ChildrenRequest>();
-                req.with_context(ctx)?.respond_to(msg, dom).await?
-            }
-            "dom/ancestors" => {
-                let req = msg.get_data::<dom::AncestorsRequest>();
-                req.with_context(ctx)?.respond_to(msg, dom).await?
-            }
-            "dom/findbypath" => {
-                let req = msg.get_data::<dom::FindByPathRequest>();
-                req.with_context(ctx)?.respond_to(msg, dom).await?
-            }
-            "dom/findbyquery" => {
-                let req = msg.get_data::<dom::FindByQueryRequest>();
-                req.with_context(ctx)?.respond_to(msg, dom).await?
-            }
-            "instance/insert" => {
-                let req = msg.get_data::<instance::InsertRequest>();
-                req.with_context(ctx)?.respond_to(msg, dom).await?
-            }
-            "instance/rename" => {
-                let req = msg.get_data::<instance::RenameRequest>();
-                req.with_context(ctx)?.respond_to(msg, dom).await?
-            }
-            "instance/delete" => {
-                let req = msg.get_data::<instance::DeleteRequest>();
-                req.with_context(ctx)?.respond_to(msg, dom).await?
-            }
-            "instance/move" => {
-                let req = msg.get_data::<instance::MoveRequest>();
-                req.with_context(ctx)?.respond_to(msg, dom).await?
-            }
-            _ => bail!("unknown request method '{method}'"),
-        };
-
-        let mut stdout = tokio::io::stdout();
-        response.write_to(&mut stdout).await?;
+pub use cancel::InFlightRequests;
+
+/**
+    All of the methods that clients may send requests for.
+
+    Sent to clients as part of the server capabilities when initializing.
+*/
+const METHODS: &[&str] = &[
+    "$/cancelRequest",
+    "server/initialize",
+    "dom/root",
+    "dom/roots",
+    "dom/get",
+    "dom/children",
+    "dom/properties",
+    "dom/ancestors",
+    "dom/findByPath",
+    "dom/findByQuery",
+    "dom/findByInstancePath",
+    "dom/getInstancePath",
+    "dom/requires",
+    "dom/requiredBy",
+    "dom/requireCycles",
+    "instance/insert",
+    "instance/rename",
+    "instance/setProperty",
+    "instance/delete",
+    "instance/move",
+    "instance/copy",
+    "instance/paste",
+    "instance/duplicate",
+    "instance/batch",
+    "instance/undo",
+    "instance/redo",
+    "packages/list",
+];
+
+fn invalid_params(msg: &RpcMessage, err: RpcError) -> RpcMessage {
+    let method = msg.get_method();
+    msg.respond().with_error(RpcErrorData::new(
+        RpcErrorCode::InvalidParams,
+        format!("failed to deserialize {method}: {err}"),
+    ))
+}
+
+fn get_method(msg: &RpcMessage) -> String {
+    msg.get_method().trim().to_ascii_lowercase()
+}
+
+/**
+    Checks if the given message is a request that only reads from the dom,
+    meaning it can be handled concurrently with other read-only requests.
+*/
+pub fn is_read_only_request(msg: &RpcMessage) -> bool {
+    let method = get_method(msg);
+    msg.is_request() && (method.starts_with("dom/") || method.starts_with("packages/"))
+}
+
+/**
+    Checks if the given message is a request to cancel another request.
+
+    These should be handled immediately using [`handle_cancel_request`],
+    and never wait for any other requests to finish.
+*/
+pub fn is_cancel_request(msg: &RpcMessage) -> bool {
+    msg.is_request() && get_method(msg) == "$/cancelrequest"
+}
+
+/**
+    Checks if the given message is a request to initialize the connection.
+
+    These should be handled immediately using [`handle_initialize_request`].
+*/
+pub fn is_initialize_request(msg: &RpcMessage) -> bool {
+    msg.is_request() && get_method(msg) == "server/initialize"
+}
+
+/**
+    Handles a request to initialize the connection, returning the response to send back, if any.
+*/
+pub fn handle_initialize_request(
+    msg: RpcMessage,
+    config: &Config,
+    provider_kind: InstanceProviderKind,
+) -> Result<Option<RpcMessage>> {
+    let response = match msg.get_data::<Option<server::InitializeRequest>>() {
+        Ok(req) => req
+            .unwrap_or_default()
+            .respond_to(&msg, config, provider_kind)?,
+        Err(e) => invalid_params(&msg, e),
+    };
+    Ok(if msg.is_notification() {
+        None
+    } else {
+        Some(response)
+    })
+}
+
+/**
+    Handles a request to cancel another request, returning the response to send back, if any.
+*/
+pub fn handle_cancel_request(msg: RpcMessage, requests: &InFlightRequests) -> Option<RpcMessage> {
+    let response = match msg.get_data::<cancel::CancelRequest>() {
+        Ok(req) => req.respond_to(&msg, requests),
+        Err(e) => invalid_params(&msg, e),
+    };
+    if msg.is_notification() {
+        None
+    } else {
+        Some(response)
+    }
+}
+
+/**
+    Handles a single incoming message that only reads from the
+    dom, returning the response to send back, if any.
+
+    Notifications are handled the same way as requests, but never get a response.
+*/
+pub async fn handle_read_request(
+    msg: RpcMessage,
+    dom: &Dom,
+    cancel: &CancellationToken,
+) -> Result<Option<RpcMessage>> {
+    if !msg.is_request() {
+        return Ok(None);
     }
 
+    let method = get_method(&msg);
+    let is_notification = msg.is_notification();
+    let response = match method.as_str() {
+        "dom/root" => {
+            let req = dom::RootRequest {};
+            req.respond_to(msg, dom).await?
+        }
+        "dom/roots" => {
+            let req = dom::RootsRequest {};
+            req.respond_to(msg, dom).await?
+        }
+        "dom/get" => match msg.get_data::<dom::GetRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "dom/children" => match msg.get_data::<dom::ChildrenRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "dom/properties" => match msg.get_data::<dom::PropertiesRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "dom/ancestors" => match msg.get_data::<dom::AncestorsRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "dom/findbypath" => match msg.get_data::<dom::FindByPathRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "dom/findbyquery" => match msg.get_data::<dom::FindByQueryRequest>() {
+            Ok(req) => req.respond_to(msg, dom, cancel).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "dom/findbyinstancepath" => match msg.get_data::<dom::FindByInstancePathRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "dom/getinstancepath" => match msg.get_data::<dom::GetInstancePathRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "dom/requires" => match msg.get_data::<dom::RequiresRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "dom/requiredby" => match msg.get_data::<dom::RequiredByRequest>() {
+            Ok(req) => req.respond_to(msg, dom, cancel).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "dom/requirecycles" => {
+            let req = dom::RequireCyclesRequest {};
+            req.respond_to(msg, dom, cancel).await?
+        }
+        "packages/list" => {
+            let req = packages::ListRequest {};
+            req.respond_to(msg, dom).await?
+        }
+        _ => msg.respond().with_error(RpcErrorData::new(
+            RpcErrorCode::UnknownMethod,
+            format!("unknown request method '{method}'"),
+        )),
+    };
+
+    Ok(if is_notification {
+        None
+    } else {
+        Some(response)
+    })
+}
+
+/**
+    Handles a single incoming message that may modify the
+    dom, returning the response to send back, if any.
+
+    Notifications are handled the same way as requests, but never get a response.
+*/
+pub async fn handle_write_request(
+    msg: RpcMessage,
+    dom: &mut Dom,
+    cancel: &CancellationToken,
+) -> Result<Option<RpcMessage>> {
     // FUTURE: Handle responses for server -> client rpcs?
+    if !msg.is_request() {
+        return Ok(None);
+    }
+
+    let method = get_method(&msg);
+    let is_notification = msg.is_notification();
+    let response = match method.as_str() {
+        "instance/insert" => match msg.get_data::<instance::InsertRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "instance/rename" => match msg.get_data::<instance::RenameRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "instance/setproperty" => match msg.get_data::<instance::SetPropertyRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "instance/delete" => match msg.get_data::<instance::DeleteRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "instance/move" => match msg.get_data::<instance::MoveRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "instance/copy" => match msg.get_data::<instance::CopyRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "instance/paste" => match msg.get_data::<instance::PasteRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "instance/duplicate" => match msg.get_data::<instance::DuplicateRequest>() {
+            Ok(req) => req.respond_to(msg, dom).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "instance/batch" => match msg.get_data::<instance::BatchRequest>() {
+            Ok(req) => req.respond_to(msg, dom, cancel).await?,
+            Err(e) => invalid_params(&msg, e),
+        },
+        "instance/undo" => {
+            let req = instance::UndoRequest {};
+            req.respond_to(msg, dom).await?
+        }
+        "instance/redo" => {
+            let req = instance::RedoRequest {};
+            req.respond_to(msg, dom).await?
+        }
+        _ => msg.respond().with_error(RpcErrorData::new(
+            RpcErrorCode::UnknownMethod,
+            format!("unknown request method '{method}'"),
+        )),
+    };
 
-    Ok(())
+    Ok(if is_notification {
+        None
+    } else {
+        Some(response)
+    })
 }
@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+    dom::{Dom, WallyPackage},
+    rpc::RpcMessage,
+};
+use crate::util::wally::WallyRealm;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResponseWallyProject {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    realm: Option<WallyRealm>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    registry: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    manifest: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lockfile: Option<PathBuf>,
+    packages: Vec<WallyPackage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ListRequest {}
+
+impl ListRequest {
+    /**
+        Responds with the Wally projects next to the project files of all roots,
+        and their packages, which together make up the dependency graph.
+    */
+    pub async fn respond_to(self, msg: RpcMessage, dom: &Dom) -> Result<RpcMessage> {
+        let mut dirs = Vec::new();
+        for root in &dom.config().roots {
+            if root.roblox_file.is_some() {
+                continue;
+            }
+            if let Some(dir) = root.rojo_project_file.parent() {
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+
+        let projects = dirs
+            .into_iter()
+            .filter_map(|dir| dom.wally_projects().get(dir))
+            .map(|project| {
+                let package = project.manifest.as_ref().and_then(|m| m.package.as_ref());
+                ResponseWallyProject {
+                    name: package.map(|p| p.name.clone()),
+                    version: package.map(|p| p.version.clone()),
+                    realm: package.and_then(|p| p.realm),
+                    registry: project.lockfile.as_ref().and_then(|l| l.registry.clone()),
+                    manifest: project.manifest_path(),
+                    lockfile: project.lockfile_path(),
+                    packages: project.packages(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        msg.respond()
+            .with_data(projects)
+            .context("failed to serialize response")
+    }
+}
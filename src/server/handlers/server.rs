@@ -0,0 +1,74 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::icons::IconPack;
+use crate::server::{config::Config, provider::InstanceProviderKind, rpc::RpcMessage};
+
+use super::METHODS;
+
+/**
+    The version of the protocol spoken by this server.
+
+    This must be incremented whenever a method is removed, or whenever
+    the request or response of an existing method changes in a way
+    that is not backwards compatible with older clients.
+*/
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerCapabilities {
+    methods: &'static [&'static str],
+    provider_kind: InstanceProviderKind,
+    mutations: bool,
+    icon_packs: &'static [IconPack],
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InitializeResponse {
+    protocol_version: u32,
+    server_version: &'static str,
+    capabilities: ServerCapabilities,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct InitializeRequest {
+    protocol_version: Option<u32>,
+}
+
+impl InitializeRequest {
+    pub fn respond_to(
+        self,
+        msg: &RpcMessage,
+        config: &Config,
+        provider_kind: InstanceProviderKind,
+    ) -> Result<RpcMessage> {
+        // NOTE: We always respond with our own version and capabilities,
+        // even if the versions differ, and let the client decide if it
+        // is compatible, since it is the one that knows what it needs
+        if let Some(version) = self.protocol_version {
+            if version != PROTOCOL_VERSION {
+                warn!(
+                    "client uses protocol version {version}, \
+                    but server uses protocol version {PROTOCOL_VERSION}"
+                );
+            }
+        }
+
+        msg.respond()
+            .with_data(InitializeResponse {
+                protocol_version: PROTOCOL_VERSION,
+                server_version: env!("CARGO_PKG_VERSION"),
+                capabilities: ServerCapabilities {
+                    methods: METHODS,
+                    provider_kind,
+                    mutations: !config.read_only,
+                    icon_packs: IconPack::all(),
+                },
+            })
+            .context("failed to serialize response")
+    }
+}
@@ -1,7 +1,20 @@
-use rbx_dom_weak::{types::Ref, Instance};
+use std::ops::Not; // Skip serializing 'false' bools
+
+use anyhow::{Context, Result};
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    Instance,
+};
 use serde::Serialize;
+use serde_json::{Map as JsonMap, Value as JsonValue};
 
-use crate::server::dom::{Dom, InstanceMetadata};
+use crate::server::{
+    dom::{
+        enum_item_name, instance_attributes, instance_tags, property_data_type, Dom, DomError,
+        DomErrorKind, DomQueryParams, DomQueryResult, DomRequire, DomResult, InstanceMetadata,
+    },
+    rpc::{RpcErrorCode, RpcErrorData, RpcMessage},
+};
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -12,6 +25,10 @@ pub(super) struct ResponseInstance {
     class_name: String,
     name: String,
     children: Vec<Ref>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "JsonMap::is_empty")]
+    attributes: JsonMap<String, JsonValue>,
     metadata: Option<InstanceMetadata>,
 }
 
@@ -23,6 +40,14 @@ impl ResponseInstance {
             class_name: inst.class.to_owned(),
             name: inst.name.to_owned(),
             children: inst.children().to_vec(),
+            tags: instance_tags(inst).map(ToOwned::to_owned).collect(),
+            // NOTE: Attributes use the same explicit format as properties, and
+            // any that can not be serialized are left out instead of failing
+            attributes: instance_attributes(inst)
+                .into_iter()
+                .flat_map(|attributes| attributes.iter())
+                .filter_map(|(name, value)| Some((name.clone(), serde_json::to_value(value).ok()?)))
+                .collect(),
             metadata: None,
         }
     }
@@ -32,3 +57,173 @@ impl ResponseInstance {
         self
     }
 }
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ResponseQueryResult {
+    #[serde(flatten)]
+    instance: ResponseInstance,
+    score: f64,
+    /// UTF-16 ranges of characters in the instance name that matched the query.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    matches: Vec<(usize, usize)>,
+    /// Cursor that can be passed in a later query to get results after this one.
+    cursor: String,
+}
+
+impl ResponseQueryResult {
+    pub fn from_dom_result(
+        dom: &Dom,
+        params: &DomQueryParams,
+        result: &DomQueryResult,
+    ) -> Option<Self> {
+        let inst = dom.get_instance(result.id)?;
+        Some(Self {
+            instance: ResponseInstance::from_dom_instance(inst).with_dom_metadata(dom),
+            score: result.score,
+            matches: params.matched_ranges(&inst.name),
+            cursor: result.cursor(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ResponseRequire {
+    /// Zero-based line number of the call to `require` in the script.
+    line: usize,
+    /// The argument of the call to `require`, as written in the script.
+    expression: String,
+    /// The required instance, or the requiring script, depending on the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instance: Option<ResponseInstance>,
+}
+
+impl ResponseRequire {
+    pub fn from_dom_require(dom: &Dom, require: &DomRequire, instance: Option<Ref>) -> Self {
+        Self {
+            line: require.line,
+            expression: require.expression.clone(),
+            instance: instance
+                .and_then(|id| dom.get_instance(id))
+                .map(ResponseInstance::from_dom_instance)
+                .map(|inst| inst.with_dom_metadata(dom)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ResponseProperty {
+    name: String,
+    data_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    enum_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    enum_item: Option<String>,
+    value: JsonValue,
+    #[serde(skip_serializing_if = "Not::not")]
+    is_default: bool,
+}
+
+impl ResponseProperty {
+    /**
+        Creates a new response property for a property of an instance with the given class.
+
+        The value is serialized in the same explicit format that Rojo uses
+        for properties in project files, such as `{ "Vector3": [1, 2, 3] }`.
+    */
+    pub fn from_dom_property(class_name: &str, name: &str, value: &Variant) -> Result<Self> {
+        let (data_type, enum_type) = property_data_type(class_name, name, value);
+        let enum_item = match (enum_type.as_deref(), value) {
+            (Some(enum_type), Variant::Enum(item)) => {
+                enum_item_name(enum_type, item.to_u32()).map(ToOwned::to_owned)
+            }
+            _ => None,
+        };
+        Ok(Self {
+            name: name.to_owned(),
+            data_type,
+            enum_type,
+            enum_item,
+            value: serde_json::to_value(value).context("failed to serialize property value")?,
+            is_default: false,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn with_default(mut self, is_default: bool) -> Self {
+        self.is_default = is_default;
+        self
+    }
+}
+
+impl From<DomError> for RpcErrorData {
+    fn from(err: DomError) -> Self {
+        let code = match err.kind() {
+            DomErrorKind::NotFound => RpcErrorCode::NotFound,
+            DomErrorKind::NotFileBacked => RpcErrorCode::NotFileBacked,
+            DomErrorKind::InvalidName => RpcErrorCode::InvalidName,
+            DomErrorKind::InvalidOperation => RpcErrorCode::InvalidOperation,
+            DomErrorKind::InvalidValue => RpcErrorCode::InvalidValue,
+            DomErrorKind::NameCollision => RpcErrorCode::NameCollision,
+            DomErrorKind::PermissionDenied => RpcErrorCode::PermissionDenied,
+            DomErrorKind::Cancelled => RpcErrorCode::Cancelled,
+            DomErrorKind::Io => RpcErrorCode::Internal,
+        };
+        Self {
+            code,
+            message: err.message().to_string(),
+            path: err.path().map(|p| p.to_path_buf()),
+        }
+    }
+}
+
+/**
+    Responds to a request with either the value of a successful
+    result, or an error payload if the result is an error.
+*/
+pub(super) fn respond_with_result<T: Serialize>(
+    msg: &RpcMessage,
+    result: DomResult<T>,
+) -> Result<RpcMessage> {
+    match result {
+        Ok(value) => msg
+            .respond()
+            .with_data(value)
+            .context("failed to serialize response"),
+        Err(e) => Ok(msg.respond().with_error(e)),
+    }
+}
+
+#[tokio::test]
+async fn respond_with_dom_errors() {
+    use crate::server::rpc::RpcProtocol;
+
+    let (mut dom, dir, module_id) = Dom::for_test("handlers-errors");
+    let other_path = dir.join("src/Other.luau");
+    std::fs::write(&other_path, "return {}").unwrap();
+
+    let line = r#"{"jsonrpc":"2.0","id":1,"method":"instance/rename","params":{"name":"Other"}}"#;
+    let input = RpcProtocol::JsonRpc.read_from(&mut line.as_bytes()).await;
+    let msg = input.unwrap().unwrap().messages.remove(0);
+
+    // Renaming onto a file that already exists responds with its error code and path
+    let result = dom.rename_instance(module_id, "Other".to_string()).await;
+    let response = respond_with_result(&msg, result).unwrap();
+    let mut output = Vec::new();
+    RpcProtocol::JsonRpc
+        .write_message_to(&mut output, response)
+        .await
+        .unwrap();
+    let output = serde_json::from_slice::<JsonValue>(&output).unwrap();
+    assert_eq!(output["id"], 1);
+    assert_eq!(output["error"]["code"], -32005);
+    assert_eq!(
+        output["error"]["data"]["path"],
+        other_path.to_str().unwrap()
+    );
+}
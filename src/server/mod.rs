@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use anyhow::{Context, Result};
 use tokio::{
-    sync::{mpsc::unbounded_channel, Mutex as AsyncMutex},
+    sync::{mpsc::unbounded_channel, Mutex as AsyncMutex, RwLock as AsyncRwLock},
     task::JoinSet,
 };
 
@@ -13,8 +13,11 @@ mod notify;
 mod provider;
 mod rpc;
 mod tasks;
+mod transport;
 
 pub use config::*;
+pub use rpc::RpcProtocol;
+pub use transport::ListenAddress;
 
 pub struct Server {
     config: Config,
@@ -27,19 +30,37 @@ impl Server {
 
     pub async fn serve(self) -> Result<()> {
         let (file_event_tx, file_event_rx) = unbounded_channel();
+        let (pending_tx, pending_rx) = unbounded_channel();
 
         let instance_dom = dom::Dom::new(self.config.clone());
-        let instance_dom = Arc::new(AsyncMutex::new(instance_dom));
+        let instance_dom = Arc::new(AsyncRwLock::new(instance_dom));
 
-        let instance_provider = provider::InstanceProvider::new(self.config.clone());
-        let instance_provider = Arc::new(AsyncMutex::new(instance_provider));
+        let instance_provider = (0..self.config.roots.len())
+            .map(|index| provider::InstanceProvider::new(self.config.for_root(index)))
+            .map(AsyncMutex::new)
+            .collect::<tasks::InstanceProviders>();
+        let instance_provider = Arc::new(instance_provider);
 
-        // Spawn all of our tasks: watch files -> provide instances -> serve instances -> emit notifications
+        let clients = Arc::new(transport::Clients::default());
+
+        // Spawn all of our tasks: watch files -> provide instances -> serve instances -> emit notifications -> serve clients
         // These all depend on each other and pass messages upstream, so we spawn them in reverse order
         let mut set = JoinSet::new();
+        set.spawn(tasks::dispatch_requests(
+            Arc::clone(&instance_dom),
+            pending_rx,
+        ));
+        set.spawn(tasks::serve_clients(
+            self.config.clone(),
+            Arc::clone(&instance_dom),
+            Arc::clone(&instance_provider),
+            Arc::clone(&clients),
+            pending_tx,
+        ));
         set.spawn(tasks::emit_notifications(
             self.config.clone(),
             Arc::clone(&instance_dom),
+            Arc::clone(&clients),
         ));
         set.spawn(tasks::serve_instances(
             self.config.clone(),
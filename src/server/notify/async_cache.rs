@@ -15,11 +15,13 @@ use tokio::fs;
     A simple file cache for async file watching.
 
     Performs cleanup and canonicalization of file paths and stores current contents of files.
+
+    Files may be binary, in which case their contents are not available as a string.
 */
 #[derive(Debug, Clone)]
 pub struct AsyncFileCache {
     dir: PathBuf,
-    files: HashMap<PathBuf, String>,
+    files: HashMap<PathBuf, Vec<u8>>,
 }
 
 impl AsyncFileCache {
@@ -28,7 +30,9 @@ impl AsyncFileCache {
     }
 
     pub fn get_file(&self, path: &Path) -> Option<&str> {
-        self.files.get(path).map(|s| s.as_str())
+        self.files
+            .get(path)
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
     }
 
     pub async fn read_file_at(&mut self, path: &Path) -> Result<Option<AsyncFileEvent>> {
@@ -39,13 +43,13 @@ impl AsyncFileCache {
         };
 
         let prev = self.files.get(&path);
-        let this = match fs::read_to_string(&path).await {
+        let this = match fs::read(&path).await {
             Err(e) if e.kind() == io::ErrorKind::NotFound => None,
             Err(e) => bail!("{e}"),
             Ok(v) => Some(v),
         };
 
-        let event = AsyncFileEvent::new(prev.map(|s| s.as_str()), this.as_deref());
+        let event = AsyncFileEvent::new(prev.map(|s| s.as_slice()), this.as_deref());
 
         match this {
             Some(v) => self.files.insert(path, v),
@@ -101,7 +105,7 @@ impl AsyncFileEvent {
         matches!(self, Self::Removed)
     }
 
-    fn new(prev: Option<&str>, this: Option<&str>) -> Option<Self> {
+    fn new(prev: Option<&[u8]>, this: Option<&[u8]>) -> Option<Self> {
         match (prev, this) {
             (None, None) => None,
             (None, Some(_)) => Some(Self::Created),
@@ -27,12 +27,12 @@ fn is_matching_path(path: &Path, relevant_paths: &[PathBuf]) -> bool {
     })
 }
 
-fn matching_paths(event: &DebouncedEvent, relevant_paths: &[PathBuf]) -> Vec<PathBuf> {
+fn matching_paths(event: &DebouncedEvent, filter: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
     event
         .paths
         .iter()
         .filter_map(|p| {
-            if is_matching_path(p, relevant_paths) {
+            if filter(p) {
                 Some(p.to_path_buf())
             } else {
                 None
@@ -47,6 +47,8 @@ fn matching_paths(event: &DebouncedEvent, relevant_paths: &[PathBuf]) -> Vec<Pat
     Starts watching for file changes when constructed using `AsyncFileWatcher::new`
     and a list of file paths to watch, and stops watching for file changes when dropped.
 
+    May also watch all files and folders in a directory using `AsyncFileWatcher::new_filtered`.
+
     Does not perform any cleanup or canonicalization of file paths.
 */
 pub struct AsyncFileWatcher {
@@ -58,6 +60,19 @@ pub struct AsyncFileWatcher {
 
 impl AsyncFileWatcher {
     pub fn new(relevant_paths: Vec<PathBuf>) -> Result<Self> {
+        Self::new_filtered(Path::new("."), move |path| {
+            is_matching_path(path, &relevant_paths)
+        })
+    }
+
+    /**
+        Creates a new watcher for all files and folders inside of the given
+        directory, recursively, emitting changed paths that pass the filter.
+    */
+    pub fn new_filtered(
+        dir: &Path,
+        filter: impl Fn(&Path) -> bool + Send + 'static,
+    ) -> Result<Self> {
         let (tx, rx) = unbounded_channel();
 
         let mut debouncer = new_debouncer(
@@ -67,7 +82,7 @@ impl AsyncFileWatcher {
                 Err(errors) => errors.iter().for_each(|e| error!("{e:?}")),
                 Ok(events) => {
                     for event in events {
-                        for path in matching_paths(&event, &relevant_paths) {
+                        for path in matching_paths(&event, &filter) {
                             tx.send(path).unwrap()
                         }
                     }
@@ -75,9 +90,7 @@ impl AsyncFileWatcher {
             },
         )?;
 
-        debouncer
-            .watcher()
-            .watch(Path::new("."), RecursiveMode::Recursive)?;
+        debouncer.watcher().watch(dir, RecursiveMode::Recursive)?;
 
         Ok(Self {
             _debouncer: debouncer,
@@ -88,4 +101,11 @@ impl AsyncFileWatcher {
     pub async fn recv(&mut self) -> Option<PathBuf> {
         self.receiver.recv().await
     }
+
+    /**
+        Receives a path that has already changed, without waiting for one.
+    */
+    pub fn try_recv(&mut self) -> Option<PathBuf> {
+        self.receiver.try_recv().ok()
+    }
 }
@@ -2,7 +2,7 @@ use anyhow::Result;
 use tokio::sync::mpsc::UnboundedSender;
 use tracing::trace;
 
-use super::{super::config::Config, InstanceNode};
+use super::{super::config::Config, InstanceNode, InstanceUpdate};
 
 /**
     An instance provider that uses a `sourcemap.json` file to emit diffs.
@@ -10,11 +10,11 @@ use super::{super::config::Config, InstanceNode};
 #[derive(Debug)]
 pub struct FileSourcemapProvider {
     _config: Config,
-    sender: UnboundedSender<Option<InstanceNode>>,
+    sender: UnboundedSender<InstanceUpdate>,
 }
 
 impl FileSourcemapProvider {
-    pub fn new(config: Config, sender: UnboundedSender<Option<InstanceNode>>) -> Self {
+    pub fn new(config: Config, sender: UnboundedSender<InstanceUpdate>) -> Self {
         Self {
             _config: config,
             sender,
@@ -24,7 +24,7 @@ impl FileSourcemapProvider {
     pub async fn start(&mut self, smap: Option<&InstanceNode>) -> Result<()> {
         trace!("starting file provider");
 
-        self.sender.send(smap.cloned()).ok();
+        self.sender.send(InstanceUpdate::Root(smap.cloned())).ok();
 
         Ok(())
     }
@@ -32,7 +32,7 @@ impl FileSourcemapProvider {
     pub async fn update(&mut self, smap: Option<&InstanceNode>) -> Result<()> {
         trace!("updating file provider");
 
-        self.sender.send(smap.cloned()).ok();
+        self.sender.send(InstanceUpdate::Root(smap.cloned())).ok();
 
         Ok(())
     }
@@ -4,32 +4,47 @@ use tracing::error;
 
 mod file_sourcemap;
 mod none;
+mod properties;
+mod roblox_file;
 mod rojo;
 mod rojo_client;
+mod rojo_project;
+mod rojo_resolver;
+mod rojo_session;
 mod rojo_sourcemap;
 mod rojo_stub;
 mod variant;
 
+pub use properties::PropertyFiles;
+pub use roblox_file::ModelFiles;
 pub use rojo::*;
 pub use variant::*;
 
 use super::config::Config;
-use super::dom::InstanceNode;
+use super::dom::{
+    InstanceChange, InstanceChanges, InstanceNode, InstancePatch, InstanceProperties,
+    InstanceUpdate,
+};
 
 /**
     A fault-tolerant instance provider & watcher.
 
-    Prioritizes instance watching in the following order:
+    Standalone place and model files are always read directly, other
+    roots prioritize instance watching in the following order:
 
-    1. Using `rojo sourcemap --watch`, if a project is available and valid
-    2. Using a `sourcemap.json` file, if available and valid
-    3. Using empty data, which should display as blank in an explorer
+    1. Using a running `rojo serve` session, if one is found for the project
+    2. Using `rojo sourcemap --watch`, if a project is available and valid
+    3. Resolving the project natively, if `rojo` is not installed or too old
+    4. Using a `sourcemap.json` file, if available and valid
+    5. Using empty data, which should display as blank in an explorer
 */
 #[derive(Debug)]
 pub struct InstanceProvider {
     config: Config,
-    instance_tx: UnboundedSender<Option<InstanceNode>>,
-    instance_rx: Option<UnboundedReceiver<Option<InstanceNode>>>,
+    instance_tx: UnboundedSender<InstanceUpdate>,
+    instance_rx: Option<UnboundedReceiver<InstanceUpdate>>,
+    fallback_tx: UnboundedSender<InstanceProviderKind>,
+    fallback_rx: Option<UnboundedReceiver<InstanceProviderKind>>,
     last_sourcemap: Option<InstanceNode>,
     last_project: Option<RojoProjectFile>,
     provider: Option<InstanceProviderVariant>,
@@ -38,38 +53,73 @@ pub struct InstanceProvider {
 impl InstanceProvider {
     pub fn new(config: Config) -> Self {
         let (instance_tx, instance_rx) = unbounded_channel();
+        let (fallback_tx, fallback_rx) = unbounded_channel();
         Self {
             config,
             instance_tx,
             instance_rx: Some(instance_rx),
+            fallback_tx,
+            fallback_rx: Some(fallback_rx),
             last_sourcemap: None,
             last_project: None,
             provider: None,
         }
     }
 
-    pub fn take_instance_receiver(&mut self) -> Option<UnboundedReceiver<Option<InstanceNode>>> {
+    pub fn kind(&self) -> InstanceProviderKind {
+        self.provider.as_ref().map(|p| p.kind()).unwrap_or_default()
+    }
+
+    pub fn take_instance_receiver(&mut self) -> Option<UnboundedReceiver<InstanceUpdate>> {
         self.instance_rx.take()
     }
 
+    /**
+        Takes out the receiver for kinds of providers that stopped working after they
+        were started, which should be passed to [`InstanceProvider::fallback`].
+    */
+    pub fn take_fallback_receiver(&mut self) -> Option<UnboundedReceiver<InstanceProviderKind>> {
+        self.fallback_rx.take()
+    }
+
+    /**
+        Replaces the current provider with the provider it falls back to,
+        if the current provider is still of the given kind.
+    */
+    pub async fn fallback(&mut self, kind: InstanceProviderKind) -> Result<()> {
+        if self.kind() != kind {
+            return Ok(());
+        }
+        match kind.fallback() {
+            Some(fallback_kind) => self.update_inner(fallback_kind).await,
+            None => Ok(()),
+        }
+    }
+
     async fn update_inner(&mut self, desired_kind: InstanceProviderKind) -> Result<()> {
         let smap = self.last_sourcemap.as_ref();
         let proj = self.last_project.as_ref();
         if !matches!(self.provider.as_ref().map(|p| p.kind()), Some(k) if k == desired_kind) {
-            // Create, start, and store a new provider, stop the old one if one existed
-            let mut this = InstanceProviderVariant::from_kind(
-                desired_kind,
-                self.config.clone(),
-                self.instance_tx.clone(),
-            );
-            match this.start(smap, proj).await {
-                Err(e) => {
-                    error!("failed to start provider - {e}");
-                }
-                Ok(_) => {
-                    if let Some(mut last) = self.provider.replace(this) {
-                        if let Err(e) = last.stop().await {
-                            error!("failed to stop provider - {e}");
+            // Create, start, and store a new provider, stop the old one if one existed,
+            // falling back to other providers if the desired one fails to start
+            let mut kind = Some(desired_kind);
+            while let Some(current_kind) = kind.take() {
+                let mut this = InstanceProviderVariant::from_kind(
+                    current_kind,
+                    self.config.clone(),
+                    self.instance_tx.clone(),
+                    self.fallback_tx.clone(),
+                );
+                match this.start(smap, proj).await {
+                    Err(e) => {
+                        error!("failed to start provider - {e:?}");
+                        kind = current_kind.fallback();
+                    }
+                    Ok(_) => {
+                        if let Some(mut last) = self.provider.replace(this) {
+                            if let Err(e) = last.stop().await {
+                                error!("failed to stop provider - {e}");
+                            }
                         }
                     }
                 }
@@ -100,7 +150,7 @@ impl InstanceProvider {
         // We should not update the current provider if it is rojo,
         // we let that take precendence since it is more efficient
         let provider_kind = self.provider.as_ref().map(|p| p.kind());
-        if !matches!(provider_kind, Some(InstanceProviderKind::RojoSourcemap)) {
+        if !provider_kind.is_some_and(InstanceProviderKind::is_rojo) {
             if is_some {
                 self.update_inner(InstanceProviderKind::FileSourcemap)
                     .await?;
@@ -112,6 +162,14 @@ impl InstanceProvider {
         Ok(())
     }
 
+    pub async fn update_roblox_file(&mut self, exists: bool) -> Result<()> {
+        if exists {
+            self.update_inner(InstanceProviderKind::RobloxFile).await
+        } else {
+            self.update_inner(InstanceProviderKind::None).await
+        }
+    }
+
     pub async fn update_rojo(&mut self, contents: Option<&str>) -> Result<()> {
         let proj = contents
             .and_then(|c| if c.is_empty() { None } else { Some(c) })
@@ -123,12 +181,10 @@ impl InstanceProvider {
                 }
             });
 
-        if let Some(proj) = &proj {
-            if let Some(_session) = proj.find_serve_session().await {
-                // FUTURE: Use a "rojo session provider" if we manage to find
-                // info!("found rojo serve session - {session:#?}");
-            }
-        }
+        let has_session = match &proj {
+            Some(proj) => proj.find_serve_session().await.is_some(),
+            None => false,
+        };
 
         // Replace stored project manifest, and check if it changed.
         // If the rojo project did not change substantially or in
@@ -143,7 +199,10 @@ impl InstanceProvider {
         // Stop / despawn any spawned process
         self.update_inner(InstanceProviderKind::None).await?;
 
-        if is_some {
+        if is_some && has_session {
+            // We have a project that is being served, use the serve session
+            self.update_inner(InstanceProviderKind::RojoSession).await
+        } else if is_some {
             // We have a project, try to spawn a new process
             self.update_inner(InstanceProviderKind::RojoSourcemap).await
         } else {
@@ -2,7 +2,7 @@ use anyhow::Result;
 use tokio::sync::mpsc::UnboundedSender;
 use tracing::trace;
 
-use super::{super::config::Config, InstanceNode};
+use super::{super::config::Config, InstanceUpdate};
 
 /**
     An instance provider that emits `null` once at startup.
@@ -12,11 +12,11 @@ use super::{super::config::Config, InstanceNode};
 #[derive(Debug)]
 pub struct NoneProvider {
     _config: Config,
-    sender: UnboundedSender<Option<InstanceNode>>,
+    sender: UnboundedSender<InstanceUpdate>,
 }
 
 impl NoneProvider {
-    pub fn new(config: Config, sender: UnboundedSender<Option<InstanceNode>>) -> Self {
+    pub fn new(config: Config, sender: UnboundedSender<InstanceUpdate>) -> Self {
         Self {
             _config: config,
             sender,
@@ -26,7 +26,7 @@ impl NoneProvider {
     pub async fn start(&mut self) -> Result<()> {
         trace!("starting none provider");
 
-        self.sender.send(None).ok();
+        self.sender.send(InstanceUpdate::Root(None)).ok();
 
         Ok(())
     }
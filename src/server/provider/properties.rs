@@ -0,0 +1,242 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use rbx_dom_weak::types::Variant;
+use rustc_hash::FxHashMap as HashMap;
+use serde::Deserialize;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use tracing::debug;
+
+#[cfg(test)]
+use crate::util::path::TestDir;
+use crate::util::{path::make_absolute_and_clean, rojo::file_name_str};
+
+use super::{
+    super::dom::{resolve_attributes, resolve_property_value},
+    InstanceNode, InstanceUpdate,
+};
+
+/**
+    A file that properties can be loaded from.
+*/
+#[derive(Debug, Clone)]
+enum PropertyFile {
+    Json(JsonPropertyFile),
+    Text(String),
+}
+
+/**
+    A meta file (`.meta.json`) or model file (`.model.json`) in the format that Rojo uses.
+
+    Model files may also contain children, which get matched with the children
+    of the instance node for the model file using their names and class names.
+*/
+#[derive(Debug, Clone, Default, Deserialize)]
+struct JsonPropertyFile {
+    #[serde(default, rename = "name", alias = "Name")]
+    name: Option<String>,
+    #[serde(default, rename = "className", alias = "ClassName")]
+    class_name: Option<String>,
+    #[serde(default, rename = "properties", alias = "Properties")]
+    properties: JsonMap<String, JsonValue>,
+    #[serde(default, rename = "attributes", alias = "Attributes")]
+    attributes: JsonMap<String, JsonValue>,
+    #[serde(default, rename = "children", alias = "Children")]
+    children: Vec<JsonPropertyFile>,
+}
+
+/**
+    Loads properties for instances in instance updates from other providers, using the files that
+    those instances were created from - meta files (`.meta.json` and `init.meta.json`), model files
+    (`.model.json`), and plain text files (`.txt`) for `StringValue` instances.
+
+    Files are only read again if they were modified since they were last read.
+*/
+#[derive(Debug, Default)]
+pub struct PropertyFiles {
+    cache: HashMap<PathBuf, (SystemTime, Option<PropertyFile>)>,
+}
+
+impl PropertyFiles {
+    pub fn load(&mut self, update: &mut InstanceUpdate) {
+        match update {
+            InstanceUpdate::Root(Some(node)) => self.load_node(node),
+            InstanceUpdate::Root(None) => {}
+            InstanceUpdate::Patch(patch) => {
+                for node in &mut patch.nodes {
+                    self.load_node(node);
+                }
+            }
+            InstanceUpdate::Changes(changes) => {
+                for (_, node) in &mut changes.added {
+                    self.load_node(node);
+                }
+            }
+        }
+    }
+
+    fn load_node(&mut self, node: &mut InstanceNode) {
+        let paths = node
+            .file_paths
+            .iter()
+            .filter(|path| is_property_file_path(path))
+            .map(make_absolute_and_clean)
+            .collect::<Vec<_>>();
+        for path in paths {
+            match self.read(path) {
+                Some(PropertyFile::Json(file)) => apply_json_properties(node, file),
+                Some(PropertyFile::Text(text)) if node.class_name == "StringValue" => {
+                    let value = Variant::String(text.clone());
+                    node.properties.insert(String::from("Value"), value);
+                }
+                _ => {}
+            }
+        }
+        for child in &mut node.children {
+            self.load_node(child);
+        }
+    }
+
+    fn read(&mut self, path: PathBuf) -> Option<&PropertyFile> {
+        let modified = path.metadata().and_then(|m| m.modified()).ok()?;
+        let is_cached = self
+            .cache
+            .get(&path)
+            .is_some_and(|(cached, _)| *cached == modified);
+        if !is_cached {
+            let file = match read_property_file(&path) {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    debug!("failed to read property file at {} - {e}", path.display());
+                    None
+                }
+            };
+            self.cache.insert(path.clone(), (modified, file));
+        }
+        self.cache.get(&path).and_then(|(_, file)| file.as_ref())
+    }
+}
+
+fn is_property_file_path(path: &Path) -> bool {
+    file_name_str(path).is_some_and(|f| {
+        f.ends_with(".meta.json") || f.ends_with(".model.json") || f.ends_with(".txt")
+    })
+}
+
+fn read_property_file(path: &Path) -> anyhow::Result<PropertyFile> {
+    let contents = fs::read_to_string(path)?;
+    if file_name_str(path).is_some_and(|f| f.ends_with(".txt")) {
+        Ok(PropertyFile::Text(contents))
+    } else {
+        Ok(PropertyFile::Json(serde_json::from_str(&contents)?))
+    }
+}
+
+fn apply_json_properties(node: &mut InstanceNode, file: &JsonPropertyFile) {
+    for (name, value) in &file.properties {
+        match resolve_property_value(&node.class_name, name, value.clone()) {
+            Ok(value) => {
+                node.properties.insert(name.clone(), value);
+            }
+            Err(e) => debug!("failed to resolve property - {e:#}"),
+        }
+    }
+
+    if !file.attributes.is_empty() {
+        match resolve_attributes(file.attributes.clone()) {
+            Ok(attributes) => {
+                let value = Variant::Attributes(attributes);
+                node.properties.insert(String::from("Attributes"), value);
+            }
+            Err(e) => debug!("failed to resolve attributes - {e:#}"),
+        }
+    }
+
+    // NOTE: Rojo uses the class name for children without names, and
+    // the same name may be used more than once, so we match in order
+    let mut matched = vec![false; node.children.len()];
+    for child_file in &file.children {
+        let class_name = child_file.class_name.as_deref();
+        let name = child_file.name.as_deref().or(class_name);
+        let found = (0..node.children.len()).find(|index| {
+            let child = &node.children[*index];
+            !matched[*index]
+                && Some(child.name.as_str()) == name
+                && Some(child.class_name.as_str()) == class_name
+        });
+        if let Some(index) = found {
+            matched[index] = true;
+            apply_json_properties(&mut node.children[index], child_file);
+        }
+    }
+}
+
+#[test]
+fn load_properties_from_files() {
+    use rbx_dom_weak::types::Vector3;
+
+    use super::InstanceProperties;
+
+    let dir = TestDir::new("properties");
+    let write = |name: &str, contents: &str| {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    };
+    let node = |class_name: &str, name: &str, file_paths, children| InstanceNode {
+        class_name: class_name.to_string(),
+        name: name.to_string(),
+        file_paths,
+        children,
+        provider_id: None,
+        properties: InstanceProperties::default(),
+    };
+
+    let meta = write(
+        "Script.meta.json",
+        r#"{ "properties": { "Disabled": true }, "attributes": { "Speed": 5 } }"#,
+    );
+    let model = write(
+        "Model.model.json",
+        r#"{
+            "className": "Model",
+            "children": [{ "className": "Part", "properties": { "Size": [1, 2, 3] } }]
+        }"#,
+    );
+    let text = write("Greeting.txt", "Hello");
+
+    let mut update = InstanceUpdate::Root(Some(node(
+        "Folder",
+        "Root",
+        Vec::new(),
+        vec![
+            node("Script", "Script", vec![meta], Vec::new()),
+            node(
+                "Model",
+                "Model",
+                vec![model],
+                vec![node("Part", "Part", Vec::new(), Vec::new())],
+            ),
+            node("StringValue", "Greeting", vec![text], Vec::new()),
+        ],
+    )));
+    PropertyFiles::default().load(&mut update);
+    let InstanceUpdate::Root(Some(root)) = update else {
+        panic!("expected root")
+    };
+
+    let script = &root.children[0].properties;
+    assert_eq!(script.get("Disabled"), Some(&Variant::Bool(true)));
+    assert!(matches!(script.get("Attributes"), Some(Variant::Attributes(a)) if a.len() == 1));
+    assert_eq!(
+        root.children[1].children[0].properties.get("Size"),
+        Some(&Variant::Vector3(Vector3::new(1.0, 2.0, 3.0)))
+    );
+    assert_eq!(
+        root.children[2].properties.get("Value"),
+        Some(&Variant::String(String::from("Hello")))
+    );
+}
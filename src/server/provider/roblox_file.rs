@@ -0,0 +1,274 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{bail, Context, Result};
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    Instance, WeakDom,
+};
+use rustc_hash::FxHashMap as HashMap;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, trace};
+
+#[cfg(test)]
+use crate::util::path::TestDir;
+use crate::util::{
+    path::{make_absolute_and_clean, make_relative_to_cwd},
+    rojo::{file_name_str, is_roblox_model_path, is_roblox_place_path},
+};
+
+use super::{super::config::Config, InstanceNode, InstanceProperties, InstanceUpdate};
+
+/**
+    An instance provider that reads a standalone Roblox place or model
+    file, either binary or XML, and reads it again whenever it changes.
+*/
+#[derive(Debug)]
+pub struct RobloxFileProvider {
+    config: Config,
+    sender: UnboundedSender<InstanceUpdate>,
+}
+
+impl RobloxFileProvider {
+    pub fn new(config: Config, sender: UnboundedSender<InstanceUpdate>) -> Self {
+        Self { config, sender }
+    }
+
+    async fn read(&self) -> Result<()> {
+        let path = self
+            .config
+            .roblox_file
+            .clone()
+            .context("missing roblox file")?;
+        let root = tokio::task::spawn_blocking(move || read_roblox_file(&path))
+            .await
+            .context("failed to join roblox file task")??;
+        self.sender.send(InstanceUpdate::Root(Some(root))).ok();
+        Ok(())
+    }
+
+    pub async fn start(&mut self) -> Result<()> {
+        trace!("starting roblox file provider");
+        self.read().await
+    }
+
+    pub async fn update(&mut self) -> Result<()> {
+        trace!("updating roblox file provider");
+        self.read().await
+    }
+
+    pub async fn stop(&mut self) -> Result<()> {
+        trace!("stopping roblox file provider");
+        Ok(())
+    }
+}
+
+/**
+    Reads a place or model file into a root instance node, named after the file.
+
+    Places become a `DataModel` containing all services, models become their single
+    top-level instance, or a `Folder` if the model has more than one of those.
+*/
+pub fn read_roblox_file(path: &Path) -> Result<InstanceNode> {
+    let dom = read_dom(path)?;
+    let name = file_name_str(path)
+        .and_then(|f| f.rsplit_once('.'))
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_default();
+
+    let mut root = if is_roblox_place_path(path) {
+        InstanceNode {
+            class_name: String::from("DataModel"),
+            name,
+            file_paths: Vec::new(),
+            children: dom_children(&dom, dom.root_ref()),
+            provider_id: None,
+            properties: InstanceProperties::default(),
+        }
+    } else {
+        let mut children = dom_children(&dom, dom.root_ref());
+        if children.len() == 1 {
+            let mut node = children.pop().unwrap();
+            node.name = name;
+            node
+        } else {
+            InstanceNode {
+                class_name: String::from("Folder"),
+                name,
+                file_paths: Vec::new(),
+                children,
+                provider_id: None,
+                properties: InstanceProperties::default(),
+            }
+        }
+    };
+
+    root.file_paths.push(make_relative_to_cwd(path));
+    root.sort_recursive();
+    Ok(root)
+}
+
+fn read_dom(path: &Path) -> Result<WeakDom> {
+    let file_name = file_name_str(path).unwrap_or_default();
+    let reader = BufReader::new(File::open(path)?);
+    if file_name.ends_with(".rbxl") || file_name.ends_with(".rbxm") {
+        rbx_binary::from_reader(reader).context("failed to decode binary file")
+    } else if file_name.ends_with(".rbxlx") || file_name.ends_with(".rbxmx") {
+        rbx_xml::from_reader_default(reader).context("failed to decode xml file")
+    } else {
+        bail!("unknown roblox file type '{}'", path.display())
+    }
+}
+
+fn dom_children(dom: &WeakDom, id: Ref) -> Vec<InstanceNode> {
+    dom.get_by_ref(id)
+        .map(|inst| inst.children())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|child_id| dom.get_by_ref(*child_id))
+        .map(|child| InstanceNode {
+            class_name: child.class.clone(),
+            name: child.name.clone(),
+            file_paths: Vec::new(),
+            children: dom_children(dom, child.referent()),
+            provider_id: None,
+            properties: dom_properties(child),
+        })
+        .collect()
+}
+
+fn dom_properties(inst: &Instance) -> InstanceProperties {
+    // NOTE: Refs point to instances in the file and not in our own dom, so they are useless
+    let properties = inst
+        .properties
+        .iter()
+        .filter(|(_, value)| !matches!(value, Variant::Ref(_)))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    InstanceProperties(properties)
+}
+
+/**
+    Expands instances created from model files (`.rbxm` and `.rbxmx`) in instance
+    updates from other providers into the real instances inside of those files.
+
+    Files are only read again if they were modified since they were last read.
+*/
+#[derive(Debug, Default)]
+pub struct ModelFiles {
+    cache: HashMap<PathBuf, (SystemTime, Option<InstanceNode>)>,
+}
+
+impl ModelFiles {
+    pub fn expand(&mut self, update: &mut InstanceUpdate) {
+        match update {
+            InstanceUpdate::Root(Some(node)) => self.expand_node(node),
+            InstanceUpdate::Root(None) => {}
+            InstanceUpdate::Patch(patch) => {
+                for node in &mut patch.nodes {
+                    self.expand_node(node);
+                }
+            }
+            InstanceUpdate::Changes(changes) => {
+                for (_, node) in &mut changes.added {
+                    self.expand_node(node);
+                }
+            }
+        }
+    }
+
+    fn expand_node(&mut self, node: &mut InstanceNode) {
+        let model_path = node
+            .file_paths
+            .iter()
+            .find(|path| is_roblox_model_path(path))
+            .map(make_absolute_and_clean);
+        match model_path.and_then(|path| self.read(path)) {
+            // NOTE: Model files may contain scripts, but those are not file-backed,
+            // any children given by the provider are replaced with the real ones
+            Some(model) => {
+                model.class_name.clone_into(&mut node.class_name);
+                node.children.clone_from(&model.children);
+                node.properties.clone_from(&model.properties);
+            }
+            None => {
+                for child in &mut node.children {
+                    self.expand_node(child);
+                }
+            }
+        }
+    }
+
+    fn read(&mut self, path: PathBuf) -> Option<&InstanceNode> {
+        let modified = path.metadata().and_then(|m| m.modified()).ok()?;
+        let is_cached = self
+            .cache
+            .get(&path)
+            .is_some_and(|(cached, _)| *cached == modified);
+        if !is_cached {
+            let model = match read_roblox_file(&path) {
+                Ok(model) => Some(model),
+                Err(e) => {
+                    debug!("failed to read model file at {} - {e:?}", path.display());
+                    None
+                }
+            };
+            self.cache.insert(path.clone(), (modified, model));
+        }
+        self.cache.get(&path).and_then(|(_, model)| model.as_ref())
+    }
+}
+
+#[test]
+fn read_place_and_model_files() {
+    use rbx_dom_weak::InstanceBuilder;
+
+    let dir = TestDir::new("roblox-file");
+
+    let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let workspace = dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace"));
+    let model = dom.insert(workspace, InstanceBuilder::new("Model").with_name("Tree"));
+    dom.insert(model, InstanceBuilder::new("Part").with_name("Trunk"));
+    dom.insert(dom.root_ref(), InstanceBuilder::new("ReplicatedStorage"));
+
+    let place_path = dir.join("Game.rbxl");
+    let place_file = File::create(&place_path).unwrap();
+    rbx_binary::to_writer(place_file, &dom, dom.root().children()).unwrap();
+    let place = read_roblox_file(&place_path).unwrap();
+    assert_eq!(place.class_name, "DataModel");
+    assert_eq!(place.name, "Game");
+    assert_eq!(place.children[0].name, "ReplicatedStorage");
+    assert_eq!(place.children[1].children[0].children[0].name, "Trunk");
+
+    let model_path = dir.join("Tree.rbxmx");
+    let model_file = File::create(&model_path).unwrap();
+    rbx_xml::to_writer_default(model_file, &dom, &[model]).unwrap();
+
+    // Model files in updates from other providers get their real class name and children
+    let mut update = InstanceUpdate::Root(Some(InstanceNode {
+        class_name: String::from("Folder"),
+        name: String::from("Root"),
+        file_paths: Vec::new(),
+        children: vec![InstanceNode {
+            class_name: String::from("Instance"),
+            name: String::from("Tree"),
+            file_paths: vec![model_path.clone()],
+            children: Vec::new(),
+            provider_id: None,
+            properties: InstanceProperties::default(),
+        }],
+        provider_id: None,
+        properties: InstanceProperties::default(),
+    }));
+    ModelFiles::default().expand(&mut update);
+    let InstanceUpdate::Root(Some(root)) = update else {
+        panic!("expected root")
+    };
+    assert_eq!(root.children[0].class_name, "Model");
+    assert_eq!(root.children[0].name, "Tree");
+    assert_eq!(root.children[0].children[0].name, "Trunk");
+}
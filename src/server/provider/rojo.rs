@@ -1,7 +1,7 @@
 use std::{net::SocketAddr, path::PathBuf};
 
 use anyhow::Result;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use serde_json::{Map as JsonMap, Value as JsonValue};
 
 use super::rojo_client::{RojoSessionClient, RojoSessionInfo};
@@ -12,10 +12,12 @@ use super::rojo_client::{RojoSessionClient, RojoSessionInfo};
 #[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
 #[serde(default, rename_all = "camelCase")]
 pub struct RojoProjectFileNode {
-    #[serde(rename = "$path")]
+    #[serde(rename = "$path", deserialize_with = "deserialize_project_path")]
     pub path: Option<PathBuf>,
     #[serde(rename = "$className")]
     pub class_name: Option<String>,
+    #[serde(rename = "$ignoreUnknownInstances")]
+    pub ignore_unknown_instances: Option<bool>,
     #[serde(flatten)]
     pub other_fields: JsonMap<String, JsonValue>,
 }
@@ -30,6 +32,7 @@ pub struct RojoProjectFile {
     pub tree: RojoProjectFileNode,
     pub serve_address: Option<String>,
     pub serve_port: Option<u16>,
+    pub glob_ignore_paths: Vec<String>,
 }
 
 impl RojoProjectFile {
@@ -65,28 +68,42 @@ impl RojoProjectFile {
         let info = client.get_info().await.ok()?;
 
         // Now that we have the info struct we need to verify that it actually
-        // came from this project file, which we can do by comparing paths
-        // For this we can read the root instance which should have this data
-        let root_id = info.root_instance_id;
+        // came from this project file, and that the session can be read from
+        let root_id = info.root_instance_id.clone();
         let root_res = client.read(&root_id).await.ok()?;
 
-        if root_res.session_id == info.session_id {
-            let root = root_res
-                .instances
-                .iter()
-                .find_map(|(instance_id, instance)| {
-                    if instance_id == &root_id {
-                        Some(instance)
-                    } else {
-                        None
-                    }
-                })?;
-            if let Some(_meta) = &root.metadata {
-                // TODO: Compare paths when paths are included in the metadata
-                // https://github.com/rojo-rbx/rojo/pull/337
-            }
+        // NOTE: Rojo does not include paths in instance metadata yet, so we compare
+        // project names instead, which should be unique enough in practice
+        // https://github.com/rojo-rbx/rojo/pull/337
+        let is_same_session = root_res.session_id == info.session_id;
+        let is_same_project = info.project_name == self.name;
+        let has_root = root_res.instances.contains_key(&root_id);
+        if is_same_session && is_same_project && has_root {
+            Some(info)
+        } else {
+            None
         }
+    }
+}
 
-        None
+/**
+    Deserializes a `$path` field, which may either be a plain path
+    or an object such as `{ "optional": "path" }` for optional paths.
+*/
+fn deserialize_project_path<'de, D>(deserializer: D) -> Result<Option<PathBuf>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ProjectPath {
+        Required(PathBuf),
+        Optional { optional: PathBuf },
     }
+
+    Ok(match Option::<ProjectPath>::deserialize(deserializer)? {
+        Some(ProjectPath::Required(path)) => Some(path),
+        Some(ProjectPath::Optional { optional }) => Some(optional),
+        None => None,
+    })
 }
@@ -8,6 +8,7 @@ pub struct RojoSessionClient {
     client: reqwest::Client,
     url_info: String,
     url_read: String,
+    url_subscribe: String,
 }
 
 impl RojoSessionClient {
@@ -34,6 +35,7 @@ impl RojoSessionClient {
             client,
             url_info: format!("http://{addr}/api/rojo"),
             url_read: format!("http://{addr}/api/read/"),
+            url_subscribe: format!("http://{addr}/api/subscribe/"),
         })
     }
 
@@ -66,14 +68,14 @@ impl RojoSessionClient {
     }
 
     /**
-        Read the instance with the given id.
+        Read the instance with the given id, and all of its descendants.
 
         May fail if the serve session is no longer available.
     */
     pub async fn read(&self, id: impl AsRef<str>) -> Result<RojoSessionReadResponse> {
         let read_res = self
             .client
-            .get(&format!("{}{}", self.url_read, id.as_ref()))
+            .get(format!("{}{}", self.url_read, id.as_ref()))
             .send()
             .await
             .context("failed to make request")?;
@@ -92,13 +94,42 @@ impl RojoSessionClient {
             .context("failed to get response bytes")?;
         serde_json::from_slice(&read_bytes).context("failed to deserialize response")
     }
+
+    /**
+        Wait for changes to instances after the given message cursor.
+
+        This is a long-polling request, which will only return once there are new changes.
+
+        May fail if the serve session is no longer available.
+    */
+    pub async fn subscribe(&self, cursor: u32) -> Result<RojoSessionSubscribeResponse> {
+        let subscribe_res = self
+            .client
+            .get(format!("{}{cursor}", self.url_subscribe))
+            .send()
+            .await
+            .context("failed to make request")?;
+
+        if !subscribe_res.status().is_success() {
+            bail!(
+                "{} {}",
+                subscribe_res.status().as_u16(),
+                subscribe_res.status().canonical_reason().unwrap_or("N/A")
+            )
+        }
+
+        let subscribe_bytes = subscribe_res
+            .bytes()
+            .await
+            .context("failed to get response bytes")?;
+        serde_json::from_slice(&subscribe_bytes).context("failed to deserialize response")
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RojoSessionInfo {
     pub session_id: String,
-    #[allow(dead_code)]
     pub project_name: String,
     pub root_instance_id: String,
 }
@@ -107,15 +138,40 @@ pub struct RojoSessionInfo {
 #[serde(rename_all = "camelCase")]
 pub struct RojoSessionReadResponse {
     pub session_id: String,
+    pub message_cursor: u32,
     pub instances: HashMap<String, RojoSessionInstance>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct RojoSessionSubscribeResponse {
+    pub session_id: String,
+    pub message_cursor: u32,
+    pub messages: Vec<RojoSessionMessage>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RojoSessionMessage {
+    pub removed: Vec<String>,
+    pub added: HashMap<String, RojoSessionInstance>,
+    pub updated: Vec<RojoSessionInstanceUpdate>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct RojoSessionInstance {
-    pub metadata: Option<RojoSessionInstanceMetadata>,
+    pub parent: Option<String>,
+    pub name: String,
+    pub class_name: String,
+    #[serde(default)]
+    pub children: Vec<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct RojoSessionInstanceMetadata {}
+pub struct RojoSessionInstanceUpdate {
+    pub id: String,
+    pub changed_name: Option<String>,
+    pub changed_class_name: Option<String>,
+}
@@ -0,0 +1,160 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle};
+use tracing::{debug, error, trace};
+
+use crate::server::notify::AsyncFileWatcher;
+use crate::util::path::make_absolute_and_clean;
+
+use super::{
+    super::config::Config,
+    rojo_resolver::{resolve_project, ProjectChange, ResolvedProject},
+    InstanceUpdate, RojoProjectFile,
+};
+
+/**
+    An instance provider that resolves a rojo project file natively,
+    without needing the `rojo` binary, and patches it on file changes.
+*/
+#[derive(Debug)]
+pub struct RojoProjectProvider {
+    config: Config,
+    sender: UnboundedSender<InstanceUpdate>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl RojoProjectProvider {
+    pub fn new(config: Config, sender: UnboundedSender<InstanceUpdate>) -> Self {
+        Self {
+            config,
+            sender,
+            task: None,
+        }
+    }
+
+    pub async fn start(&mut self, project_file: Option<&RojoProjectFile>) -> Result<()> {
+        trace!("starting rojo project provider");
+
+        if project_file.is_none() {
+            self.sender.send(InstanceUpdate::Root(None)).ok();
+            return Ok(());
+        }
+
+        let project_file_path = make_absolute_and_clean(&self.config.rojo_project_file);
+        let path = project_file_path.clone();
+        let (root, resolved) = tokio::task::spawn_blocking(move || resolve_project(&path))
+            .await
+            .context("failed to join project resolver task")??;
+        self.sender.send(InstanceUpdate::Root(Some(root))).ok();
+
+        // NOTE: Changes to the project file itself restart the provider, but
+        // nested project files and any paths in the project are watched here
+        let project_dir = project_file_path
+            .parent()
+            .map(Path::to_path_buf)
+            .context("project file has no parent directory")?;
+        let ignored_path = project_file_path.clone();
+        let watcher = AsyncFileWatcher::new_filtered(&project_dir, move |path| {
+            make_absolute_and_clean(path) != ignored_path
+        })?;
+
+        let sender = self.sender.clone();
+        self.task.replace(tokio::spawn(watch_project(
+            project_file_path,
+            resolved,
+            watcher,
+            sender,
+        )));
+
+        Ok(())
+    }
+
+    pub async fn update(&mut self, _project_file: Option<&RojoProjectFile>) -> Result<()> {
+        trace!("updating rojo project provider");
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) -> Result<()> {
+        trace!("stopping rojo project provider");
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RojoProjectProvider {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+async fn watch_project(
+    project_file_path: PathBuf,
+    mut resolved: ResolvedProject,
+    mut watcher: AsyncFileWatcher,
+    sender: UnboundedSender<InstanceUpdate>,
+) {
+    while let Some(path) = watcher.recv().await {
+        // Gather any other changes that happened at the same time,
+        // so that we can resolve the project once if we need to
+        let mut paths = vec![path];
+        while let Some(path) = watcher.try_recv() {
+            paths.push(path);
+        }
+        paths.sort();
+        paths.dedup();
+
+        let project_file_path = project_file_path.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let updates = resolve_updates(&project_file_path, &mut resolved, &paths);
+            (resolved, updates)
+        })
+        .await;
+
+        match result {
+            Err(e) => {
+                error!("failed to join project resolver task - {e}");
+                break;
+            }
+            Ok((new_resolved, updates)) => {
+                resolved = new_resolved;
+                match updates {
+                    Err(e) => error!("failed to resolve rojo project - {e:?}"),
+                    Ok(updates) => {
+                        for update in updates {
+                            sender.send(update).ok();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn resolve_updates(
+    project_file_path: &Path,
+    resolved: &mut ResolvedProject,
+    paths: &[PathBuf],
+) -> Result<Vec<InstanceUpdate>> {
+    let mut updates = Vec::new();
+    for path in paths {
+        match resolved.resolve_change(path) {
+            ProjectChange::None => {}
+            ProjectChange::Patch(patch) => {
+                debug!("patching rojo project after change to {}", path.display());
+                updates.push(InstanceUpdate::Patch(patch));
+            }
+            ProjectChange::Project => {
+                debug!("resolving rojo project after change to {}", path.display());
+                let (root, new_resolved) = resolve_project(project_file_path)?;
+                *resolved = new_resolved;
+                return Ok(vec![InstanceUpdate::Root(Some(root))]);
+            }
+        }
+    }
+    Ok(updates)
+}
@@ -0,0 +1,708 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use path_clean::PathClean;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use serde::Deserialize;
+use tracing::debug;
+
+#[cfg(test)]
+use crate::util::path::TestDir;
+use crate::util::{
+    path::{make_absolute_and_clean, make_relative_to_cwd},
+    rojo::{file_name_str, is_roblox_model_path, parse_name_and_class_name},
+};
+
+use super::{
+    InstanceNode, InstancePatch, InstanceProperties, RojoProjectFile, RojoProjectFileNode,
+};
+
+const DEFAULT_PROJECT_FILE_NAME: &str = "default.project.json";
+
+/**
+    A change to a resolved Rojo project, caused by a file or folder being changed.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProjectChange {
+    /**
+        The change does not affect the instance tree.
+    */
+    None,
+    /**
+        The change affects a small part of the instance tree, described by the patch.
+    */
+    Patch(InstancePatch),
+    /**
+        The change could not be turned into a patch, and the whole project must be resolved again.
+    */
+    Project,
+}
+
+/**
+    Information about a Rojo project file that has been resolved into an instance tree,
+    which can be used to turn file changes into patches for that same instance tree.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedProject {
+    dirs: HashMap<PathBuf, ResolvedDir>,
+    files: HashSet<PathBuf>,
+    project_files: HashSet<PathBuf>,
+    watched_paths: Vec<PathBuf>,
+}
+
+/**
+    A folder whose contents were resolved into children of an instance in the tree.
+*/
+#[derive(Debug, Clone)]
+struct ResolvedDir {
+    instance_path: Vec<String>,
+    reserved_names: Vec<String>,
+    ctx: Arc<ProjectContext>,
+}
+
+impl ResolvedProject {
+    /**
+        Checks if the given path is inside of any project file, file,
+        or folder that the instance tree was resolved from.
+    */
+    pub fn is_watched_path(&self, path: &Path) -> bool {
+        self.watched_paths.iter().any(|w| path.starts_with(w))
+    }
+
+    /**
+        Resolves how a change to the file or folder at the given path affects the instance tree.
+
+        Only the instances with the same name as the changed file or folder, in the same
+        parent, are resolved again - any other part of the instance tree is left untouched.
+    */
+    pub fn resolve_change(&mut self, path: &Path) -> ProjectChange {
+        let path = make_absolute_and_clean(path);
+        if !self.is_watched_path(&path) {
+            return ProjectChange::None;
+        }
+        if self.project_files.contains(&path) || is_project_file(&path) {
+            return ProjectChange::Project;
+        }
+
+        // NOTE: Modifying the contents of a file that we already know about can
+        // only change the instance tree if it is a file that we read contents of,
+        // which includes model files that get expanded into their real instances,
+        // and text files that get loaded as the value of a StringValue instance
+        if self.files.contains(&path)
+            && path.is_file()
+            && !is_json_file(&path)
+            && !is_text_file(&path)
+            && !is_roblox_model_path(&path)
+        {
+            return ProjectChange::None;
+        }
+
+        // Init files and meta files belong to other instances, find out which one changed
+        let file_name = match file_name_str(&path) {
+            Some(f) => f,
+            None => return ProjectChange::Project,
+        };
+        let (entry_path, name) = if file_name.starts_with("init.") {
+            let dir = match path.parent() {
+                Some(d) => d.to_path_buf(),
+                None => return ProjectChange::Project,
+            };
+            match file_name_str(&dir) {
+                Some(name) => (dir.clone(), name.to_string()),
+                None => return ProjectChange::Project,
+            }
+        } else if let Some(name) = file_name.strip_suffix(".meta.json") {
+            (path.clone(), name.to_string())
+        } else {
+            (path.clone(), entry_name(&path))
+        };
+
+        let dir_path = match entry_path.parent() {
+            Some(d) => d.to_path_buf(),
+            None => return ProjectChange::Project,
+        };
+        let dir = match self.dirs.get(&dir_path) {
+            Some(d) => d.clone(),
+            None => return ProjectChange::Project,
+        };
+        if dir.reserved_names.contains(&name) {
+            return ProjectChange::Project;
+        }
+
+        let mut instance_path = dir.instance_path.clone();
+        instance_path.push(name.clone());
+
+        // Forget about anything we previously resolved for this instance name
+        let is_stale = |p: &Path| match p.strip_prefix(&dir_path) {
+            Err(_) => false,
+            Ok(rest) => {
+                let mut components = rest.components();
+                let first = components.next().and_then(|c| c.as_os_str().to_str());
+                if components.next().is_some() {
+                    first == Some(name.as_str())
+                } else {
+                    entry_name(p) == name
+                }
+            }
+        };
+        self.dirs.retain(|p, _| !is_stale(p));
+        self.files.retain(|p| !is_stale(p));
+
+        // Resolve all of the entries with the same instance name in the parent folder
+        let mut entries = fs::read_dir(&dir_path)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .filter(|p| entry_name(p) == name && !is_meta_file(p))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        entries.sort();
+
+        let mut resolver = Resolver::default();
+        let mut nodes = entries
+            .iter()
+            .filter_map(|p| resolver.resolve_path(&dir.ctx, p, name.clone(), &instance_path))
+            .collect::<Vec<_>>();
+        for node in nodes.iter_mut() {
+            node.sort_recursive();
+        }
+        nodes.sort();
+
+        if !resolver.project.project_files.is_empty() {
+            // NOTE: Nested projects may have paths anywhere on disk,
+            // so we can not know which ones to watch without a full resolve
+            return ProjectChange::Project;
+        }
+        self.dirs.extend(resolver.project.dirs);
+        self.files.extend(resolver.project.files);
+
+        ProjectChange::Patch(InstancePatch {
+            parent: dir.instance_path,
+            name,
+            nodes,
+        })
+    }
+}
+
+/**
+    Resolves the full instance tree for a Rojo project file, without using the `rojo` binary.
+
+    This follows the sync rules used by Rojo for project nodes, init files,
+    meta files, model files, and nested projects, as described here:
+
+    https://rojo.space/docs/v7/sync-details/
+
+    Note that properties are not resolved, only instance names & class names.
+    This also means that `$ignoreUnknownInstances` has no effect on the resolved
+    tree, since it only changes how Rojo syncs instances that are not on disk.
+*/
+pub fn resolve_project(project_file_path: &Path) -> Result<(InstanceNode, ResolvedProject)> {
+    let mut resolver = Resolver::default();
+    let mut root = resolver
+        .resolve_project_file(&make_absolute_and_clean(project_file_path), None, &[])?
+        .with_context(|| {
+            format!(
+                "failed to resolve class name for root of project file at '{}'",
+                project_file_path.display()
+            )
+        })?;
+    root.sort_recursive();
+    Ok((root, resolver.project))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MetaFile {
+    #[serde(default, rename = "className")]
+    class_name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ModelFile {
+    #[serde(default, rename = "ClassName", alias = "className")]
+    class_name: Option<String>,
+    #[serde(default, rename = "Name", alias = "name")]
+    name: Option<String>,
+    #[serde(default, rename = "Children", alias = "children")]
+    children: Vec<ModelFile>,
+}
+
+impl ModelFile {
+    fn into_instance_node(self, name: String) -> Option<InstanceNode> {
+        let class_name = self.class_name?;
+        let children = self
+            .children
+            .into_iter()
+            .filter_map(|child| {
+                // NOTE: Rojo uses the class name for children without names
+                let name = child.name.clone().or_else(|| child.class_name.clone())?;
+                child.into_instance_node(name)
+            })
+            .collect();
+        Some(InstanceNode {
+            class_name,
+            name,
+            file_paths: Vec::new(),
+            children,
+            provider_id: None,
+            properties: InstanceProperties::default(),
+        })
+    }
+}
+
+/**
+    Context for the project file that is currently being resolved,
+    since nested projects may have their own `globIgnorePaths`.
+*/
+#[derive(Debug)]
+struct ProjectContext {
+    dir: PathBuf,
+    ignore_globs: GlobSet,
+}
+
+impl ProjectContext {
+    fn new(project_file_path: &Path, project: &RojoProjectFile) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &project.glob_ignore_paths {
+            let glob = Glob::new(pattern)
+                .with_context(|| format!("invalid glob ignore path '{pattern}'"))?;
+            builder.add(glob);
+        }
+        Ok(Self {
+            dir: project_file_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            ignore_globs: builder.build()?,
+        })
+    }
+
+    fn is_ignored(&self, path: &Path) -> bool {
+        match path.strip_prefix(&self.dir) {
+            Ok(relative) => self.ignore_globs.is_match(relative),
+            Err(_) => false,
+        }
+    }
+}
+
+/**
+    Resolves instances from project files, files, and folders, and keeps track
+    of everything that was resolved in a [`ResolvedProject`] while doing so.
+
+    Instance paths passed to the resolver are the names of an instance and its
+    ancestors, starting at (but not including) the root, same as in [`InstancePatch`].
+*/
+#[derive(Debug, Default)]
+struct Resolver {
+    project: ResolvedProject,
+}
+
+impl Resolver {
+    fn resolve_project_file(
+        &mut self,
+        project_file_path: &Path,
+        name: Option<String>,
+        instance_path: &[String],
+    ) -> Result<Option<InstanceNode>> {
+        self.project
+            .project_files
+            .insert(project_file_path.to_path_buf());
+        self.project
+            .watched_paths
+            .push(project_file_path.to_path_buf());
+
+        let contents = fs::read_to_string(project_file_path).with_context(|| {
+            format!(
+                "failed to read project file at '{}'",
+                project_file_path.display()
+            )
+        })?;
+        let project = RojoProjectFile::from_json(contents).with_context(|| {
+            format!(
+                "failed to parse project file at '{}'",
+                project_file_path.display()
+            )
+        })?;
+
+        let ctx = Arc::new(ProjectContext::new(project_file_path, &project)?);
+        let name = name.unwrap_or_else(|| project.name.clone());
+        let mut root =
+            self.resolve_project_node(&ctx, name, &project.tree, None, instance_path.to_vec());
+        if let Some(root) = root.as_mut() {
+            root.file_paths
+                .insert(0, make_relative_to_cwd(project_file_path));
+        }
+
+        Ok(root)
+    }
+
+    fn resolve_project_node(
+        &mut self,
+        ctx: &Arc<ProjectContext>,
+        name: String,
+        node: &RojoProjectFileNode,
+        parent_class_name: Option<&str>,
+        instance_path: Vec<String>,
+    ) -> Option<InstanceNode> {
+        let node_path = node.path.as_deref().map(|path| ctx.dir.join(path).clean());
+        let from_path = node_path.as_deref().and_then(|path| {
+            self.project.watched_paths.push(path.to_path_buf());
+            self.resolve_path(ctx, path, name.clone(), &instance_path)
+        });
+
+        let class_name = node
+            .class_name
+            .clone()
+            .or_else(|| {
+                // HACK: We assume that all children of a DataModel are services which have
+                // class names that are the same as their names, same as in the project stub
+                if parent_class_name == Some("DataModel") {
+                    Some(name.clone())
+                } else {
+                    None
+                }
+            })
+            .or_else(|| from_path.as_ref().map(|n| n.class_name.clone()));
+        let class_name = match class_name {
+            Some(c) => c,
+            None => {
+                debug!("failed to resolve class name for project node '{name}'");
+                return None;
+            }
+        };
+
+        let (file_paths, mut children) = match from_path {
+            Some(n) => (n.file_paths, n.children),
+            None => (Vec::new(), Vec::new()),
+        };
+
+        let mut child_names = Vec::new();
+        for (key, value) in &node.other_fields {
+            if key.starts_with('$') {
+                continue;
+            }
+            let child_node = match serde_json::from_value::<RojoProjectFileNode>(value.clone()) {
+                Ok(n) => n,
+                Err(_) => continue,
+            };
+            // NOTE: Project nodes take precedence over
+            // instances on disk that have the same name
+            children.retain(|c| &c.name != key);
+            child_names.push(key.clone());
+            let mut child_path = instance_path.clone();
+            child_path.push(key.clone());
+            if let Some(child) = self.resolve_project_node(
+                ctx,
+                key.clone(),
+                &child_node,
+                Some(&class_name),
+                child_path,
+            ) {
+                children.push(child);
+            }
+        }
+
+        // Changes to files with the same names as project nodes can't be patched,
+        // since they would replace the project nodes, so we reserve those names
+        if let Some(dir) = node_path.and_then(|path| self.project.dirs.get_mut(&path)) {
+            dir.reserved_names = child_names;
+        }
+
+        Some(InstanceNode {
+            class_name,
+            name,
+            file_paths,
+            children,
+            provider_id: None,
+            properties: InstanceProperties::default(),
+        })
+    }
+
+    fn resolve_path(
+        &mut self,
+        ctx: &Arc<ProjectContext>,
+        path: &Path,
+        name: String,
+        instance_path: &[String],
+    ) -> Option<InstanceNode> {
+        if ctx.is_ignored(path) {
+            return None;
+        }
+        let meta = match fs::metadata(path) {
+            Ok(m) => m,
+            Err(e) => {
+                debug!("failed to read metadata for '{}' - {e}", path.display());
+                return None;
+            }
+        };
+        if meta.is_dir() {
+            self.resolve_dir(ctx, path, name, instance_path)
+        } else if meta.is_file() {
+            self.resolve_file(path, name, instance_path)
+        } else {
+            None
+        }
+    }
+
+    fn resolve_dir(
+        &mut self,
+        ctx: &Arc<ProjectContext>,
+        dir: &Path,
+        name: String,
+        instance_path: &[String],
+    ) -> Option<InstanceNode> {
+        // A folder containing a default project file is a nested project
+        let nested_project_path = dir.join(DEFAULT_PROJECT_FILE_NAME);
+        if nested_project_path.is_file() {
+            return self.resolve_nested_project(&nested_project_path, name, instance_path);
+        }
+
+        let mut entries = fs::read_dir(dir)
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .collect::<Vec<_>>();
+        entries.sort();
+
+        let init_path = entries.iter().find(|path| {
+            matches!(parse_name_and_class_name(path), Some(("init", _))) && !is_json_file(path)
+        });
+        let init_meta_path = entries
+            .iter()
+            .find(|path| file_name_str(path) == Some("init.meta.json"));
+
+        let init_meta_class_name = init_meta_path
+            .and_then(|path| read_json::<MetaFile>(path))
+            .and_then(|meta| meta.class_name);
+        let class_name = init_meta_class_name
+            .or_else(|| {
+                init_path
+                    .and_then(|path| parse_name_and_class_name(path))
+                    .map(|(_, class_name)| class_name.to_string())
+            })
+            .unwrap_or_else(|| "Folder".to_string());
+
+        let mut file_paths = vec![make_relative_to_cwd(dir)];
+        for path in init_path.iter().chain(init_meta_path.iter()) {
+            self.project.files.insert(path.to_path_buf());
+            file_paths.push(make_relative_to_cwd(path));
+        }
+
+        let children = entries
+            .iter()
+            .filter(|path| Some(*path) != init_path && Some(*path) != init_meta_path)
+            .filter(|path| !is_meta_file(path))
+            .filter_map(|path| {
+                let name = entry_name(path);
+                let mut child_path = instance_path.to_vec();
+                child_path.push(name.clone());
+                self.resolve_path(ctx, path, name, &child_path)
+            })
+            .collect();
+
+        self.project.dirs.insert(
+            dir.to_path_buf(),
+            ResolvedDir {
+                instance_path: instance_path.to_vec(),
+                reserved_names: Vec::new(),
+                ctx: Arc::clone(ctx),
+            },
+        );
+
+        Some(InstanceNode {
+            class_name,
+            name,
+            file_paths,
+            children,
+            provider_id: None,
+            properties: InstanceProperties::default(),
+        })
+    }
+
+    fn resolve_file(
+        &mut self,
+        path: &Path,
+        name: String,
+        instance_path: &[String],
+    ) -> Option<InstanceNode> {
+        // NOTE: Meta files are not instances, they belong to their sibling
+        if is_meta_file(path) {
+            return None;
+        }
+        if is_project_file(path) {
+            return self.resolve_nested_project(path, name, instance_path);
+        }
+
+        let (file_name, class_name) = parse_name_and_class_name(path)?;
+        let mut node = if is_model_file(path) {
+            let model = read_json::<ModelFile>(path)?;
+            model.into_instance_node(name)?
+        } else {
+            InstanceNode {
+                class_name: class_name.to_string(),
+                name,
+                file_paths: Vec::new(),
+                children: Vec::new(),
+                provider_id: None,
+                properties: InstanceProperties::default(),
+            }
+        };
+
+        self.project.files.insert(path.to_path_buf());
+        node.file_paths.push(make_relative_to_cwd(path));
+        let meta_path = path.with_file_name(format!("{file_name}.meta.json"));
+        if meta_path.is_file() {
+            self.project.files.insert(meta_path.clone());
+            node.file_paths.push(make_relative_to_cwd(meta_path));
+        }
+
+        Some(node)
+    }
+
+    fn resolve_nested_project(
+        &mut self,
+        path: &Path,
+        name: String,
+        instance_path: &[String],
+    ) -> Option<InstanceNode> {
+        match self.resolve_project_file(path, Some(name), instance_path) {
+            Ok(node) => node,
+            Err(e) => {
+                debug!("failed to resolve nested project - {e:?}");
+                None
+            }
+        }
+    }
+}
+
+/**
+    Gets the name of the instance that a file or folder would be resolved into,
+    without checking the filesystem - the path may have been removed already.
+*/
+fn entry_name(path: &Path) -> String {
+    let file_name = file_name_str(path).unwrap_or_default();
+    if let Some(name) = file_name.strip_suffix(".project.json") {
+        return name.to_string();
+    }
+    match parse_name_and_class_name(path) {
+        Some((name, _)) if !path.is_dir() => name.to_string(),
+        _ => file_name.to_string(),
+    }
+}
+
+fn is_json_file(path: &Path) -> bool {
+    file_name_str(path).is_some_and(|f| f.ends_with(".json"))
+}
+
+fn is_text_file(path: &Path) -> bool {
+    file_name_str(path).is_some_and(|f| f.ends_with(".txt"))
+}
+
+fn is_meta_file(path: &Path) -> bool {
+    file_name_str(path).is_some_and(|f| f.ends_with(".meta.json"))
+}
+
+fn is_model_file(path: &Path) -> bool {
+    file_name_str(path).is_some_and(|f| f.ends_with(".model.json"))
+}
+
+fn is_project_file(path: &Path) -> bool {
+    file_name_str(path).is_some_and(|f| f.ends_with(".project.json"))
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Option<T> {
+    let contents = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            debug!("failed to parse json file at '{}' - {e}", path.display());
+            None
+        }
+    }
+}
+
+#[test]
+fn resolve_project_tree() {
+    let dir = TestDir::new("resolver");
+    let write = |path: &str, contents: &str| {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    };
+
+    write(
+        "default.project.json",
+        r#"{
+            "name": "Game",
+            "globIgnorePaths": ["src/Ignored/**"],
+            "tree": {
+                "$className": "DataModel",
+                "ReplicatedStorage": {
+                    "$path": "src",
+                    "Nested": { "$path": "nested" }
+                }
+            }
+        }"#,
+    );
+    write("src/Module/init.luau", "");
+    write(
+        "src/Config/init.meta.json",
+        r#"{ "className": "Configuration" }"#,
+    );
+    write("src/Main.server.luau", "");
+    write("src/Main.meta.json", "{}");
+    write("src/Part.model.json", r#"{ "ClassName": "Part" }"#);
+    write("src/Ignored/Script.luau", "");
+    write(
+        "nested/default.project.json",
+        r#"{ "name": "N", "tree": { "$path": "lib" } }"#,
+    );
+    write("nested/lib/init.client.luau", "");
+
+    let (root, mut resolved) = resolve_project(&dir.join("default.project.json")).unwrap();
+
+    write("src/Added.client.luau", "");
+    let added_change = resolved.resolve_change(&dir.join("src/Added.client.luau"));
+    let content_change = resolved.resolve_change(&dir.join("src/Main.server.luau"));
+    let nested_change = resolved.resolve_change(&dir.join("nested/default.project.json"));
+
+    let storage = &root.children[0];
+    let classes = storage
+        .children
+        .iter()
+        .map(|c| (c.name.as_str(), c.class_name.as_str()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(root.class_name, "DataModel");
+    assert_eq!(storage.class_name, "ReplicatedStorage");
+    assert_eq!(
+        classes,
+        vec![
+            ("Config", "Configuration"),
+            ("Ignored", "Folder"),
+            ("Main", "Script"),
+            ("Module", "ModuleScript"),
+            ("Nested", "LocalScript"),
+            ("Part", "Part"),
+        ]
+    );
+    assert!(storage.children[1].children.is_empty());
+    assert_eq!(storage.children[2].file_paths.len(), 2);
+    assert!(resolved.is_watched_path(&dir.join("nested/lib/init.client.luau")));
+
+    match added_change {
+        ProjectChange::Patch(patch) => {
+            assert_eq!(patch.parent, vec!["ReplicatedStorage".to_string()]);
+            assert_eq!(patch.name, "Added");
+            assert_eq!(patch.nodes.len(), 1);
+            assert_eq!(patch.nodes[0].class_name, "LocalScript");
+        }
+        other => panic!("expected patch, got {other:?}"),
+    }
+    assert_eq!(content_change, ProjectChange::None);
+    assert_eq!(nested_change, ProjectChange::Project);
+}
@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use rustc_hash::FxHashSet as HashSet;
+use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle};
+use tracing::{debug, trace, warn};
+
+use crate::util::path::make_absolute_and_clean;
+
+use super::{
+    super::config::Config,
+    rojo_client::{RojoSessionClient, RojoSessionInstance, RojoSessionMessage},
+    rojo_resolver::resolve_project,
+    InstanceChange, InstanceChanges, InstanceNode, InstanceProperties, InstanceProviderKind,
+    InstanceUpdate, RojoProjectFile,
+};
+
+/**
+    An instance provider that uses a running `rojo serve` session,
+    reading its full instance tree and subscribing to live changes.
+
+    Reconnects if the serve session is replaced by a new session for the same
+    project, and signals that it should be replaced by its fallback provider
+    once the serve session ends, see [`InstanceProviderKind::fallback`].
+*/
+#[derive(Debug)]
+pub struct RojoSessionProvider {
+    config: Config,
+    sender: UnboundedSender<InstanceUpdate>,
+    fallback: UnboundedSender<InstanceProviderKind>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl RojoSessionProvider {
+    pub fn new(
+        config: Config,
+        sender: UnboundedSender<InstanceUpdate>,
+        fallback: UnboundedSender<InstanceProviderKind>,
+    ) -> Self {
+        Self {
+            config,
+            sender,
+            fallback,
+            task: None,
+        }
+    }
+
+    pub async fn start(&mut self, project_file: Option<&RojoProjectFile>) -> Result<()> {
+        trace!("starting rojo session provider");
+
+        let project_file = project_file.context("missing rojo project file")?;
+        let (session, root) = connect_session(&self.config, project_file).await?;
+        self.sender.send(InstanceUpdate::Root(Some(root))).ok();
+
+        self.task.replace(tokio::spawn(watch_session(
+            self.config.clone(),
+            project_file.clone(),
+            session,
+            self.sender.clone(),
+            self.fallback.clone(),
+        )));
+
+        Ok(())
+    }
+
+    pub async fn update(&mut self, _project_file: Option<&RojoProjectFile>) -> Result<()> {
+        trace!("updating rojo session provider");
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) -> Result<()> {
+        trace!("stopping rojo session provider");
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        Ok(())
+    }
+}
+
+/**
+    A connection to a serve session, and the state needed to subscribe to its changes.
+*/
+struct Session {
+    client: RojoSessionClient,
+    tree: SessionTree,
+    session_id: String,
+    cursor: u32,
+}
+
+/**
+    Connects to the serve session for the given project file, and reads its full instance tree.
+*/
+async fn connect_session(
+    config: &Config,
+    project_file: &RojoProjectFile,
+) -> Result<(Session, InstanceNode)> {
+    let info = project_file
+        .find_serve_session()
+        .await
+        .context("no serve session was found for the rojo project file")?;
+
+    let client = RojoSessionClient::connect(project_file.serve_address()).await?;
+    let read = client.read(&info.root_instance_id).await?;
+    let tree = SessionTree {
+        instances: read.instances,
+    };
+    let mut root = tree
+        .node(&info.root_instance_id)
+        .context("serve session is missing its root instance")?;
+    root.sort_recursive();
+
+    // NOTE: Rojo does not include file paths in instance metadata, so we try
+    // to get them from resolving the project natively, same as for sourcemaps
+    let project_file_path = make_absolute_and_clean(&config.rojo_project_file);
+    let resolved = tokio::task::spawn_blocking(move || resolve_project(&project_file_path));
+    if let Ok(Ok((stub, _))) = resolved.await {
+        root.merge_stub(&stub);
+    }
+
+    let session = Session {
+        client,
+        tree,
+        session_id: info.session_id,
+        cursor: read.message_cursor,
+    };
+    Ok((session, root))
+}
+
+async fn watch_session(
+    config: Config,
+    project_file: RojoProjectFile,
+    mut session: Session,
+    sender: UnboundedSender<InstanceUpdate>,
+    fallback: UnboundedSender<InstanceProviderKind>,
+) {
+    loop {
+        match session.client.subscribe(session.cursor).await {
+            Err(e) => {
+                warn!("rojo serve session ended - {e}");
+                break;
+            }
+            Ok(res) if res.session_id != session.session_id => {
+                // NOTE: Restarting `rojo serve` creates a new session, which
+                // may still be serving the same project, so we try to reconnect
+                debug!("rojo serve session was replaced, reconnecting");
+                match connect_session(&config, &project_file).await {
+                    Err(e) => {
+                        warn!("failed to reconnect to rojo serve session - {e:?}");
+                        break;
+                    }
+                    Ok((new_session, root)) => {
+                        session = new_session;
+                        sender.send(InstanceUpdate::Root(Some(root))).ok();
+                    }
+                }
+            }
+            Ok(res) => {
+                session.cursor = res.message_cursor;
+                for message in res.messages {
+                    let changes = session.tree.apply_message(message);
+                    if changes != InstanceChanges::default() {
+                        sender.send(InstanceUpdate::Changes(changes)).ok();
+                    }
+                }
+            }
+        }
+    }
+
+    // The serve session is gone, the instance provider
+    // will replace this provider with its fallback provider
+    fallback.send(InstanceProviderKind::RojoSession).ok();
+}
+
+/**
+    A copy of the instances in a serve session, kept up to date using subscription messages.
+*/
+#[derive(Debug)]
+struct SessionTree {
+    instances: HashMap<String, RojoSessionInstance>,
+}
+
+impl SessionTree {
+    fn node(&self, id: &str) -> Option<InstanceNode> {
+        let inst = self.instances.get(id)?;
+        Some(InstanceNode {
+            class_name: inst.class_name.clone(),
+            name: inst.name.clone(),
+            file_paths: Vec::new(),
+            children: inst
+                .children
+                .iter()
+                .filter_map(|child_id| self.node(child_id))
+                .collect(),
+            provider_id: Some(id.to_string()),
+            properties: InstanceProperties::default(),
+        })
+    }
+
+    fn remove(&mut self, id: &str) -> bool {
+        let inst = match self.instances.remove(id) {
+            Some(inst) => inst,
+            None => return false,
+        };
+        if let Some(parent) = inst.parent.and_then(|p| self.instances.get_mut(&p)) {
+            parent.children.retain(|child_id| child_id != id);
+        }
+        for child_id in inst.children {
+            self.remove(&child_id);
+        }
+        true
+    }
+
+    fn apply_message(&mut self, message: RojoSessionMessage) -> InstanceChanges {
+        let mut changes = InstanceChanges::default();
+
+        for id in message.removed {
+            if self.remove(&id) {
+                changes.removed.push(id);
+            }
+        }
+
+        // NOTE: Added instances may be descendants of other added instances, in
+        // which case they will be included in the node for their added ancestor
+        let added_ids = message.added.keys().cloned().collect::<HashSet<_>>();
+        let mut added_roots = Vec::new();
+        for (id, inst) in message.added {
+            if self.remove(&id) {
+                changes.removed.push(id.clone());
+            }
+            match inst.parent.as_deref() {
+                Some(parent_id) if !added_ids.contains(parent_id) => {
+                    if let Some(parent) = self.instances.get_mut(parent_id) {
+                        if !parent.children.contains(&id) {
+                            parent.children.push(id.clone());
+                        }
+                        added_roots.push((parent_id.to_string(), id.clone()));
+                    }
+                }
+                _ => {}
+            }
+            self.instances.insert(id, inst);
+        }
+        for (parent_id, id) in added_roots {
+            if let Some(mut node) = self.node(&id) {
+                node.sort_recursive();
+                changes.added.push((parent_id, node));
+            }
+        }
+
+        for update in message.updated {
+            let inst = match self.instances.get_mut(&update.id) {
+                Some(inst) => inst,
+                None => continue,
+            };
+            if update.changed_name.is_none() && update.changed_class_name.is_none() {
+                continue;
+            }
+            if let Some(name) = &update.changed_name {
+                name.clone_into(&mut inst.name);
+            }
+            if let Some(class_name) = &update.changed_class_name {
+                class_name.clone_into(&mut inst.class_name);
+            }
+            changes.changed.push(InstanceChange {
+                id: update.id,
+                name: update.changed_name,
+                class_name: update.changed_class_name,
+            });
+        }
+
+        changes
+    }
+}
+
+#[tokio::test]
+async fn session_provider_with_mock_server() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        sync::mpsc::unbounded_channel,
+    };
+
+    // A tiny http server emulating the parts of the Rojo API that we use,
+    // which gets restarted with a new session after the first change
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let restarted = Arc::new(AtomicBool::new(false));
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let restarted = Arc::clone(&restarted);
+            tokio::spawn(async move {
+                let mut buf = vec![0; 4096];
+                let len = stream.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..len]).to_string();
+                let (session_id, cursor, part_name) = match restarted.load(Ordering::SeqCst) {
+                    false => ("session", 1, "Part"),
+                    true => ("restarted", 3, "Restarted"),
+                };
+                let body = match request.split_whitespace().nth(1) {
+                    None => return,
+                    Some("/api/rojo") => Some(json!({
+                        "sessionId": session_id,
+                        "projectName": "Mock",
+                        "rootInstanceId": "root",
+                    })),
+                    Some("/api/read/root") => Some(json!({
+                        "sessionId": session_id,
+                        "messageCursor": cursor,
+                        "instances": {
+                            "root": { "Parent": null, "Name": "Mock", "ClassName": "DataModel", "Children": ["ws"] },
+                            "ws": { "Parent": "root", "Name": "Workspace", "ClassName": "Workspace", "Children": ["part"] },
+                            "part": { "Parent": "ws", "Name": part_name, "ClassName": "Part", "Children": [] },
+                        },
+                    })),
+                    Some("/api/subscribe/1") => Some(json!({
+                        "sessionId": "session",
+                        "messageCursor": 2,
+                        "messages": [{
+                            "removed": ["part"],
+                            "added": {
+                                "model": { "Parent": "ws", "Name": "Model", "ClassName": "Model", "Children": ["child"] },
+                                "child": { "Parent": "model", "Name": "Child", "ClassName": "Part", "Children": [] },
+                            },
+                            "updated": [{ "id": "ws", "changedName": "World", "changedProperties": {} }],
+                        }],
+                    })),
+                    Some("/api/subscribe/2") => {
+                        restarted.store(true, Ordering::SeqCst);
+                        Some(json!({
+                            "sessionId": "restarted",
+                            "messageCursor": 3,
+                            "messages": [],
+                        }))
+                    }
+                    // The restarted session ends once it is subscribed to
+                    Some(_) => None,
+                };
+                let response = match body {
+                    Some(body) => {
+                        let body = body.to_string();
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                            Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                            body.len()
+                        )
+                    }
+                    None => {
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_string()
+                    }
+                };
+                stream.write_all(response.as_bytes()).await.ok();
+            });
+        }
+    });
+
+    let project_file = RojoProjectFile {
+        name: "Mock".to_string(),
+        serve_port: Some(port),
+        ..Default::default()
+    };
+    let (tx, mut rx) = unbounded_channel();
+    let (fallback_tx, mut fallback_rx) = unbounded_channel();
+    let mut provider = RojoSessionProvider::new(Config::default(), tx, fallback_tx);
+    provider.start(Some(&project_file)).await.unwrap();
+
+    let root = match rx.recv().await {
+        Some(InstanceUpdate::Root(Some(root))) => root,
+        other => panic!("expected root, got {other:?}"),
+    };
+    assert_eq!(root.class_name, "DataModel");
+    assert_eq!(root.children[0].provider_id.as_deref(), Some("ws"));
+    assert_eq!(root.children[0].children[0].name, "Part");
+
+    let changes = match rx.recv().await {
+        Some(InstanceUpdate::Changes(changes)) => changes,
+        other => panic!("expected changes, got {other:?}"),
+    };
+    assert_eq!(changes.removed, vec!["part".to_string()]);
+    assert_eq!(changes.added.len(), 1);
+    assert_eq!(changes.added[0].0, "ws");
+    assert_eq!(changes.added[0].1.name, "Model");
+    assert_eq!(changes.added[0].1.children[0].name, "Child");
+    assert_eq!(changes.changed[0].name.as_deref(), Some("World"));
+
+    // A replaced session for the same project is reconnected to, and read again
+    let root = match rx.recv().await {
+        Some(InstanceUpdate::Root(Some(root))) => root,
+        other => panic!("expected root, got {other:?}"),
+    };
+    assert_eq!(root.children[0].children[0].name, "Restarted");
+
+    // Once the session ends, the provider asks to be replaced by its fallback
+    assert_eq!(
+        fallback_rx.recv().await,
+        Some(InstanceProviderKind::RojoSession)
+    );
+
+    provider.stop().await.unwrap();
+}
@@ -16,7 +16,7 @@ use tokio::{
 
 use super::{
     super::config::Config, rojo_stub::generate_project_file_instance_tree, InstanceNode,
-    RojoProjectFile,
+    InstanceUpdate, RojoProjectFile,
 };
 
 const SPAWN_TIMEOUT: Duration = Duration::from_secs(5);
@@ -29,13 +29,13 @@ static REQUIRED_VERSION: Lazy<VersionReq> = Lazy::new(|| VersionReq::parse("7.3.
 #[derive(Debug)]
 pub struct RojoSourcemapProvider {
     config: Config,
-    sender: UnboundedSender<Option<InstanceNode>>,
+    sender: UnboundedSender<InstanceUpdate>,
     version: Option<Version>,
     child: Option<AsyncGroupChild>,
 }
 
 impl RojoSourcemapProvider {
-    pub fn new(config: Config, sender: UnboundedSender<Option<InstanceNode>>) -> Self {
+    pub fn new(config: Config, sender: UnboundedSender<InstanceUpdate>) -> Self {
         Self {
             config,
             sender,
@@ -74,10 +74,10 @@ impl RojoSourcemapProvider {
         // the rojo project file and parsing its 'tree' field, but this may fail
         let tree_stub = if let Some(project_file) = project_file {
             let tree = generate_project_file_instance_tree(project_file).await;
-            self.sender.send(tree.clone()).ok();
+            self.sender.send(InstanceUpdate::Root(tree.clone())).ok();
             tree
         } else {
-            self.sender.send(None).ok();
+            self.sender.send(InstanceUpdate::Root(None)).ok();
             None
         };
 
@@ -150,7 +150,7 @@ fn spawn_rojo_sourcemap(config: &Config) -> Result<AsyncGroupChild> {
 fn handle_rojo_streams(
     stdout: ChildStdout,
     stderr: ChildStderr,
-    sender: UnboundedSender<Option<InstanceNode>>,
+    sender: UnboundedSender<InstanceUpdate>,
     tree_stub: Option<InstanceNode>,
 ) {
     // Note that we don't really need to care about the join handles
@@ -167,7 +167,7 @@ fn handle_rojo_streams(
                     if let Some(stub) = &tree_stub {
                         smap.merge_stub(stub);
                     }
-                    sender.send(Some(smap)).ok();
+                    sender.send(InstanceUpdate::Root(Some(smap))).ok();
                 }
             }
             buffer.clear();
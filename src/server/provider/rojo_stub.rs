@@ -6,7 +6,7 @@ use tokio::fs::{metadata, read_dir, read_to_string};
 
 use crate::util::rojo::parse_name_and_class_name;
 
-use super::{InstanceNode, RojoProjectFile, RojoProjectFileNode};
+use super::{InstanceNode, InstanceProperties, RojoProjectFile, RojoProjectFileNode};
 
 /**
     Max generation depth is limited here since the user will probably not navigate
@@ -77,6 +77,8 @@ async fn generate_project_node_instance(
             name,
             children,
             file_paths,
+            provider_id: None,
+            properties: InstanceProperties::default(),
         })
     } else {
         // Add children from direct project nodes
@@ -113,6 +115,8 @@ async fn generate_project_node_instance(
             name,
             children,
             file_paths,
+            provider_id: None,
+            properties: InstanceProperties::default(),
         })
     }
 }
@@ -209,6 +213,8 @@ async fn instance_nodes_at_path(
                 name: path.file_name().unwrap().to_string_lossy().to_string(),
                 file_paths: vec![path],
                 children: dir_children,
+                provider_id: None,
+                properties: InstanceProperties::default(),
             });
         }
     } else if meta.is_file() {
@@ -219,6 +225,8 @@ async fn instance_nodes_at_path(
                     name: name.to_string(),
                     file_paths: vec![path],
                     children: vec![],
+                    provider_id: None,
+                    properties: InstanceProperties::default(),
                 })
             }
         }
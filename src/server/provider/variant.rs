@@ -1,19 +1,48 @@
 use anyhow::Result;
+use serde::Serialize;
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::server::Config;
 
 use super::{
-    file_sourcemap::FileSourcemapProvider, none::NoneProvider,
-    rojo_sourcemap::RojoSourcemapProvider, InstanceNode, RojoProjectFile,
+    file_sourcemap::FileSourcemapProvider, none::NoneProvider, roblox_file::RobloxFileProvider,
+    rojo_project::RojoProjectProvider, rojo_session::RojoSessionProvider,
+    rojo_sourcemap::RojoSourcemapProvider, InstanceNode, InstanceUpdate, RojoProjectFile,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
 pub enum InstanceProviderKind {
     #[default]
     None,
     FileSourcemap,
     RojoSourcemap,
+    RojoProject,
+    RojoSession,
+    RobloxFile,
+}
+
+impl InstanceProviderKind {
+    /**
+        Returns `true` if this provider uses a rojo project file.
+    */
+    pub fn is_rojo(self) -> bool {
+        matches!(
+            self,
+            Self::RojoSourcemap | Self::RojoProject | Self::RojoSession
+        )
+    }
+
+    /**
+        Returns the provider that should be used if this provider fails to start,
+        or stops working after it was started, if any.
+    */
+    pub fn fallback(self) -> Option<Self> {
+        match self {
+            Self::RojoSession => Some(Self::RojoSourcemap),
+            Self::RojoSourcemap => Some(Self::RojoProject),
+            _ => None,
+        }
+    }
 }
 
 /**
@@ -29,17 +58,22 @@ pub enum InstanceProviderKind {
     `update` method must be called *after* `start`, but *before* `stop`.
 */
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum InstanceProviderVariant {
     None(NoneProvider),
     FileSourcemap(FileSourcemapProvider),
     RojoSourcemap(RojoSourcemapProvider),
+    RojoProject(RojoProjectProvider),
+    RojoSession(RojoSessionProvider),
+    RobloxFile(RobloxFileProvider),
 }
 
 impl InstanceProviderVariant {
     pub fn from_kind(
         kind: InstanceProviderKind,
         config: Config,
-        sender: UnboundedSender<Option<InstanceNode>>,
+        sender: UnboundedSender<InstanceUpdate>,
+        fallback: UnboundedSender<InstanceProviderKind>,
     ) -> Self {
         match kind {
             InstanceProviderKind::None => Self::None(NoneProvider::new(config, sender)),
@@ -49,6 +83,15 @@ impl InstanceProviderVariant {
             InstanceProviderKind::RojoSourcemap => {
                 Self::RojoSourcemap(RojoSourcemapProvider::new(config, sender))
             }
+            InstanceProviderKind::RojoProject => {
+                Self::RojoProject(RojoProjectProvider::new(config, sender))
+            }
+            InstanceProviderKind::RojoSession => {
+                Self::RojoSession(RojoSessionProvider::new(config, sender, fallback))
+            }
+            InstanceProviderKind::RobloxFile => {
+                Self::RobloxFile(RobloxFileProvider::new(config, sender))
+            }
         }
     }
 
@@ -57,6 +100,9 @@ impl InstanceProviderVariant {
             Self::None(_) => InstanceProviderKind::None,
             Self::FileSourcemap(_) => InstanceProviderKind::FileSourcemap,
             Self::RojoSourcemap(_) => InstanceProviderKind::RojoSourcemap,
+            Self::RojoProject(_) => InstanceProviderKind::RojoProject,
+            Self::RojoSession(_) => InstanceProviderKind::RojoSession,
+            Self::RobloxFile(_) => InstanceProviderKind::RobloxFile,
         }
     }
 
@@ -69,6 +115,9 @@ impl InstanceProviderVariant {
             Self::None(n) => n.start().await,
             Self::FileSourcemap(f) => f.start(smap).await,
             Self::RojoSourcemap(r) => r.start(proj).await,
+            Self::RojoProject(r) => r.start(proj).await,
+            Self::RojoSession(r) => r.start(proj).await,
+            Self::RobloxFile(r) => r.start().await,
         }
     }
 
@@ -81,6 +130,9 @@ impl InstanceProviderVariant {
             Self::None(n) => n.update().await,
             Self::FileSourcemap(f) => f.update(smap).await,
             Self::RojoSourcemap(r) => r.update(proj).await,
+            Self::RojoProject(r) => r.update(proj).await,
+            Self::RojoSession(r) => r.update(proj).await,
+            Self::RobloxFile(r) => r.update().await,
         }
     }
 
@@ -89,6 +141,9 @@ impl InstanceProviderVariant {
             Self::None(n) => n.stop().await,
             Self::FileSourcemap(f) => f.stop().await,
             Self::RojoSourcemap(r) => r.stop().await,
+            Self::RojoProject(r) => r.stop().await,
+            Self::RojoSession(r) => r.stop().await,
+            Self::RobloxFile(r) => r.stop().await,
         }
     }
 }
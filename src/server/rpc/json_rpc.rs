@@ -0,0 +1,299 @@
+use std::path::PathBuf;
+
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use super::{RpcData, RpcErrorCode, RpcErrorData, RpcId, RpcInput, RpcMessage, RpcResult};
+
+const JSON_RPC_VERSION: &str = "2.0";
+
+/**
+    Error codes as defined by the JSON-RPC 2.0 specification, with any errors
+    that are specific to this server being in the reserved server error range.
+*/
+const ERROR_CODES: &[(RpcErrorCode, i64)] = &[
+    (RpcErrorCode::ParseError, -32700),
+    (RpcErrorCode::InvalidRequest, -32600),
+    (RpcErrorCode::UnknownMethod, -32601),
+    (RpcErrorCode::InvalidParams, -32602),
+    (RpcErrorCode::Internal, -32603),
+    (RpcErrorCode::Cancelled, -32800),
+    (RpcErrorCode::NotFound, -32001),
+    (RpcErrorCode::NotFileBacked, -32002),
+    (RpcErrorCode::InvalidName, -32003),
+    (RpcErrorCode::InvalidOperation, -32004),
+    (RpcErrorCode::NameCollision, -32005),
+    (RpcErrorCode::PermissionDenied, -32006),
+    (RpcErrorCode::InvalidValue, -32007),
+];
+
+fn code_to_number(code: RpcErrorCode) -> i64 {
+    ERROR_CODES
+        .iter()
+        .find_map(|(c, n)| if *c == code { Some(*n) } else { None })
+        .expect("missing json-rpc error code")
+}
+
+fn number_to_code(number: i64) -> RpcErrorCode {
+    ERROR_CODES
+        .iter()
+        .find_map(|(c, n)| if *n == number { Some(*c) } else { None })
+        .unwrap_or(RpcErrorCode::Internal)
+}
+
+fn error_response(id: RpcId, code: RpcErrorCode, message: impl Into<String>) -> RpcMessage {
+    RpcMessage::Response(RpcData {
+        id: Some(id),
+        method: String::new(),
+        value: None,
+        error: Some(RpcErrorData::new(code, message)),
+    })
+}
+
+fn decode_id(value: &JsonValue) -> Option<RpcId> {
+    match value {
+        JsonValue::Null => Some(RpcId::Null),
+        JsonValue::String(s) => Some(RpcId::String(s.clone())),
+        JsonValue::Number(n) if n.is_i64() || n.is_u64() => Some(RpcId::Number(n.clone())),
+        _ => None,
+    }
+}
+
+fn decode_error(value: JsonValue) -> RpcErrorData {
+    let code = value.get("code").and_then(JsonValue::as_i64).unwrap_or(0);
+    let message = value
+        .get("message")
+        .and_then(JsonValue::as_str)
+        .unwrap_or_default();
+    let path = value
+        .get("data")
+        .and_then(|data| data.get("path"))
+        .and_then(JsonValue::as_str)
+        .map(PathBuf::from);
+    RpcErrorData {
+        code: number_to_code(code),
+        message: message.to_string(),
+        path,
+    }
+}
+
+/**
+    Decodes a single request, notification, or response object.
+
+    Returns an error response if the object is not a valid message.
+*/
+fn decode_message(value: JsonValue) -> Result<RpcMessage, Box<RpcMessage>> {
+    let invalid = |id: Option<RpcId>, message: &str| {
+        Box::new(error_response(
+            id.unwrap_or(RpcId::Null),
+            RpcErrorCode::InvalidRequest,
+            message,
+        ))
+    };
+
+    let mut object = match value {
+        JsonValue::Object(object) => object,
+        _ => return Err(invalid(None, "Message must be an object")),
+    };
+
+    let id = match object.remove("id") {
+        None => None,
+        Some(id) => match decode_id(&id) {
+            Some(id) => Some(id),
+            None => return Err(invalid(None, "Id must be a string, integer, or null")),
+        },
+    };
+
+    if object.get("jsonrpc").and_then(JsonValue::as_str) != Some(JSON_RPC_VERSION) {
+        return Err(invalid(
+            id,
+            "Message must have a 'jsonrpc' field set to '2.0'",
+        ));
+    }
+
+    if let Some(method) = object.remove("method") {
+        let method = match method {
+            JsonValue::String(method) => method,
+            _ => return Err(invalid(id, "Method must be a string")),
+        };
+        let params = match object.remove("params") {
+            None | Some(JsonValue::Null) => None,
+            Some(params) if params.is_object() || params.is_array() => Some(params),
+            Some(_) => return Err(invalid(id, "Params must be an object or array")),
+        };
+        Ok(RpcMessage::Request(RpcData {
+            id,
+            method,
+            value: params,
+            error: None,
+        }))
+    } else if object.contains_key("result") || object.contains_key("error") {
+        if id.is_none() {
+            return Err(invalid(id, "Response must have an id"));
+        }
+        Ok(RpcMessage::Response(RpcData {
+            id,
+            method: String::new(),
+            value: object.remove("result"),
+            error: object.remove("error").map(decode_error),
+        }))
+    } else {
+        Err(invalid(id, "Message must have a method, result, or error"))
+    }
+}
+
+/**
+    Decodes a line containing either a single message or a batch of messages.
+*/
+pub(super) fn decode(line: &str) -> RpcInput {
+    let mut input = RpcInput::default();
+    match serde_json::from_str::<JsonValue>(line) {
+        Err(e) => input.errors.push(error_response(
+            RpcId::Null,
+            RpcErrorCode::ParseError,
+            e.to_string(),
+        )),
+        Ok(JsonValue::Array(values)) if values.is_empty() => input.errors.push(error_response(
+            RpcId::Null,
+            RpcErrorCode::InvalidRequest,
+            "Batch must not be empty",
+        )),
+        Ok(JsonValue::Array(values)) => {
+            input.is_batch = true;
+            for value in values {
+                match decode_message(value) {
+                    Ok(message) => input.messages.push(message),
+                    Err(error) => input.errors.push(*error),
+                }
+            }
+        }
+        Ok(value) => match decode_message(value) {
+            Ok(message) => input.messages.push(message),
+            Err(error) => input.errors.push(*error),
+        },
+    }
+    input
+}
+
+fn encode_message(message: RpcMessage) -> JsonValue {
+    let mut object = JsonMap::new();
+    object.insert("jsonrpc".to_string(), json!(JSON_RPC_VERSION));
+    match message {
+        RpcMessage::Request(data) => {
+            if let Some(id) = data.id {
+                object.insert("id".to_string(), json!(id));
+            }
+            object.insert("method".to_string(), json!(data.method));
+            if let Some(params) = data.value.filter(|v| !v.is_null()) {
+                object.insert("params".to_string(), params);
+            }
+        }
+        RpcMessage::Response(data) => {
+            object.insert("id".to_string(), json!(data.id.unwrap_or(RpcId::Null)));
+            if let Some(error) = data.error {
+                let mut error_data = json!({ "kind": error.code });
+                if let Some(path) = error.path {
+                    error_data["path"] = json!(path);
+                }
+                let error = json!({
+                    "code": code_to_number(error.code),
+                    "message": error.message,
+                    "data": error_data,
+                });
+                object.insert("error".to_string(), error);
+            } else {
+                let result = data.value.unwrap_or(JsonValue::Null);
+                object.insert("result".to_string(), result);
+            }
+        }
+    }
+    JsonValue::Object(object)
+}
+
+/**
+    Encodes one or more messages, as a batch if needed.
+*/
+pub(super) fn encode(messages: Vec<RpcMessage>, is_batch: bool) -> RpcResult<String> {
+    let mut values = messages.into_iter().map(encode_message).collect::<Vec<_>>();
+    if is_batch {
+        Ok(serde_json::to_string(&values)?)
+    } else {
+        Ok(serde_json::to_string(&values.swap_remove(0))?)
+    }
+}
+
+#[test]
+fn decode_json_rpc_messages() {
+    // Batches, with invalid messages turned into error responses
+    let input = decode(
+        r#"[
+            {"jsonrpc": "2.0", "id": 1, "method": "dom/root"},
+            {"jsonrpc": "2.0", "method": "$/cancelRequest", "params": {"id": 1}},
+            {"jsonrpc": "1.0", "id": "a", "method": "dom/root"},
+            {"jsonrpc": "2.0", "id": 2.5, "method": "dom/root"}
+        ]"#,
+    );
+    assert!(input.is_batch);
+    assert_eq!(input.messages.len(), 2);
+    assert_eq!(input.errors.len(), 2);
+    assert_eq!(input.messages[0].get_method(), "dom/root");
+
+    // Notifications have no id
+    assert!(!input.messages[0].is_notification());
+    assert!(input.messages[1].is_notification());
+    assert_eq!(input.messages[1].get_id(), None);
+
+    // Errors keep the id of the invalid message, if it could be decoded
+    assert_eq!(
+        input.errors[0].get_id(),
+        Some(&RpcId::String("a".to_string()))
+    );
+    assert_eq!(input.errors[1].get_id(), Some(&RpcId::Null));
+
+    let input = decode("[]");
+    assert!(input.messages.is_empty());
+    assert_eq!(
+        input.errors[0].get_error().map(|e| e.code),
+        Some(RpcErrorCode::InvalidRequest)
+    );
+    let input = decode("{");
+    assert_eq!(
+        input.errors[0].get_error().map(|e| e.code),
+        Some(RpcErrorCode::ParseError)
+    );
+}
+
+#[test]
+fn encode_json_rpc_messages() {
+    // Ids are echoed back exactly as they were received
+    for id in ["-1", "18446744073709551615", "\"abc\"", "null"] {
+        let line = format!(r#"{{"jsonrpc": "2.0", "id": {id}, "method": "dom/root"}}"#);
+        let request = decode(&line).messages.remove(0);
+        let response = request.respond().with_data(true).unwrap();
+        let encoded = encode(vec![response], false).unwrap();
+        assert_eq!(
+            serde_json::from_str::<JsonValue>(&encoded).unwrap(),
+            serde_json::from_str::<JsonValue>(&format!(
+                r#"{{"jsonrpc": "2.0", "id": {id}, "result": true}}"#
+            ))
+            .unwrap()
+        );
+    }
+
+    // Error codes are mapped to numbers and back, keeping the path
+    for (code, number) in ERROR_CODES {
+        let request = decode(r#"{"jsonrpc": "2.0", "id": 1, "method": "dom/root"}"#)
+            .messages
+            .remove(0);
+        let mut error = RpcErrorData::new(*code, "message");
+        error.path = Some(PathBuf::from("src/Module.luau"));
+        let encoded = encode(vec![request.respond().with_error(error)], true).unwrap();
+        let value = serde_json::from_str::<JsonValue>(&encoded).unwrap();
+        assert_eq!(value[0]["error"]["code"], json!(number));
+
+        let decoded = decode(&encoded).messages.remove(0);
+        let error = decoded.get_error().unwrap();
+        assert_eq!(error.code, *code);
+        assert_eq!(error.path.as_deref(), Some("src/Module.luau".as_ref()));
+    }
+    assert_eq!(number_to_code(-1), RpcErrorCode::Internal);
+}
@@ -0,0 +1,329 @@
+#![allow(dead_code)]
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+mod json_rpc;
+
+fn next_data_id() -> u64 {
+    static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+    ID_COUNTER.fetch_add(1, Ordering::SeqCst)
+}
+
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub enum RpcError {
+    Io(#[from] io::Error),
+    Json(#[from] serde_json::Error),
+}
+
+pub type RpcResult<T> = Result<T, RpcError>;
+
+/**
+    The protocol used to encode and decode messages.
+
+    - `Legacy` is the protocol used by the VSCode extension, where all
+      messages are `{ kind, data: { id, method, value } }` objects
+    - `JsonRpc` is [JSON-RPC 2.0](https://www.jsonrpc.org/specification),
+      with support for notifications, error objects, and batches
+
+    Both protocols send one message (or batch) per line.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum RpcProtocol {
+    #[default]
+    Legacy,
+    JsonRpc,
+}
+
+/**
+    A machine-readable code describing why a request failed.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RpcErrorCode {
+    ParseError,
+    InvalidRequest,
+    UnknownMethod,
+    InvalidParams,
+    NotFound,
+    NotFileBacked,
+    InvalidName,
+    InvalidOperation,
+    InvalidValue,
+    NameCollision,
+    PermissionDenied,
+    Cancelled,
+    Internal,
+}
+
+/**
+    An error payload, sent in a response instead of a value when a request fails.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcErrorData {
+    pub code: RpcErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+impl RpcErrorData {
+    pub fn new(code: RpcErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            path: None,
+        }
+    }
+}
+
+/**
+    The id of a message, used to correlate responses with requests.
+
+    Ids are always sent back exactly as they were received, which is why numbers
+    are kept as they were parsed, including negative and very large numbers.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RpcId {
+    Number(serde_json::Number),
+    String(String),
+    Null,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcData {
+    #[serde(default)]
+    id: Option<RpcId>,
+    method: String,
+    value: Option<JsonValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<RpcErrorData>,
+}
+
+impl RpcData {
+    fn new(method: impl Into<String>) -> Self {
+        Self {
+            id: Some(RpcId::Number(next_data_id().into())),
+            method: method.into(),
+            value: None,
+            error: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data")]
+pub enum RpcMessage {
+    Request(RpcData),
+    Response(RpcData),
+}
+
+impl RpcMessage {
+    pub fn new_request(method: impl Into<String>) -> Self {
+        Self::Request(RpcData::new(method))
+    }
+
+    pub fn new_response(method: impl Into<String>) -> Self {
+        Self::Response(RpcData::new(method))
+    }
+
+    /**
+        Creates a new notification, which is a request that expects no response.
+
+        Notifications are sent as regular requests with new ids when using the legacy protocol.
+    */
+    pub fn new_notification(method: impl Into<String>) -> Self {
+        let mut data = RpcData::new(method);
+        data.id = None;
+        Self::Request(data)
+    }
+
+    pub const fn is_request(&self) -> bool {
+        matches!(self, Self::Request(_))
+    }
+
+    pub const fn is_response(&self) -> bool {
+        matches!(self, Self::Response(_))
+    }
+
+    pub const fn is_notification(&self) -> bool {
+        matches!(self, Self::Request(RpcData { id: None, .. }))
+    }
+
+    pub fn respond(&self) -> Self {
+        if let Self::Request(req) = self {
+            let mut data = RpcData::new(&req.method);
+            data.id.clone_from(&req.id);
+            Self::Response(data)
+        } else {
+            panic!("can only respond to requests")
+        }
+    }
+
+    pub fn with_data(mut self, data: impl Serialize) -> RpcResult<Self> {
+        let value = serde_json::to_value(data)?;
+        let inner = match self {
+            Self::Request(ref mut d) => &mut d.value,
+            Self::Response(ref mut d) => &mut d.value,
+        };
+        inner.replace(value);
+        Ok(self)
+    }
+
+    pub fn with_error(mut self, error: impl Into<RpcErrorData>) -> Self {
+        let inner = match self {
+            Self::Request(ref mut d) => &mut d.error,
+            Self::Response(ref mut d) => &mut d.error,
+        };
+        inner.replace(error.into());
+        self
+    }
+
+    pub fn get_error(&self) -> Option<&RpcErrorData> {
+        let inner = match self {
+            Self::Request(d) => d,
+            Self::Response(d) => d,
+        };
+        inner.error.as_ref()
+    }
+
+    pub fn get_data<T>(&self) -> RpcResult<T>
+    where
+        T: DeserializeOwned,
+    {
+        let inner = match self {
+            Self::Request(d) => d,
+            Self::Response(d) => d,
+        };
+        Ok(serde_json::from_value(match inner.value.as_ref() {
+            None => JsonValue::Null,
+            Some(v) => v.clone(),
+        })?)
+    }
+
+    pub fn get_id(&self) -> Option<&RpcId> {
+        let inner = match self {
+            Self::Request(d) => d,
+            Self::Response(d) => d,
+        };
+        inner.id.as_ref()
+    }
+
+    pub fn get_method(&self) -> &str {
+        let inner = match self {
+            Self::Request(d) => d,
+            Self::Response(d) => d,
+        };
+        &inner.method
+    }
+}
+
+/**
+    A single line of input, containing one or more messages.
+
+    Messages that could not be decoded are turned into error responses,
+    which should be sent back together with responses to other messages.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct RpcInput {
+    pub messages: Vec<RpcMessage>,
+    pub errors: Vec<RpcMessage>,
+    pub is_batch: bool,
+}
+
+impl RpcProtocol {
+    /**
+        Reads a single line of input, decoding it into one or more messages.
+
+        Returns `None` if the reader has reached its end.
+    */
+    pub async fn read_from<R>(self, reader: &mut R) -> Option<RpcResult<RpcInput>>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut buf = String::new();
+        let num_bytes = match reader.read_line(&mut buf).await {
+            Err(e) => return Some(Err(e.into())),
+            Ok(v) => v,
+        };
+        if num_bytes > 0 {
+            Some(self.decode(&buf))
+        } else {
+            None
+        }
+    }
+
+    fn decode(self, line: &str) -> RpcResult<RpcInput> {
+        match self {
+            Self::Legacy => Ok(RpcInput {
+                messages: vec![serde_json::from_str(line)?],
+                ..Default::default()
+            }),
+            Self::JsonRpc => Ok(json_rpc::decode(line)),
+        }
+    }
+
+    /**
+        Writes one or more messages as a single line of output.
+
+        Writes nothing if there are no messages, and always writes a
+        single message as-is, unless the messages are part of a batch.
+    */
+    pub async fn write_to<W>(
+        self,
+        writer: &mut W,
+        messages: Vec<RpcMessage>,
+        is_batch: bool,
+    ) -> RpcResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        if messages.is_empty() {
+            return Ok(());
+        }
+        let mut line = self.encode(messages, is_batch)?;
+        line.push('\n');
+        writer.write_all(line.as_bytes()).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    fn encode(self, mut messages: Vec<RpcMessage>, is_batch: bool) -> RpcResult<String> {
+        match self {
+            Self::Legacy => {
+                for message in &mut messages {
+                    if let RpcMessage::Request(data) = message {
+                        data.id
+                            .get_or_insert_with(|| RpcId::Number(next_data_id().into()));
+                    }
+                }
+                if is_batch {
+                    Ok(serde_json::to_string(&messages)?)
+                } else {
+                    Ok(serde_json::to_string(&messages[0])?)
+                }
+            }
+            Self::JsonRpc => json_rpc::encode(messages, is_batch),
+        }
+    }
+
+    /**
+        Writes a single message as a line of output.
+    */
+    pub async fn write_message_to<W>(self, writer: &mut W, message: RpcMessage) -> RpcResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.write_to(writer, vec![message], false).await
+    }
+}
@@ -1,100 +1,390 @@
 use std::{path::PathBuf, sync::Arc};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde_json::Value as JsonValue;
 use tokio::{
-    io::BufReader,
+    io::{AsyncRead, AsyncWrite, BufReader},
+    runtime::Handle,
     sync::{
-        mpsc::{UnboundedReceiver, UnboundedSender},
-        Mutex as AsyncMutex,
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        oneshot, Mutex as AsyncMutex, RwLock as AsyncRwLock,
     },
+    task::JoinSet,
 };
-use tracing::{debug, error};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info};
 
 use super::{
     config::Config,
-    dom::Dom,
-    handlers::handle_rpc_message,
+    dom::{Dom, DomNotification},
+    handlers::{
+        handle_cancel_request, handle_initialize_request, handle_read_request,
+        handle_write_request, is_cancel_request, is_initialize_request, is_read_only_request,
+        InFlightRequests,
+    },
     notify::{AsyncFileCache, AsyncFileEvent, AsyncFileWatcher},
-    provider::InstanceProvider,
-    rpc::RpcMessage,
+    provider::{InstanceProvider, ModelFiles, PropertyFiles},
+    rpc::{RpcErrorCode, RpcErrorData, RpcMessage, RpcProtocol},
+    transport::{Clients, Connection, Listener, OutputMessages},
 };
 
 type FileEvent = (AsyncFileEvent, PathBuf, Option<String>);
 
 /**
-    Emits notifications from an instance dom over stdio when they become available.
+    Instance providers for all roots, in the same order as in the config.
 */
-pub async fn emit_notifications(_config: Config, instance_dom: Arc<AsyncMutex<Dom>>) -> Result<()> {
-    let mut stdout = tokio::io::stdout();
-
-    // Emit an initial 'null' (meaning no instance data) to
-    // let the consumer know instance notifications have started
-    RpcMessage::new_request("dom/notification")
-        .with_data(JsonValue::Null)?
-        .write_to(&mut stdout)
-        .await?;
+pub type InstanceProviders = Vec<AsyncMutex<InstanceProvider>>;
 
+/**
+    Emits notifications from an instance dom to all connected clients when they become available.
+*/
+pub async fn emit_notifications(
+    _config: Config,
+    instance_dom: Arc<AsyncRwLock<Dom>>,
+    clients: Arc<Clients>,
+) -> Result<()> {
     // Take out the notification receiver from the dom
     let mut notification_receiver = {
-        let mut dom = instance_dom.lock().await;
+        let mut dom = instance_dom.write().await;
         dom.take_notification_receiver().unwrap()
     };
 
-    // Emit rest of notifications while they keep coming in
+    // Emit notifications while they keep coming in
     while let Some(notification) = notification_receiver.recv().await {
-        RpcMessage::new_request("dom/notification")
-            .with_data(notification)?
-            .write_to(&mut stdout)
-            .await?;
+        let message = RpcMessage::new_notification("dom/notification").with_data(notification)?;
+        clients.broadcast(message);
     }
 
     Ok(())
 }
 
 /**
-    Receives instances from an instance provider (receiver) and serves them over stdio.
+    A request that has been received, but not yet handled.
 */
-pub async fn serve_instances(
-    _config: Config,
-    instance_dom: Arc<AsyncMutex<Dom>>,
-    instances: Arc<AsyncMutex<InstanceProvider>>,
+pub struct PendingRequest {
+    msg: RpcMessage,
+    cancel: CancellationToken,
+    requests: Arc<InFlightRequests>,
+    response_tx: oneshot::Sender<Option<RpcMessage>>,
+}
+
+/**
+    Writes messages from the given receiver to a single client.
+
+    All output for a client goes through this task, so that messages sent
+    from different tasks never end up interleaved with each other.
+*/
+async fn write_output(
+    protocol: RpcProtocol,
+    mut writer: impl AsyncWrite + Unpin,
+    mut output_rx: UnboundedReceiver<OutputMessages>,
+) {
+    while let Some((messages, is_batch)) = output_rx.recv().await {
+        if let Err(e) = protocol.write_to(&mut writer, messages, is_batch).await {
+            error!("error writing message: {e:?}");
+            break;
+        }
+    }
+}
+
+/**
+    Reads requests from a single client and passes them on to be dispatched, and sends
+    their responses back (as a single line per line of input) once they are ready.
+
+    Requests to initialize the connection or cancel other requests are
+    handled right away, and never wait for any other requests to finish first.
+*/
+async fn read_requests(
+    config: &Config,
+    instances: &InstanceProviders,
+    reader: impl AsyncRead + Unpin,
+    pending_tx: UnboundedSender<PendingRequest>,
+    output_tx: UnboundedSender<OutputMessages>,
+) {
+    let requests = Arc::new(InFlightRequests::default());
+    let mut reader = BufReader::new(reader);
+    while let Some(res) = config.protocol.read_from(&mut reader).await {
+        let input = match res {
+            Err(e) => {
+                error!("error reading message: {e:?}");
+                continue;
+            }
+            Ok(input) => input,
+        };
+
+        let mut response_rxs = Vec::new();
+        for msg in input.messages {
+            debug!("got message: {msg:?}");
+            let (response_tx, response_rx) = oneshot::channel();
+            if is_initialize_request(&msg) {
+                // NOTE: Clients that only know about a single root get the kind of the first root
+                let provider_kind = instances[0].lock().await.kind();
+                match handle_initialize_request(msg, config, provider_kind) {
+                    Ok(response) => response_tx.send(response).ok(),
+                    Err(e) => {
+                        error!("failed to respond to message: {e:?}");
+                        response_tx.send(None).ok()
+                    }
+                };
+            } else if is_cancel_request(&msg) {
+                response_tx.send(handle_cancel_request(msg, &requests)).ok();
+            } else {
+                let cancel = requests.begin(&msg);
+                pending_tx
+                    .send(PendingRequest {
+                        msg,
+                        cancel,
+                        requests: Arc::clone(&requests),
+                        response_tx,
+                    })
+                    .ok();
+            }
+            response_rxs.push(response_rx);
+        }
+
+        let output_tx = output_tx.clone();
+        tokio::spawn(async move {
+            let mut responses = input.errors;
+            for response_rx in response_rxs {
+                if let Ok(Some(response)) = response_rx.await {
+                    responses.push(response);
+                }
+            }
+            output_tx.send((responses, input.is_batch)).ok();
+        });
+    }
+}
+
+/**
+    Dispatches requests from all clients to their handlers, in the order they were received.
+
+    Read-only requests are handled concurrently with each other, while any requests
+    that may modify the dom wait for all previous requests to finish, and vice versa.
+*/
+pub async fn dispatch_requests(
+    instance_dom: Arc<AsyncRwLock<Dom>>,
+    mut pending_rx: UnboundedReceiver<PendingRequest>,
 ) -> Result<()> {
-    let stdin = tokio::io::stdin();
+    while let Some(pending) = pending_rx.recv().await {
+        let PendingRequest {
+            msg,
+            cancel,
+            requests,
+            response_tx,
+        } = pending;
 
-    // Spawn a task to listen for requests over stdin
-    let stdin_dom = Arc::clone(&instance_dom);
-    let stdin_handle = tokio::spawn(async move {
-        let mut reader = BufReader::new(stdin);
-        while let Some(res) = RpcMessage::read_from(&mut reader).await {
-            match res {
-                Err(e) => error!("error reading stdio message: {e:?}"),
-                Ok(msg) => {
-                    debug!("got stdio message: {msg:?}");
-                    let mut dom = stdin_dom.lock().await;
-                    if let Err(e) = handle_rpc_message(msg, &mut dom).await {
-                        error!("failed to respond to message: {e:?}")
+        let id = msg.get_id().cloned();
+        let fallback = if msg.is_request() && !msg.is_notification() {
+            Some(msg.respond())
+        } else {
+            None
+        };
+        let cancelled = fallback.clone().map(|response| {
+            response.with_error(RpcErrorData::new(
+                RpcErrorCode::Cancelled,
+                "Request was cancelled",
+            ))
+        });
+
+        // NOTE: Locks must be acquired here and not in the spawned
+        // tasks, otherwise requests may be handled out of order
+        let handle = if cancel.is_cancelled() {
+            tokio::spawn(async move { Ok(cancelled) })
+        } else if is_read_only_request(&msg) {
+            // NOTE: Read-only requests such as queries may do a lot of work without
+            // yielding, so we run them on the blocking thread pool to make sure
+            // that they never prevent other requests from being read or handled
+            let dom = Arc::clone(&instance_dom).read_owned().await;
+            let runtime = Handle::current();
+            tokio::task::spawn_blocking(move || {
+                runtime.block_on(async move {
+                    tokio::select! {
+                        biased;
+                        _ = cancel.cancelled() => Ok(cancelled),
+                        res = handle_read_request(msg, &dom, &cancel) => res,
                     }
+                })
+            })
+        } else {
+            let mut dom = Arc::clone(&instance_dom).write_owned().await;
+            tokio::spawn(async move {
+                if cancel.is_cancelled() {
+                    Ok(cancelled)
+                } else {
+                    handle_write_request(msg, &mut dom, &cancel).await
                 }
+            })
+        };
+
+        tokio::spawn(async move {
+            let response = match handle.await {
+                Ok(Ok(response)) => response,
+                Ok(Err(e)) => {
+                    error!("failed to respond to message: {e:?}");
+                    fallback.map(|response| {
+                        response
+                            .with_error(RpcErrorData::new(RpcErrorCode::Internal, e.to_string()))
+                    })
+                }
+                Err(e) => {
+                    error!("failed to respond to message: {e:?}");
+                    None
+                }
+            };
+            if let Some(id) = id.as_ref() {
+                requests.finish(id);
             }
+            response_tx.send(response).ok();
+        });
+    }
+
+    Ok(())
+}
+
+/**
+    Serves a single connected client until it disconnects.
+
+    The client receives an initial `null` notification (meaning no instance data),
+    followed by a notification for each root instance that has already been added.
+*/
+async fn serve_client(
+    config: Config,
+    instance_dom: Arc<AsyncRwLock<Dom>>,
+    instances: Arc<InstanceProviders>,
+    clients: Arc<Clients>,
+    pending_tx: UnboundedSender<PendingRequest>,
+    connection: Connection,
+) -> Result<()> {
+    let (output_tx, output_rx) = unbounded_channel();
+    let writer_handle = tokio::spawn(write_output(config.protocol, connection.writer, output_rx));
+
+    // NOTE: We hold the dom lock while connecting so that
+    // the dom can not change until the client is connected
+    let client_id = {
+        let dom = instance_dom.read().await;
+        let mut initial =
+            vec![RpcMessage::new_notification("dom/notification").with_data(JsonValue::Null)?];
+        for root_id in dom.get_root_ids() {
+            initial.push(RpcMessage::new_notification("dom/notification").with_data(
+                DomNotification::Added {
+                    parent_id: None,
+                    child_id: root_id,
+                },
+            )?);
+        }
+        for message in initial {
+            output_tx.send((vec![message], false))?;
         }
-    });
+        clients.connect(output_tx.clone())
+    };
+
+    read_requests(
+        &config,
+        &instances,
+        connection.reader,
+        pending_tx,
+        output_tx,
+    )
+    .await;
+
+    // Disconnecting drops the last long-lived output sender, so the writer
+    // will stop once any remaining responses have been written
+    clients.disconnect(client_id);
+    writer_handle.await.ok();
 
-    // Take out the instance receiver from the provider
-    let mut instance_receiver = {
-        let mut instances = instances.lock().await;
-        instances.take_instance_receiver().unwrap()
+    Ok(())
+}
+
+/**
+    Serves clients over stdio, or listens for clients connecting
+    to the configured address and serves all of them concurrently.
+*/
+pub async fn serve_clients(
+    config: Config,
+    instance_dom: Arc<AsyncRwLock<Dom>>,
+    instances: Arc<InstanceProviders>,
+    clients: Arc<Clients>,
+    pending_tx: UnboundedSender<PendingRequest>,
+) -> Result<()> {
+    let Some(address) = config.listen.clone() else {
+        return serve_client(
+            config,
+            instance_dom,
+            instances,
+            clients,
+            pending_tx,
+            Connection::stdio(),
+        )
+        .await;
     };
 
-    // Watch for further changes received from instance provider(s)
-    while let Some(root_node_opt) = instance_receiver.recv().await {
-        let mut dom = instance_dom.lock().await;
-        dom.apply_new_root(root_node_opt);
+    let listener = Listener::bind(&address)
+        .await
+        .with_context(|| format!("failed to listen on {address}"))?;
+    info!("Listening on {}", listener.local_address()?);
+
+    loop {
+        let connection = match listener.accept().await {
+            Err(e) => {
+                error!("error accepting client: {e:?}");
+                continue;
+            }
+            Ok(connection) => connection,
+        };
+        debug!("client connected");
+        tokio::spawn(serve_client(
+            config.clone(),
+            Arc::clone(&instance_dom),
+            Arc::clone(&instances),
+            Arc::clone(&clients),
+            pending_tx.clone(),
+            connection,
+        ));
+    }
+}
+
+/**
+    Receives instances from the instance providers (receivers) of all roots and applies them to the instance dom.
+*/
+pub async fn serve_instances(
+    _config: Config,
+    instance_dom: Arc<AsyncRwLock<Dom>>,
+    instances: Arc<InstanceProviders>,
+) -> Result<()> {
+    let mut set = JoinSet::new();
+    for (index, instances) in instances.iter().enumerate() {
+        // Take out the instance receiver from the provider for this root
+        let mut instance_receiver = {
+            let mut instances = instances.lock().await;
+            instances.take_instance_receiver().unwrap()
+        };
+
+        // Watch for further changes received from instance provider(s), and expand any model
+        // files + load properties in them before locking the dom, since reading is slow
+        let instance_dom = Arc::clone(&instance_dom);
+        set.spawn(async move {
+            let mut model_files = ModelFiles::default();
+            let mut property_files = PropertyFiles::default();
+            while let Some(mut update) = instance_receiver.recv().await {
+                let expanded = tokio::task::spawn_blocking(move || {
+                    model_files.expand(&mut update);
+                    property_files.load(&mut update);
+                    (model_files, property_files, update)
+                })
+                .await;
+                let Ok((new_model_files, new_property_files, update)) = expanded else {
+                    error!("failed to join model & property file task");
+                    break;
+                };
+                model_files = new_model_files;
+                property_files = new_property_files;
+                let mut dom = instance_dom.write().await;
+                dom.apply_update(index, update);
+            }
+        });
     }
 
-    // Since our stdin task was spawned in the background
-    // we must also manually abort it when we are done
-    stdin_handle.abort();
+    while set.join_next().await.is_some() {}
 
     Ok(())
 }
@@ -104,30 +394,57 @@ pub async fn serve_instances(
 
     Will process the file events, start/update/stop relevant instance providers,
     use instance provider(s) to process files and subprocesses, and then
-    send changes using the sender in the given [`InstanceProvider`].
+    send changes using the sender in the given [`InstanceProvider`] for each root.
 */
 pub async fn provide_instances(
     config: Config,
-    _instance_dom: Arc<AsyncMutex<Dom>>,
-    instances: Arc<AsyncMutex<InstanceProvider>>,
+    instance_dom: Arc<AsyncRwLock<Dom>>,
+    instances: Arc<InstanceProviders>,
     mut file_event_rx: UnboundedReceiver<FileEvent>,
 ) -> Result<()> {
-    while let Some((event, file_path, file_contents)) = file_event_rx.recv().await {
-        // TODO: Make the dom aware of this file event, to add it to root metadata (for rojo, wally, ...)
-
-        let res = if config.is_sourcemap_path(&file_path) {
-            let mut instances = instances.lock().await;
-            instances.update_file(file_contents.as_deref()).await
-        } else if config.is_rojo_project_path(&file_path) {
-            let mut instances = instances.lock().await;
-            instances.update_rojo(file_contents.as_deref()).await
-        } else {
-            Ok(())
+    // Providers may stop working after they were started, such as when a
+    // rojo serve session ends, and must then be replaced by their fallbacks
+    for (index, provider) in instances.iter().enumerate() {
+        let mut fallback_receiver = {
+            let mut instances = provider.lock().await;
+            instances.take_fallback_receiver().unwrap()
         };
+        let instances = Arc::clone(&instances);
+        tokio::spawn(async move {
+            while let Some(kind) = fallback_receiver.recv().await {
+                let mut instances = instances[index].lock().await;
+                if let Err(e) = instances.fallback(kind).await {
+                    error!("failed to fall back from {kind:?} provider - {e:?}");
+                }
+            }
+        });
+    }
+
+    while let Some((event, file_path, file_contents)) = file_event_rx.recv().await {
+        // NOTE: Roots may share the same sourcemap file, so every root must be checked
+        for (index, root) in config.roots.iter().enumerate() {
+            let res = if root.is_project_file_path(&file_path) {
+                // Project files are not used by instance providers, only for root metadata
+                let mut dom = instance_dom.write().await;
+                dom.apply_project_file(index, &file_path, file_contents.as_deref());
+                Ok(())
+            } else if root.is_roblox_file_path(&file_path) {
+                let mut instances = instances[index].lock().await;
+                instances.update_roblox_file(!event.is_removed()).await
+            } else if root.is_sourcemap_path(&file_path) {
+                let mut instances = instances[index].lock().await;
+                instances.update_file(file_contents.as_deref()).await
+            } else if root.is_rojo_project_path(&file_path) {
+                let mut instances = instances[index].lock().await;
+                instances.update_rojo(file_contents.as_deref()).await
+            } else {
+                continue;
+            };
 
-        match res {
-            Err(e) => error!("{:?} -> {} -> {e:?}", event, file_path.display()),
-            Ok(_) => debug!("{:?} -> {}", event, file_path.display()),
+            match res {
+                Err(e) => error!("{:?} -> {} -> {e:?}", event, file_path.display()),
+                Ok(_) => debug!("{:?} -> {}", event, file_path.display()),
+            }
         }
     }
 
@@ -139,7 +456,6 @@ pub async fn provide_instances(
 */
 pub async fn watch_files(config: Config, file_event_tx: UnboundedSender<FileEvent>) -> Result<()> {
     let paths = config.paths_to_watch();
-    let paths = paths.iter().map(|p| p.to_path_buf()).collect::<Vec<_>>();
 
     // Update all paths once initially
     let mut cache = AsyncFileCache::new();
@@ -0,0 +1,254 @@
+use std::{
+    fmt, io,
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use dashmap::DashMap;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+    sync::mpsc::UnboundedSender,
+};
+
+use super::rpc::RpcMessage;
+
+/**
+    Messages that should be written as a single line of output, and if they are a batch.
+*/
+pub type OutputMessages = (Vec<RpcMessage>, bool);
+
+/**
+    An address that the server can listen on for clients, instead of using stdio.
+
+    Parsed from either a TCP socket address, such as `127.0.0.1:4000`
+    or `localhost:4000`, or a path to a Unix domain socket.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl FromStr for ListenAddress {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("Listen address must not be empty".to_string());
+        }
+
+        let tcp_addr = match s.strip_prefix("localhost:") {
+            Some(port) => port
+                .parse::<u16>()
+                .map(|port| SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+                .map_err(|e| format!("Invalid port '{port}': {e}"))
+                .map(Some)?,
+            None => s.parse::<SocketAddr>().ok(),
+        };
+
+        match tcp_addr {
+            // NOTE: Clients can modify files in the project, so we
+            // must never accept connections from other machines
+            Some(addr) if !addr.ip().is_loopback() => Err(format!(
+                "TCP address '{addr}' must be a loopback address, such as 127.0.0.1"
+            )),
+            Some(addr) => Ok(Self::Tcp(addr)),
+            None => {
+                let path = s.strip_prefix("unix:").unwrap_or(s);
+                Ok(Self::Unix(PathBuf::from(path)))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{addr}"),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/**
+    A connected client, with separate halves for reading and writing.
+*/
+pub struct Connection {
+    pub reader: Box<dyn AsyncRead + Send + Unpin>,
+    pub writer: Box<dyn AsyncWrite + Send + Unpin>,
+}
+
+impl Connection {
+    pub fn stdio() -> Self {
+        Self {
+            reader: Box::new(tokio::io::stdin()),
+            writer: Box::new(tokio::io::stdout()),
+        }
+    }
+}
+
+/**
+    A listener accepting connections from clients, on any supported transport.
+*/
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, PathBuf),
+}
+
+impl Listener {
+    pub async fn bind(address: &ListenAddress) -> io::Result<Self> {
+        match address {
+            ListenAddress::Tcp(addr) => Ok(Self::Tcp(TcpListener::bind(addr).await?)),
+            #[cfg(unix)]
+            ListenAddress::Unix(path) => {
+                use std::os::unix::fs::FileTypeExt;
+                // NOTE: A socket file is left behind if the server
+                // exits without cleaning up, so we remove stale ones
+                if let Ok(meta) = tokio::fs::symlink_metadata(path).await {
+                    if meta.file_type().is_socket() {
+                        tokio::fs::remove_file(path).await?;
+                    }
+                }
+                let listener = tokio::net::UnixListener::bind(path)?;
+                Ok(Self::Unix(listener, path.clone()))
+            }
+            #[cfg(not(unix))]
+            ListenAddress::Unix(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Unix domain sockets are not supported on this platform",
+            )),
+        }
+    }
+
+    /**
+        Returns the address that the listener is bound to.
+
+        This may differ from the address it was bound with, for example
+        when binding to a TCP address with port `0`.
+    */
+    pub fn local_address(&self) -> io::Result<ListenAddress> {
+        match self {
+            Self::Tcp(listener) => Ok(ListenAddress::Tcp(listener.local_addr()?)),
+            #[cfg(unix)]
+            Self::Unix(_, path) => Ok(ListenAddress::Unix(path.clone())),
+        }
+    }
+
+    pub async fn accept(&self) -> io::Result<Connection> {
+        match self {
+            Self::Tcp(listener) => {
+                let (stream, _) = listener.accept().await?;
+                let (reader, writer) = stream.into_split();
+                Ok(Connection {
+                    reader: Box::new(reader),
+                    writer: Box::new(writer),
+                })
+            }
+            #[cfg(unix)]
+            Self::Unix(listener, _) => {
+                let (stream, _) = listener.accept().await?;
+                let (reader, writer) = stream.into_split();
+                Ok(Connection {
+                    reader: Box::new(reader),
+                    writer: Box::new(writer),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Self::Unix(_, path) = self {
+            std::fs::remove_file(path).ok();
+        }
+    }
+}
+
+/**
+    Keeps track of all connected clients, so that notifications can be sent to all of them.
+*/
+#[derive(Debug, Default)]
+pub struct Clients {
+    next_id: AtomicU64,
+    outputs: DashMap<u64, UnboundedSender<OutputMessages>>,
+}
+
+impl Clients {
+    /**
+        Adds a new client, returning its id.
+    */
+    pub fn connect(&self, output_tx: UnboundedSender<OutputMessages>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.outputs.insert(id, output_tx);
+        id
+    }
+
+    /**
+        Removes a client, once it has disconnected.
+    */
+    pub fn disconnect(&self, id: u64) {
+        self.outputs.remove(&id);
+    }
+
+    /**
+        Sends a message to all connected clients.
+    */
+    pub fn broadcast(&self, message: RpcMessage) {
+        self.outputs
+            .retain(|_, output_tx| output_tx.send((vec![message.clone()], false)).is_ok());
+    }
+}
+
+#[test]
+fn parse_listen_addresses() {
+    let tcp = |s: &str| s.parse::<SocketAddr>().map(ListenAddress::Tcp).unwrap();
+    assert_eq!("127.0.0.1:4000".parse(), Ok(tcp("127.0.0.1:4000")));
+    assert_eq!("localhost:4000".parse(), Ok(tcp("127.0.0.1:4000")));
+    assert_eq!("[::1]:4000".parse(), Ok(tcp("[::1]:4000")));
+    assert_eq!(
+        "unix:/tmp/roblox-ui.sock".parse(),
+        Ok(ListenAddress::Unix(PathBuf::from("/tmp/roblox-ui.sock")))
+    );
+
+    // Addresses that accept connections from other machines are rejected
+    for address in [
+        "0.0.0.0:4000",
+        "[::]:4000",
+        "192.168.1.2:4000",
+        "10.0.0.1:4000",
+    ] {
+        assert!(address.parse::<ListenAddress>().is_err(), "{address}");
+    }
+    assert!("".parse::<ListenAddress>().is_err());
+    assert!("localhost:port".parse::<ListenAddress>().is_err());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn bind_unix_socket() {
+    use crate::util::path::TestDir;
+
+    let dir = TestDir::new("transport");
+    let path = dir.join("server.sock");
+    let address = ListenAddress::Unix(path.clone());
+
+    // Socket files left behind by a previous server are replaced
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+    let listener = Listener::bind(&address).await.unwrap();
+    assert_eq!(listener.local_address().unwrap(), address);
+    tokio::net::UnixStream::connect(&path).await.unwrap();
+    drop(listener);
+    assert!(!path.exists());
+
+    // Any other kind of file is never removed
+    std::fs::write(&path, "").unwrap();
+    assert!(Listener::bind(&address).await.is_err());
+    assert!(path.exists());
+}
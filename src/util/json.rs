@@ -0,0 +1,359 @@
+use std::ops::Range;
+
+use anyhow::{bail, Context, Result};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+const DEFAULT_INDENT: &str = "  ";
+
+/**
+    A single member of an object in a JSON document, with byte offsets into the document.
+*/
+#[derive(Debug)]
+struct Member {
+    key: String,
+    start: usize,
+    value: Range<usize>,
+}
+
+/**
+    An object in a JSON document, with byte offsets for its braces and members.
+*/
+#[derive(Debug)]
+struct Object {
+    open: usize,
+    close: usize,
+    members: Vec<Member>,
+}
+
+/**
+    Sets or removes a value in a JSON document, given the keys of the objects leading to it,
+    while preserving everything else in the document as-is - whitespace, key order, and so on.
+
+    Any missing objects along the way are created, and new values are
+    formatted to match the indentation that the document already uses.
+*/
+pub fn edit_json(source: &str, keys: &[&str], value: Option<&JsonValue>) -> Result<String> {
+    if keys.is_empty() {
+        bail!("missing keys to edit");
+    }
+    let src = source.as_bytes();
+    let open = skip_whitespace(src, 0);
+    if src.get(open) != Some(&b'{') {
+        bail!("expected document to be an object");
+    }
+    let unit = detect_indent_unit(source);
+    match find_edit(source, open, keys, value, &unit)? {
+        Some((range, replacement)) => {
+            let mut edited = String::with_capacity(source.len() + replacement.len());
+            edited.push_str(&source[..range.start]);
+            edited.push_str(&replacement);
+            edited.push_str(&source[range.end..]);
+            Ok(edited)
+        }
+        None => Ok(source.to_string()),
+    }
+}
+
+fn find_edit(
+    source: &str,
+    open: usize,
+    keys: &[&str],
+    value: Option<&JsonValue>,
+    unit: &str,
+) -> Result<Option<(Range<usize>, String)>> {
+    let src = source.as_bytes();
+    let object = parse_object(src, open)?;
+    let index = object.members.iter().position(|m| m.key == keys[0]);
+
+    // Values for keys that do not exist yet get wrapped in objects for any remaining keys
+    let wrapped = value.map(|value| {
+        keys[1..].iter().rev().fold(value.clone(), |inner, key| {
+            JsonValue::Object(JsonMap::from_iter([(key.to_string(), inner)]))
+        })
+    });
+
+    Ok(match (index, value) {
+        (Some(index), _) if keys.len() > 1 && src[object.members[index].value.start] == b'{' => {
+            find_edit(
+                source,
+                object.members[index].value.start,
+                &keys[1..],
+                value,
+                unit,
+            )?
+        }
+        (Some(index), Some(_)) => {
+            let member = &object.members[index];
+            let indent = line_indent(source, member.start);
+            let formatted = format_value(wrapped.as_ref().unwrap(), Some(&indent), unit);
+            Some((member.value.clone(), formatted))
+        }
+        (Some(_), None) if keys.len() > 1 => None,
+        (Some(index), None) => {
+            let members = &object.members;
+            let range = if members.len() == 1 {
+                object.open + 1..object.close
+            } else if index + 1 < members.len() {
+                members[index].start..members[index + 1].start
+            } else {
+                members[index - 1].value.end..members[index].value.end
+            };
+            Some((range, String::new()))
+        }
+        (None, None) => None,
+        (None, Some(_)) => {
+            let key = serde_json::to_string(keys[0])?;
+            let wrapped = wrapped.as_ref().unwrap();
+            let object_indent = line_indent(source, object.open);
+            match object.members.last() {
+                // NOTE: Empty objects have no members to copy formatting from, so
+                // we only put them on multiple lines if the document already is
+                None if source.trim().contains('\n') || source.trim() == "{}" => {
+                    let indent = format!("{object_indent}{unit}");
+                    let formatted = format_value(wrapped, Some(&indent), unit);
+                    let replacement = format!("{{\n{indent}{key}: {formatted}\n{object_indent}}}");
+                    Some((object.open..object.close + 1, replacement))
+                }
+                None => {
+                    let formatted = format_value(wrapped, None, unit);
+                    Some((
+                        object.open..object.close + 1,
+                        format!("{{ {key}: {formatted} }}"),
+                    ))
+                }
+                Some(last) => {
+                    let first = &object.members[0];
+                    let is_multiline = source[object.open..first.start].contains('\n');
+                    let replacement = if is_multiline {
+                        let indent = line_indent(source, first.start);
+                        let formatted = format_value(wrapped, Some(&indent), unit);
+                        format!(",\n{indent}{key}: {formatted}")
+                    } else {
+                        format!(", {key}: {}", format_value(wrapped, None, unit))
+                    };
+                    Some((last.value.end..last.value.end, replacement))
+                }
+            }
+        }
+    })
+}
+
+/**
+    Formats a value to be inserted into a document, on multiple lines using
+    the given indentation, or on a single line if there is no indentation.
+
+    Arrays that only contain other arrays and plain values are always kept
+    on a single line, since those are common for types such as `Vector3`.
+*/
+fn format_value(value: &JsonValue, indent: Option<&str>, unit: &str) -> String {
+    match value {
+        JsonValue::Object(map) if map.is_empty() => String::from("{}"),
+        JsonValue::Object(map) => match indent {
+            Some(indent) => {
+                let inner = format!("{indent}{unit}");
+                let members = map
+                    .iter()
+                    .map(|(key, value)| {
+                        let key = JsonValue::String(key.clone());
+                        format!("{inner}{key}: {}", format_value(value, Some(&inner), unit))
+                    })
+                    .collect::<Vec<_>>();
+                format!("{{\n{}\n{indent}}}", members.join(",\n"))
+            }
+            None => {
+                let members = map
+                    .iter()
+                    .map(|(key, value)| {
+                        let key = JsonValue::String(key.clone());
+                        format!("{key}: {}", format_value(value, None, unit))
+                    })
+                    .collect::<Vec<_>>();
+                format!("{{ {} }}", members.join(", "))
+            }
+        },
+        JsonValue::Array(items) => {
+            let has_objects = items.iter().any(JsonValue::is_object);
+            match indent {
+                Some(indent) if has_objects => {
+                    let inner = format!("{indent}{unit}");
+                    let items = items
+                        .iter()
+                        .map(|item| format!("{inner}{}", format_value(item, Some(&inner), unit)))
+                        .collect::<Vec<_>>();
+                    format!("[\n{}\n{indent}]", items.join(",\n"))
+                }
+                _ => {
+                    let items = items
+                        .iter()
+                        .map(|item| format_value(item, None, unit))
+                        .collect::<Vec<_>>();
+                    format!("[{}]", items.join(", "))
+                }
+            }
+        }
+        value => value.to_string(),
+    }
+}
+
+/**
+    Finds the indentation used for a single level in the given document,
+    using the first indented line, or a default if there are none.
+*/
+fn detect_indent_unit(source: &str) -> String {
+    source
+        .lines()
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or(DEFAULT_INDENT)
+        .to_string()
+}
+
+/**
+    Gets the indentation of the line containing the given byte offset.
+*/
+fn line_indent(source: &str, offset: usize) -> String {
+    let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    source[line_start..]
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect()
+}
+
+fn parse_object(src: &[u8], open: usize) -> Result<Object> {
+    let mut members = Vec::new();
+    let mut pos = skip_whitespace(src, open + 1);
+    if src.get(pos) == Some(&b'}') {
+        return Ok(Object {
+            open,
+            close: pos,
+            members,
+        });
+    }
+    loop {
+        let start = pos;
+        if src.get(pos) != Some(&b'"') {
+            bail!("expected object key at offset {pos}");
+        }
+        pos = skip_string(src, pos)?;
+        let key = serde_json::from_slice::<String>(&src[start..pos])
+            .with_context(|| format!("invalid object key at offset {start}"))?;
+        pos = skip_whitespace(src, pos);
+        if src.get(pos) != Some(&b':') {
+            bail!("expected ':' at offset {pos}");
+        }
+        let value_start = skip_whitespace(src, pos + 1);
+        let value_end = skip_value(src, value_start)?;
+        members.push(Member {
+            key,
+            start,
+            value: value_start..value_end,
+        });
+        pos = skip_whitespace(src, value_end);
+        match src.get(pos) {
+            Some(b',') => pos = skip_whitespace(src, pos + 1),
+            Some(b'}') => {
+                return Ok(Object {
+                    open,
+                    close: pos,
+                    members,
+                })
+            }
+            _ => bail!("expected ',' or '}}' at offset {pos}"),
+        }
+    }
+}
+
+fn skip_whitespace(src: &[u8], mut pos: usize) -> usize {
+    while src.get(pos).is_some_and(u8::is_ascii_whitespace) {
+        pos += 1;
+    }
+    pos
+}
+
+fn skip_string(src: &[u8], pos: usize) -> Result<usize> {
+    let mut pos = pos + 1;
+    while let Some(byte) = src.get(pos) {
+        match byte {
+            b'\\' => pos += 2,
+            b'"' => return Ok(pos + 1),
+            _ => pos += 1,
+        }
+    }
+    bail!("unterminated string")
+}
+
+fn skip_value(src: &[u8], pos: usize) -> Result<usize> {
+    match src.get(pos) {
+        Some(b'"') => skip_string(src, pos),
+        Some(b'{' | b'[') => {
+            let mut depth = 0;
+            let mut pos = pos;
+            while let Some(byte) = src.get(pos) {
+                match byte {
+                    b'"' => {
+                        pos = skip_string(src, pos)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Ok(pos + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                pos += 1;
+            }
+            bail!("unterminated object or array")
+        }
+        Some(_) => {
+            let mut end = pos;
+            while src
+                .get(end)
+                .is_some_and(|b| !b.is_ascii_whitespace() && !b",}]".contains(b))
+            {
+                end += 1;
+            }
+            Ok(end)
+        }
+        None => bail!("expected value at offset {pos}"),
+    }
+}
+
+#[test]
+fn edit_json_preserves_formatting() {
+    use serde_json::json;
+
+    let source = "{\n\t\"className\": \"Part\",\n\t\"properties\": {\n\t\t\"Size\": [1, 2, 3],\n\t\t\"Anchored\": true\n\t}\n}\n";
+
+    // Replacing keeps key order and everything else as-is
+    let edited = edit_json(source, &["properties", "Size"], Some(&json!([4, 5, 6]))).unwrap();
+    assert_eq!(
+        edited,
+        "{\n\t\"className\": \"Part\",\n\t\"properties\": {\n\t\t\"Size\": [4, 5, 6],\n\t\t\"Anchored\": true\n\t}\n}\n"
+    );
+
+    // Inserting uses the same indentation as existing members
+    let edited = edit_json(source, &["properties", "Transparency"], Some(&json!(0.5))).unwrap();
+    assert_eq!(
+        edited,
+        "{\n\t\"className\": \"Part\",\n\t\"properties\": {\n\t\t\"Size\": [1, 2, 3],\n\t\t\"Anchored\": true,\n\t\t\"Transparency\": 0.5\n\t}\n}\n"
+    );
+
+    // Removing the last member also removes the comma before it
+    let edited = edit_json(source, &["properties", "Anchored"], None).unwrap();
+    assert_eq!(
+        edited,
+        "{\n\t\"className\": \"Part\",\n\t\"properties\": {\n\t\t\"Size\": [1, 2, 3]\n\t}\n}\n"
+    );
+
+    // Missing objects are created, and empty documents get the default indentation
+    let edited = edit_json("{}", &["properties", "Disabled"], Some(&json!(true))).unwrap();
+    assert_eq!(
+        edited,
+        "{\n  \"properties\": {\n    \"Disabled\": true\n  }\n}"
+    );
+
+    assert!(edit_json("[]", &["properties"], None).is_err());
+}
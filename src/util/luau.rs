@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+
+/**
+    A single call to `require` in Luau source code.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LuauRequire {
+    /// Zero-based line number of the call.
+    pub line: usize,
+    /// The argument of the call, as written in the source, such as `Shared.Util`.
+    pub expression: String,
+    /// The argument of the call, with any local variables replaced by their values.
+    pub argument: LuauRequireArgument,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LuauRequireArgument {
+    /// An instance expression, such as `script.Parent.Util`.
+    Instance(String),
+    /// A string path, such as `@Packages/Promise` or `./Util`.
+    Path(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    Ident,
+    Str(String),
+    Punct(char),
+    Other,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+    line: usize,
+}
+
+impl Token {
+    fn is_ident(&self, source: &str, ident: &str) -> bool {
+        self.kind == TokenKind::Ident && &source[self.start..self.end] == ident
+    }
+
+    fn is_punct(&self, c: char) -> bool {
+        self.kind == TokenKind::Punct(c)
+    }
+}
+
+/**
+    Finds all calls to `require` in the given Luau source code.
+
+    Local variables that are assigned an instance expression, such as
+    `local Shared = game:GetService("ReplicatedStorage").Shared`, are tracked
+    and replaced in any later requires, so that `require(Shared.Util)` gets
+    the argument `game:GetService("ReplicatedStorage").Shared.Util`.
+
+    NOTE: This is not a full parser, and does not know about scopes, so the
+    last assignment to a local with the same name in the source is used.
+*/
+pub fn scan_requires(source: &str) -> Vec<LuauRequire> {
+    let tokens = tokenize(source);
+    let mut locals = HashMap::<String, String>::new();
+    let mut requires = Vec::new();
+
+    let mut index = 0;
+    while index < tokens.len() {
+        let token = &tokens[index];
+        let after_member =
+            index > 0 && (tokens[index - 1].is_punct('.') || tokens[index - 1].is_punct(':'));
+
+        // local Name = <instance expression>
+        if token.is_ident(source, "local")
+            && tokens
+                .get(index + 1)
+                .is_some_and(|t| t.kind == TokenKind::Ident)
+            && tokens.get(index + 2).is_some_and(|t| t.is_punct('='))
+        {
+            let name = &tokens[index + 1];
+            let name = source[name.start..name.end].to_string();
+            if let Some(end) = scan_instance_expression(source, &tokens, index + 3) {
+                let text = expression_text(source, &tokens[index + 3..end], &locals);
+                locals.insert(name, text);
+                index = end;
+            } else {
+                locals.remove(&name);
+                index += 3;
+            }
+            continue;
+        }
+
+        // require(<argument>)
+        if token.is_ident(source, "require")
+            && !after_member
+            && tokens.get(index + 1).is_some_and(|t| t.is_punct('('))
+        {
+            let start = index + 2;
+            let Some(end) = find_closing_paren(&tokens, start) else {
+                break;
+            };
+            if end > start {
+                let args = &tokens[start..end];
+                let expression = source[args[0].start..args[args.len() - 1].end].to_string();
+                let argument = match args {
+                    [Token {
+                        kind: TokenKind::Str(path),
+                        ..
+                    }] => LuauRequireArgument::Path(path.clone()),
+                    _ => LuauRequireArgument::Instance(expression_text(source, args, &locals)),
+                };
+                requires.push(LuauRequire {
+                    line: token.line,
+                    expression,
+                    argument,
+                });
+            }
+            index = end + 1;
+            continue;
+        }
+
+        index += 1;
+    }
+
+    requires
+}
+
+/**
+    Scans an instance expression starting at the given token, such as
+    `script.Parent:WaitForChild("Foo")`, returning the index of the token after it.
+*/
+fn scan_instance_expression(source: &str, tokens: &[Token], start: usize) -> Option<usize> {
+    if tokens.get(start)?.kind != TokenKind::Ident {
+        return None;
+    }
+    let mut index = start + 1;
+    while let Some(token) = tokens.get(index) {
+        match token.kind {
+            TokenKind::Punct('.') if tokens.get(index + 1)?.kind == TokenKind::Ident => {
+                index += 2;
+            }
+            TokenKind::Punct('[')
+                if matches!(tokens.get(index + 1)?.kind, TokenKind::Str(_))
+                    && tokens.get(index + 2)?.is_punct(']') =>
+            {
+                index += 3;
+            }
+            TokenKind::Punct(':')
+                if tokens.get(index + 1)?.kind == TokenKind::Ident
+                    && tokens.get(index + 2)?.is_punct('(') =>
+            {
+                index = find_closing_paren(tokens, index + 3)? + 1;
+            }
+            _ => break,
+        }
+    }
+    // NOTE: Anything else directly after the expression, such as a call,
+    // means that this is not an instance expression, but something else
+    match tokens.get(index) {
+        Some(token) if token.is_punct('(') || token.is_punct('{') => None,
+        Some(token) if matches!(token.kind, TokenKind::Str(_)) => None,
+        _ if tokens[start].is_ident(source, "require") => None,
+        _ => Some(index),
+    }
+}
+
+/**
+    Finds the index of the closing parenthesis for an opening parenthesis right before the given index.
+*/
+fn find_closing_paren(tokens: &[Token], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(start) {
+        match token.kind {
+            TokenKind::Punct('(') => depth += 1,
+            TokenKind::Punct(')') if depth == 0 => return Some(index),
+            TokenKind::Punct(')') => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/**
+    Gets the source text for the given tokens, replacing the first
+    token with the value of a local variable if there is one.
+*/
+fn expression_text(source: &str, tokens: &[Token], locals: &HashMap<String, String>) -> String {
+    let first = &tokens[0];
+    let last = &tokens[tokens.len() - 1];
+    match locals.get(&source[first.start..first.end]) {
+        Some(value) if first.kind == TokenKind::Ident => {
+            format!("{value}{}", &source[first.end..last.end])
+        }
+        _ => source[first.start..last.end].to_string(),
+    }
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line = 0;
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let start = pos;
+        let start_line = line;
+        let byte = bytes[pos];
+        let kind = match byte {
+            b'\n' => {
+                line += 1;
+                pos += 1;
+                continue;
+            }
+            b if b.is_ascii_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            b'-' if bytes.get(pos + 1) == Some(&b'-') => {
+                pos += 2;
+                match long_bracket_level(bytes, pos) {
+                    Some(level) => pos = skip_long_bracket(bytes, pos, level, &mut line),
+                    None => {
+                        while pos < bytes.len() && bytes[pos] != b'\n' {
+                            pos += 1;
+                        }
+                    }
+                }
+                continue;
+            }
+            b'[' if long_bracket_level(bytes, pos).is_some() => {
+                let level = long_bracket_level(bytes, pos).unwrap();
+                let content_start = pos + level + 2;
+                pos = skip_long_bracket(bytes, pos, level, &mut line);
+                let content_end = pos.saturating_sub(level + 2).max(content_start);
+                let content = &source[content_start..content_end];
+                TokenKind::Str(content.strip_prefix('\n').unwrap_or(content).to_string())
+            }
+            b'"' | b'\'' | b'`' => {
+                let (value, end) = scan_string(source, pos, &mut line);
+                pos = end;
+                TokenKind::Str(value)
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' => {
+                while pos < bytes.len()
+                    && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_')
+                {
+                    pos += 1;
+                }
+                TokenKind::Ident
+            }
+            b if b.is_ascii_digit() => {
+                while pos < bytes.len()
+                    && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'.')
+                {
+                    pos += 1;
+                }
+                TokenKind::Other
+            }
+            b if b.is_ascii_punctuation() => {
+                pos += 1;
+                TokenKind::Punct(b as char)
+            }
+            _ => {
+                // Skip the rest of any non-ascii character
+                pos += source[pos..].chars().next().map_or(1, char::len_utf8);
+                TokenKind::Other
+            }
+        };
+        tokens.push(Token {
+            kind,
+            start,
+            end: pos,
+            line: start_line,
+        });
+    }
+    tokens
+}
+
+/**
+    Gets the level of a long bracket, such as `[[` or `[==[`, starting at the given position.
+*/
+fn long_bracket_level(bytes: &[u8], pos: usize) -> Option<usize> {
+    if bytes.get(pos) != Some(&b'[') {
+        return None;
+    }
+    let level = bytes[pos + 1..].iter().take_while(|b| **b == b'=').count();
+    (bytes.get(pos + 1 + level) == Some(&b'[')).then_some(level)
+}
+
+/**
+    Skips a long bracket starting at the given position, returning the position after it.
+*/
+fn skip_long_bracket(bytes: &[u8], pos: usize, level: usize, line: &mut usize) -> usize {
+    let mut pos = pos + level + 2;
+    while pos < bytes.len() {
+        if bytes[pos] == b'\n' {
+            *line += 1;
+        } else if bytes[pos] == b']'
+            && bytes[pos + 1..].iter().take(level).all(|b| *b == b'=')
+            && bytes.get(pos + 1 + level) == Some(&b']')
+        {
+            return pos + level + 2;
+        }
+        pos += 1;
+    }
+    bytes.len()
+}
+
+/**
+    Scans a quoted string starting at the given position, returning its
+    value and the position after it. Escape sequences other than quotes
+    and backslashes are kept as-is, since they never appear in paths.
+*/
+fn scan_string(source: &str, pos: usize, line: &mut usize) -> (String, usize) {
+    let quote = source.as_bytes()[pos] as char;
+    let mut value = String::new();
+    let mut chars = source[pos + 1..].char_indices();
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            c if c == quote => return (value, pos + 1 + offset + 1),
+            // NOTE: Unterminated strings end before the newline, which is counted by the caller
+            '\n' => return (value, pos + 1 + offset),
+            '\\' => match chars.next() {
+                Some((_, c @ ('\\' | '"' | '\'' | '`'))) => value.push(c),
+                Some((_, '\n')) => {
+                    *line += 1;
+                    value.push('\n');
+                }
+                Some((_, c)) => {
+                    value.push('\\');
+                    value.push(c);
+                }
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    (value, source.len())
+}
+
+#[test]
+fn scan_luau_requires() {
+    use LuauRequireArgument::*;
+
+    let source = r#"
+        --[[ require(script.Commented) ]]
+        local ReplicatedStorage = game:GetService("ReplicatedStorage")
+        local Shared = ReplicatedStorage:WaitForChild("Shared")
+        local Promise = require("@Packages/Promise")
+
+        -- require(script.AlsoCommented)
+        local Util = require(Shared.Util)
+        local Other = require(script.Parent["Other Module"])
+        local text = "require(script.InString)"
+        local value = thing.require(script.Member)
+        require(getModule())
+    "#;
+
+    let requires = scan_requires(source)
+        .into_iter()
+        .map(|r| (r.line, r.argument))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        requires,
+        vec![
+            (4, Path("@Packages/Promise".into())),
+            (
+                7,
+                Instance(
+                    r#"game:GetService("ReplicatedStorage"):WaitForChild("Shared").Util"#.into()
+                )
+            ),
+            (8, Instance(r#"script.Parent["Other Module"]"#.into())),
+            (11, Instance("getModule()".into())),
+        ]
+    );
+
+    // Locals that are reassigned to something else are forgotten
+    let source = "local Shared = script.Shared\nlocal Shared = getShared()\nrequire(Shared.Util)";
+    assert_eq!(
+        scan_requires(source)[0].argument,
+        Instance("Shared.Util".into())
+    );
+
+    // Unterminated strings end at the end of their line, and keep line numbers intact
+    let source = "local text = \"unterminated\nlocal Util = require(script.Util)";
+    let requires = scan_requires(source);
+    assert_eq!(requires[0].line, 1);
+    assert_eq!(requires[0].argument, Instance("script.Util".into()));
+}
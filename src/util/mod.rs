@@ -1,3 +1,6 @@
+pub mod json;
+pub mod luau;
 pub mod path;
 pub mod rojo;
+pub mod wally;
 pub mod zip;
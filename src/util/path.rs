@@ -16,3 +16,52 @@ pub fn make_absolute_and_clean(path: impl AsRef<Path>) -> PathBuf {
         path.clean()
     }
 }
+
+pub fn make_relative_to_cwd(path: impl AsRef<Path>) -> PathBuf {
+    let path = make_absolute_and_clean(path);
+    match path.strip_prefix(&*CWD) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path,
+    }
+}
+
+/**
+    A temporary directory for tests, unique to the test process and the given name.
+
+    The directory is emptied when created, and removed once dropped,
+    which also happens when a test panics before it is done with it.
+*/
+#[cfg(test)]
+pub struct TestDir(PathBuf);
+
+#[cfg(test)]
+impl TestDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("roblox-ui-{name}-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).expect("failed to create test dir");
+        Self(dir)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestDir {
+    type Target = Path;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl AsRef<Path> for TestDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
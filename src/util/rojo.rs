@@ -7,6 +7,8 @@ use std::path::Path;
 
     Note that we intentionally mark any model / meta files as plain instances
     here, since we would have to read their contents to get proper class name
+
+    Binary and XML model files are later read by the instance server and expanded into real instances
 */
 pub const CLASS_NAME_SUFFIXES: &[(&str, &str)] = &[
     (".server.luau", "Script"),
@@ -23,6 +25,7 @@ pub const CLASS_NAME_SUFFIXES: &[(&str, &str)] = &[
     (".project.json", "Instance"),
     (".meta.json", "Instance"),
     (".json", "ModuleScript"),
+    (".toml", "ModuleScript"),
 ];
 
 pub fn file_name_str(path: &Path) -> Option<&str> {
@@ -48,3 +51,17 @@ pub fn parse_name_and_class_name(path: &Path) -> Option<(&str, &'static str)> {
     }
     None
 }
+
+/**
+    Checks if the given path is a Roblox place file, either binary (`.rbxl`) or XML (`.rbxlx`).
+*/
+pub fn is_roblox_place_path(path: &Path) -> bool {
+    file_name_str(path).is_some_and(|f| f.ends_with(".rbxl") || f.ends_with(".rbxlx"))
+}
+
+/**
+    Checks if the given path is a Roblox model file, either binary (`.rbxm`) or XML (`.rbxmx`).
+*/
+pub fn is_roblox_model_path(path: &Path) -> bool {
+    file_name_str(path).is_some_and(|f| f.ends_with(".rbxm") || f.ends_with(".rbxmx"))
+}
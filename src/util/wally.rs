@@ -0,0 +1,238 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+pub const WALLY_MANIFEST_FILE_NAME: &str = "wally.toml";
+pub const WALLY_LOCKFILE_FILE_NAME: &str = "wally.lock";
+
+/**
+    The realm of a Wally package, or of a dependency in a Wally manifest.
+
+    Packages are installed into a different folder for each realm,
+    `Packages`, `ServerPackages`, and `DevPackages` respectively.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WallyRealm {
+    Shared,
+    Server,
+    Dev,
+}
+
+impl WallyRealm {
+    pub const ALL: [Self; 3] = [Self::Shared, Self::Server, Self::Dev];
+
+    /**
+        Gets the name of the folder that packages for this realm are installed into.
+    */
+    pub fn packages_dir_name(self) -> &'static str {
+        match self {
+            Self::Shared => "Packages",
+            Self::Server => "ServerPackages",
+            Self::Dev => "DevPackages",
+        }
+    }
+
+    pub fn from_packages_dir_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|realm| realm.packages_dir_name() == name)
+    }
+}
+
+/**
+    A reference to a Wally package, such as `scope/name@^1.0.0` in a
+    manifest, or `scope/name@1.2.3` in a lockfile, split into its parts.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WallyPackageSpec {
+    pub scope: String,
+    pub name: String,
+    pub version: String,
+}
+
+impl WallyPackageSpec {
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (scope, rest) = spec.split_once('/')?;
+        let (name, version) = rest.split_once('@')?;
+        Some(Self {
+            scope: scope.to_owned(),
+            name: name.to_owned(),
+            version: version.to_owned(),
+        })
+    }
+
+    pub fn matches(&self, scope: &str, name: &str) -> bool {
+        self.scope.eq_ignore_ascii_case(scope) && self.name.eq_ignore_ascii_case(name)
+    }
+
+    /**
+        Checks if the given version satisfies the version requirement of this spec.
+
+        Wally uses the same requirement syntax as Cargo, where plain versions are caret
+        requirements, so this returns `None` if either version could not be parsed.
+    */
+    pub fn is_satisfied_by(&self, version: &str) -> Option<bool> {
+        let req = VersionReq::parse(&self.version).ok()?;
+        let version = Version::parse(version).ok()?;
+        Some(req.matches(&version))
+    }
+}
+
+/**
+    A Wally manifest (`wally.toml`), containing the package and its direct dependencies.
+
+    Dependencies map the alias that a dependency is installed
+    under to the spec of the package, such as `scope/name@^1.0.0`.
+*/
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WallyManifest {
+    #[serde(default)]
+    pub package: Option<WallyManifestPackage>,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+    #[serde(default)]
+    pub server_dependencies: BTreeMap<String, String>,
+    #[serde(default)]
+    pub dev_dependencies: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WallyManifestPackage {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub realm: Option<WallyRealm>,
+}
+
+impl WallyManifest {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    /**
+        Finds the direct dependency for the package with the given scope and name,
+        returning the realm of the dependency, its alias, and its spec.
+    */
+    pub fn find_dependency(
+        &self,
+        scope: &str,
+        name: &str,
+    ) -> Option<(WallyRealm, &str, WallyPackageSpec)> {
+        WallyRealm::ALL.into_iter().find_map(|realm| {
+            let dependencies = match realm {
+                WallyRealm::Shared => &self.dependencies,
+                WallyRealm::Server => &self.server_dependencies,
+                WallyRealm::Dev => &self.dev_dependencies,
+            };
+            dependencies.iter().find_map(|(alias, spec)| {
+                let spec = WallyPackageSpec::parse(spec)?;
+                spec.matches(scope, name)
+                    .then_some((realm, alias.as_str(), spec))
+            })
+        })
+    }
+}
+
+/**
+    A Wally lockfile (`wally.lock`), containing all resolved packages,
+    including the root package itself, and their resolved dependencies.
+*/
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WallyLockfile {
+    #[serde(default)]
+    pub registry: Option<String>,
+    #[serde(default, rename = "package")]
+    pub packages: Vec<WallyLockPackage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WallyLockPackage {
+    pub name: String,
+    pub version: String,
+    /// Pairs of aliases and resolved specs, such as `["Promise", "evaera/promise@4.0.0"]`.
+    #[serde(default)]
+    pub dependencies: Vec<(String, String)>,
+}
+
+impl WallyLockfile {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    /**
+        Finds all resolved versions of the package with the given scope and name.
+    */
+    pub fn find_packages<'a>(
+        &'a self,
+        scope: &'a str,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a WallyLockPackage> {
+        self.packages.iter().filter(move |package| {
+            package
+                .name
+                .split_once('/')
+                .is_some_and(|(s, n)| s.eq_ignore_ascii_case(scope) && n.eq_ignore_ascii_case(name))
+        })
+    }
+}
+
+#[test]
+fn parse_manifest_and_lockfile() {
+    let manifest: WallyManifest = toml::from_str(
+        r#"
+        [package]
+        name = "me/game"
+        version = "0.1.0"
+        realm = "shared"
+
+        [dependencies]
+        Promise = "evaera/promise@^4.0.0"
+
+        [server-dependencies]
+        ProfileService = "madstudioroblox/profileservice@1.4.2"
+        "#,
+    )
+    .unwrap();
+    let lockfile: WallyLockfile = toml::from_str(
+        r#"
+        registry = "test"
+
+        [[package]]
+        name = "evaera/promise"
+        version = "4.0.0"
+        dependencies = []
+
+        [[package]]
+        name = "me/game"
+        version = "0.1.0"
+        dependencies = [["Promise", "evaera/promise@4.0.0"]]
+        "#,
+    )
+    .unwrap();
+
+    let (realm, alias, spec) = manifest.find_dependency("evaera", "promise").unwrap();
+    assert_eq!((realm, alias), (WallyRealm::Shared, "Promise"));
+    assert_eq!(spec.is_satisfied_by("4.0.0"), Some(true));
+    assert_eq!(spec.is_satisfied_by("5.0.0"), Some(false));
+
+    let (realm, _, _) = manifest
+        .find_dependency("madstudioroblox", "profileservice")
+        .unwrap();
+    assert_eq!(realm, WallyRealm::Server);
+    assert!(manifest.find_dependency("me", "other").is_none());
+
+    let game = lockfile.find_packages("me", "game").next().unwrap();
+    assert_eq!(game.dependencies[0].0, "Promise");
+    let promise = lockfile.find_packages("evaera", "promise").next().unwrap();
+    assert_eq!(promise.version, "4.0.0");
+}